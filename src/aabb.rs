@@ -0,0 +1,175 @@
+use crate::{interval::Interval, point::Point3, ray::Ray};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Aabb {
+    pub const fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Aabb { x, y, z }
+    }
+
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        // treat the two points as extrema, in any order
+        Aabb {
+            x: Interval::new(a.x.min(b.x), a.x.max(b.x)),
+            y: Interval::new(a.y.min(b.y), a.y.max(b.y)),
+            z: Interval::new(a.z.min(b.z), a.z.max(b.z)),
+        }
+    }
+
+    pub fn surrounding(box0: &Aabb, box1: &Aabb) -> Self {
+        Aabb {
+            x: Interval::enclosing(&box0.x, &box1.x),
+            y: Interval::enclosing(&box0.y, &box1.y),
+            z: Interval::enclosing(&box0.z, &box1.z),
+        }
+    }
+
+    // Widens any side thinner than `delta` so that flat primitives, whose box
+    // has zero extent along one axis, are still hit by the slab test.
+    pub fn pad_to_minimums(&self, delta: f64) -> Self {
+        let pad = |interval: Interval| {
+            if interval.size() < delta {
+                interval.expand(delta)
+            } else {
+                interval
+            }
+        };
+        Aabb {
+            x: pad(self.x),
+            y: pad(self.y),
+            z: pad(self.z),
+        }
+    }
+
+    pub fn axis_interval(&self, n: usize) -> &Interval {
+        match n {
+            1 => &self.y,
+            2 => &self.z,
+            _ => &self.x,
+        }
+    }
+
+    pub fn centroid(&self, axis: usize) -> f64 {
+        let interval = self.axis_interval(axis);
+        0.5 * (interval.min + interval.max)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        if dx < 0.0 || dy < 0.0 || dz < 0.0 {
+            return 0.0;
+        }
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> bool {
+        let ray_orig = r.origin();
+        let ray_dir = r.direction();
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let adinv = 1.0 / ray_dir[axis];
+
+            let t0 = (ax.min - ray_orig[axis]) * adinv;
+            let t1 = (ax.max - ray_orig[axis]) * adinv;
+
+            if t0 < t1 {
+                if t0 > ray_t.min {
+                    ray_t.min = t0;
+                }
+                if t1 < ray_t.max {
+                    ray_t.max = t1;
+                }
+            } else {
+                if t1 > ray_t.min {
+                    ray_t.min = t1;
+                }
+                if t0 < ray_t.max {
+                    ray_t.max = t0;
+                }
+            }
+
+            if ray_t.max <= ray_t.min {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub const EMPTY: Self = Aabb::new(Interval::EMPTY, Interval::EMPTY, Interval::EMPTY);
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vector3;
+
+    #[test]
+    fn test_aabb_from_points_orders_extrema() {
+        let bbox = Aabb::from_points(Point3::new(1.0, -1.0, 3.0), Point3::new(-1.0, 2.0, 0.0));
+
+        assert_eq!((bbox.x.min, bbox.x.max), (-1.0, 1.0));
+        assert_eq!((bbox.y.min, bbox.y.max), (-1.0, 2.0));
+        assert_eq!((bbox.z.min, bbox.z.max), (0.0, 3.0));
+    }
+
+    #[test]
+    fn test_aabb_pad_to_minimums() {
+        let flat = Aabb::from_points(Point3::new(-1.0, -1.0, 2.0), Point3::new(1.0, 1.0, 2.0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let padded = flat.pad_to_minimums(0.0001);
+
+        assert!(!flat.hit(&ray, Interval::new(0.0, f64::INFINITY)));
+        assert!(padded.hit(&ray, Interval::new(0.0, f64::INFINITY)));
+        assert_eq!((padded.x.min, padded.x.max), (-1.0, 1.0));
+        assert!((padded.z.size() - 0.0001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_aabb_surrounding() {
+        let a = Aabb::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let b = Aabb::from_points(Point3::new(2.0, -1.0, 0.5), Point3::new(3.0, 0.0, 0.75));
+
+        let bbox = Aabb::surrounding(&a, &b);
+
+        assert_eq!((bbox.x.min, bbox.x.max), (0.0, 3.0));
+        assert_eq!((bbox.y.min, bbox.y.max), (-1.0, 1.0));
+        assert_eq!((bbox.z.min, bbox.z.max), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_aabb_surface_area() {
+        let bbox = Aabb::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(bbox.surface_area(), 22.0);
+        assert_eq!(Aabb::EMPTY.surface_area(), 0.0);
+    }
+
+    #[test]
+    fn test_aabb_hit() {
+        let bbox = Aabb::from_points(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(bbox.hit(&ray, Interval::new(0.0, f64::INFINITY)));
+        assert!(!bbox.hit(&ray, Interval::new(0.0, 3.0)));
+    }
+
+    #[test]
+    fn test_aabb_miss() {
+        let bbox = Aabb::from_points(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert!(!bbox.hit(&ray, Interval::new(0.0, f64::INFINITY)));
+    }
+}
@@ -0,0 +1,303 @@
+use std::cmp::Ordering;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    interval::Interval,
+    point::Point3,
+    random::{self, Sampler},
+    ray::Ray,
+    vector::Vector3,
+};
+
+pub struct BvhNode {
+    left: Box<dyn Hittable>,
+    right: Option<Box<dyn Hittable>>,
+    bbox: Aabb,
+    // which of the children light sampling picks from
+    samplable: (bool, bool),
+}
+
+impl BvhNode {
+    pub fn new(list: HittableList) -> Self {
+        let mut objects = list.into_objects();
+        match objects.len() {
+            0 => BvhNode {
+                left: Box::new(HittableList::new()),
+                right: None,
+                bbox: Aabb::EMPTY,
+                samplable: (false, false),
+            },
+            1 => {
+                let left = objects.remove(0);
+                let bbox = left.bounding_box();
+                let samplable = (left.is_samplable(), false);
+                BvhNode {
+                    left,
+                    right: None,
+                    bbox,
+                    samplable,
+                }
+            }
+            _ => BvhNode::build(objects),
+        }
+    }
+
+    fn build(mut objects: Vec<Box<dyn Hittable>>) -> Self {
+        let (axis, split) = BvhNode::sah_split(&mut objects);
+        sort_by_centroid(&mut objects, axis);
+
+        let right_objects = objects.split_off(split);
+        let left = BvhNode::subtree(objects);
+        let right = BvhNode::subtree(right_objects);
+        let bbox = Aabb::surrounding(&left.bounding_box(), &right.bounding_box());
+        let samplable = (left.is_samplable(), right.is_samplable());
+
+        BvhNode {
+            left,
+            right: Some(right),
+            bbox,
+            samplable,
+        }
+    }
+
+    fn subtree(mut objects: Vec<Box<dyn Hittable>>) -> Box<dyn Hittable> {
+        if objects.len() == 1 {
+            objects.remove(0)
+        } else {
+            Box::new(BvhNode::build(objects))
+        }
+    }
+
+    // Picks the axis and split position minimising the surface area heuristic
+    // cost `SA(left) * N(left) + SA(right) * N(right)`. The split is returned
+    // as the number of objects that go to the left child once sorted along the
+    // chosen axis.
+    fn sah_split(objects: &mut [Box<dyn Hittable>]) -> (usize, usize) {
+        let n = objects.len();
+        let mut best = (0, n / 2);
+        let mut best_cost = f64::INFINITY;
+
+        for axis in 0..3 {
+            sort_by_centroid(objects, axis);
+
+            let mut right_areas = vec![0.0; n];
+            let mut right_box = Aabb::EMPTY;
+            for i in (1..n).rev() {
+                right_box = Aabb::surrounding(&right_box, &objects[i].bounding_box());
+                right_areas[i] = right_box.surface_area();
+            }
+
+            let mut left_box = Aabb::EMPTY;
+            for i in 0..n - 1 {
+                left_box = Aabb::surrounding(&left_box, &objects[i].bounding_box());
+                let left_count = (i + 1) as f64;
+                let right_count = (n - i - 1) as f64;
+                let cost = left_box.surface_area() * left_count + right_areas[i + 1] * right_count;
+                if cost < best_cost {
+                    best_cost = cost;
+                    best = (axis, i + 1);
+                }
+            }
+        }
+
+        best
+    }
+}
+
+fn sort_by_centroid(objects: &mut [Box<dyn Hittable>], axis: usize) {
+    objects.sort_by(|a, b| {
+        let a = a.bounding_box().centroid(axis);
+        let b = b.bounding_box().centroid(axis);
+        a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+    });
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+        if !self.bbox.hit(r, int) {
+            return false;
+        }
+
+        let hit_left = self.left.hit(r, int, rec);
+        let hit_right = match &self.right {
+            Some(right) => {
+                let max = if hit_left { rec.t } else { int.max };
+                right.hit(r, Interval::new(int.min, max), rec)
+            }
+            None => false,
+        };
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_samplable(&self) -> bool {
+        self.samplable.0 || self.samplable.1
+    }
+
+    // Sampling picks either samplable child with equal probability, so the
+    // density is the average of theirs.
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3) -> f64 {
+        let right = || match &self.right {
+            Some(right) => right.pdf_value(origin, direction),
+            None => 0.0,
+        };
+        match self.samplable {
+            (true, true) => 0.5 * (self.left.pdf_value(origin, direction) + right()),
+            (true, false) => self.left.pdf_value(origin, direction),
+            (false, true) => right(),
+            (false, false) => 0.0,
+        }
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut Sampler) -> Vector3 {
+        match (&self.right, self.samplable) {
+            (Some(right), (true, true)) if random::random_double(rng) < 0.5 => {
+                right.random(origin, rng)
+            }
+            (_, (true, _)) => self.left.random(origin, rng),
+            (Some(right), (false, true)) => right.random(origin, rng),
+            _ => Vector3::new(1.0, 0.0, 0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{color::Color, material::Lambertian, sphere::Sphere};
+
+    fn random_scene(rng: &mut StdRng, count: usize) -> HittableList {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        for _ in 0..count {
+            let center = Point3::new(
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+            );
+            world.add(Box::new(Sphere::new(
+                center,
+                rng.gen_range(0.05..1.5),
+                mat.clone(),
+            )));
+        }
+        world
+    }
+
+    fn random_ray(rng: &mut StdRng) -> Ray {
+        let origin = Point3::new(
+            rng.gen_range(-15.0..15.0),
+            rng.gen_range(-15.0..15.0),
+            rng.gen_range(-15.0..15.0),
+        );
+        let target = Point3::new(
+            rng.gen_range(-5.0..5.0),
+            rng.gen_range(-5.0..5.0),
+            rng.gen_range(-5.0..5.0),
+        );
+        Ray::new(origin, target - origin)
+    }
+
+    #[test]
+    fn test_bvh_matches_linear_list() {
+        for seed in 0..8 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let count = rng.gen_range(2..300);
+            let list = random_scene(&mut StdRng::seed_from_u64(seed), count);
+            let bvh = BvhNode::new(random_scene(&mut StdRng::seed_from_u64(seed), count));
+
+            for _ in 0..500 {
+                let ray = random_ray(&mut rng);
+                let int = Interval::new(0.001, f64::INFINITY);
+                let mut list_rec = HitRecord::new();
+                let mut bvh_rec = HitRecord::new();
+
+                let list_hit = list.hit(&ray, int, &mut list_rec);
+                let bvh_hit = bvh.hit(&ray, int, &mut bvh_rec);
+
+                assert_eq!(list_hit, bvh_hit);
+                if list_hit {
+                    assert_eq!(list_rec.t, bvh_rec.t);
+                    assert_eq!(list_rec.p, bvh_rec.p);
+                    assert_eq!(list_rec.normal, bvh_rec.normal);
+                    assert_eq!(list_rec.front_face, bvh_rec.front_face);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bvh_bounding_box_encloses_list() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let list = random_scene(&mut rng, 50);
+        let expected = list.bounding_box();
+
+        let bvh = BvhNode::new(list);
+        let bbox = bvh.bounding_box();
+
+        assert_eq!((bbox.x.min, bbox.x.max), (expected.x.min, expected.x.max));
+        assert_eq!((bbox.y.min, bbox.y.max), (expected.y.min, expected.y.max));
+        assert_eq!((bbox.z.min, bbox.z.max), (expected.z.min, expected.z.max));
+    }
+
+    #[test]
+    fn test_bvh_empty_and_single() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let int = Interval::new(0.001, f64::INFINITY);
+        let mut rec = HitRecord::new();
+
+        let empty = BvhNode::new(HittableList::new());
+        assert!(!empty.hit(&ray, int, &mut rec));
+
+        let mut list = HittableList::new();
+        list.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        )));
+        let single = BvhNode::new(list);
+        assert!(single.hit(&ray, int, &mut rec));
+        assert!((rec.t - 4.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_light_sampling_skips_unsamplable_objects() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let light = || Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, mat.clone());
+        let lights = || {
+            let mut list = HittableList::new();
+            list.add(Box::new(HittableList::new()));
+            list.add(Box::new(light()));
+            list.add(Box::new(HittableList::new()));
+            list
+        };
+        let origin = Point3::new(0.0, 0.0, 5.0);
+        let toward = Vector3::new(0.0, 0.0, -1.0);
+        let expected = light().pdf_value(&origin, &toward);
+        let bvh = BvhNode::new(lights());
+
+        assert!(expected > 0.0);
+        assert_eq!(lights().pdf_value(&origin, &toward), expected);
+        assert!(bvh.is_samplable());
+        assert_eq!(bvh.pdf_value(&origin, &toward), expected);
+        assert!(!BvhNode::new(HittableList::new()).is_samplable());
+
+        let mut rng = random::sampler(3, 0, 0);
+        for _ in 0..100 {
+            let direction = bvh.random(&origin, &mut rng);
+            let mut rec = HitRecord::new();
+            let ray = Ray::new(origin, direction);
+            assert!(light().hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec));
+        }
+    }
+}
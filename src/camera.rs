@@ -1,111 +1,901 @@
+use std::{
+    fmt, io,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
 use rand::Rng;
-use std::{fs::File, io::Write};
 
 use crate::{
-    color::Color, hittable_list::HittableList, point::Point3, ray::Ray, render::render_pixel,
+    color::Color,
+    environment::EnvironmentMap,
+    hittable::Hittable,
+    integrator::Integrator,
+    point::Point3,
+    progress::{self, Counters, ProgressObserver, RenderStats},
+    random::{self, Sampler},
+    ray::Ray,
+    render::{write_image, write_plain_image, Framebuffer, TransferFunction},
+    sky::PhysicalSky,
     vector::Vector3,
 };
 
+const TILE_SIZE: i32 = 32;
+
+// What rays that leave the scene see.
+#[derive(Debug, Clone, Default)]
+pub enum Background {
+    // the white to blue gradient of the book
+    #[default]
+    Sky,
+    Solid(Color),
+    // an image around the scene, which also lights it
+    Environment(Arc<EnvironmentMap>),
+    // daylight from a sky model, with the sun in it
+    PhysicalSky(Arc<PhysicalSky>),
+}
+
+impl PartialEq for Background {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Background::Sky, Background::Sky) => true,
+            (Background::Solid(a), Background::Solid(b)) => a == b,
+            (Background::Environment(a), Background::Environment(b)) => Arc::ptr_eq(a, b),
+            (Background::PhysicalSky(a), Background::PhysicalSky(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Background {
+    pub const BLACK: Background = Background::Solid(Color {
+        red: 0.0,
+        green: 0.0,
+        blue: 0.0,
+    });
+
+    pub fn color(&self, r: &Ray) -> Color {
+        match self {
+            Background::Sky => {
+                let unit_direction = Vector3::unit(r.direction());
+                let t = 0.5 * (unit_direction.y + 1.0);
+                Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+            }
+            Background::Solid(color) => *color,
+            Background::Environment(map) => map.color(r.direction()),
+            Background::PhysicalSky(sky) => sky.color(r.direction()),
+        }
+    }
+
+    // Density per unit solid angle with which `random` picks `direction`,
+    // zero for backgrounds too dim or even to be worth sampling.
+    pub fn pdf_value(&self, direction: &Vector3) -> f64 {
+        match self {
+            Background::Environment(map) => map.pdf_value(direction),
+            Background::PhysicalSky(sky) => sky.pdf_value(direction),
+            _ => 0.0,
+        }
+    }
+
+    // A direction towards the bright parts of the background for light
+    // sampling, None if it has none.
+    pub fn random(&self, rng: &mut Sampler) -> Option<Vector3> {
+        match self {
+            Background::Environment(map) => Some(map.random(rng)),
+            Background::PhysicalSky(sky) => sky.random(rng),
+            _ => None,
+        }
+    }
+}
+
 pub struct Camera {
     aspect_ratio: f64,
     image_width: i32,
     image_height: i32,
     samples_per_pixel: i32,
+    max_depth: i32,
+    min_depth: i32,
+    vfov: f64,
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vector3,
+    defocus_angle: f64,
+    focus_dist: f64,
+    transfer: TransferFunction,
+    plain_ppm: bool,
+    background: Background,
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vector3,
     pixel_delta_v: Vector3,
     pixel_samples_scale: f64,
+    u: Vector3,
+    v: Vector3,
+    w: Vector3,
+    defocus_disk_u: Vector3,
+    defocus_disk_v: Vector3,
+    threads: usize,
+    seed: Option<u64>,
+    progress: Option<Arc<dyn ProgressObserver>>,
+}
+
+// Why a camera could not be built: without a view direction and an up
+// direction that is not along it, the image plane has no orientation and
+// every pixel would come out NaN. An image wider or taller than
+// `Camera::MAX_IMAGE_SIZE` would not fit in memory anyway.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraError {
+    LookfromIsLookat,
+    VupAlongView,
+    ImageTooLarge,
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::LookfromIsLookat => write!(f, "lookfrom and lookat are the same point"),
+            CameraError::VupAlongView => {
+                write!(f, "vup is zero or parallel to the view direction")
+            }
+            CameraError::ImageTooLarge => write!(
+                f,
+                "image is larger than {} pixels on a side",
+                Camera::MAX_IMAGE_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
+pub struct CameraBuilder {
+    aspect_ratio: f64,
+    image_width: i32,
+    samples_per_pixel: i32,
     max_depth: i32,
+    min_depth: i32,
+    vfov: f64,
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vector3,
+    defocus_angle: f64,
+    focus_dist: Option<f64>,
+    transfer: TransferFunction,
+    plain_ppm: bool,
+    background: Background,
+    threads: usize,
+    seed: Option<u64>,
+    progress: Option<Arc<dyn ProgressObserver>>,
 }
 
-impl Camera {
-    pub fn new(
-        aspect_ratio: f64,
-        image_width: i32,
-        samples_per_pixel: i32,
-        max_depth: i32,
-    ) -> Self {
+// Finished tiles by their index, with their pixels row by row.
+type RenderedTiles = Vec<(usize, Vec<Color>)>;
+
+struct Tile {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl CameraBuilder {
+    pub fn new() -> Self {
+        CameraBuilder {
+            aspect_ratio: 1.0,
+            image_width: 100,
+            samples_per_pixel: 10,
+            max_depth: 10,
+            min_depth: Integrator::DEFAULT_MIN_DEPTH,
+            vfov: 90.0,
+            lookfrom: Point3::new(0.0, 0.0, 0.0),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vector3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: None,
+            transfer: TransferFunction::default(),
+            plain_ppm: false,
+            background: Background::default(),
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: None,
+            progress: None,
+        }
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn image_width(mut self, image_width: i32) -> Self {
+        self.image_width = image_width;
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: i32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: i32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // Bounces before Russian roulette may end a path.
+    pub fn min_depth(mut self, min_depth: i32) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    // Vertical field of view, in degrees.
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    // Point the camera is looking from.
+    pub fn lookfrom(mut self, lookfrom: Point3) -> Self {
+        self.lookfrom = lookfrom;
+        self
+    }
+
+    // Point the camera is looking at.
+    pub fn lookat(mut self, lookat: Point3) -> Self {
+        self.lookat = lookat;
+        self
+    }
+
+    // Camera-relative "up" direction.
+    pub fn vup(mut self, vup: Vector3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    // Variation angle of rays through each pixel, in degrees. Zero keeps a
+    // pinhole camera.
+    pub fn defocus_angle(mut self, defocus_angle: f64) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    // Distance from `lookfrom` to the plane of perfect focus. Defaults to the
+    // distance between `lookfrom` and `lookat`.
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = Some(focus_dist);
+        self
+    }
+
+    // Encoding applied to linear radiance before quantizing to 8 bits.
+    pub fn transfer_function(mut self, transfer: TransferFunction) -> Self {
+        self.transfer = transfer;
+        self
+    }
+
+    // Makes `render` write .ppm and .pnm paths as plain (P3) text rather than
+    // binary P6.
+    pub fn plain_ppm(mut self, plain_ppm: bool) -> Self {
+        self.plain_ppm = plain_ppm;
+        self
+    }
+
+    // Radiance of rays that miss everything; scenes lit only by emissive
+    // materials want `Background::BLACK`.
+    pub fn background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    // Number of worker threads used by `render`, at least one.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    // Derives every pixel and sample random stream from `seed`, making the
+    // output reproducible and independent of the number of threads. Without
+    // a seed a fresh one is drawn for each render.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    // Told about every finished tile and, at the end, the statistics of the
+    // render. `StderrProgress` shows them on the terminal.
+    pub fn progress(mut self, observer: Arc<dyn ProgressObserver>) -> Self {
+        self.progress = Some(observer);
+        self
+    }
+
+    // Height of the image, derived from its width and aspect ratio.
+    pub fn image_height(&self) -> i32 {
+        image_height(self.image_width, self.aspect_ratio)
+    }
+
+    // Checks that the view has an orientation and the image a size that
+    // `build` can handle. Scene loaders call it to report a bad camera where
+    // it was written down.
+    pub fn validate(&self) -> Result<(), CameraError> {
+        let (width, height) = (self.image_width, self.image_height());
+        if width > Camera::MAX_IMAGE_SIZE
+            || height > Camera::MAX_IMAGE_SIZE
+            || (width.max(0) as usize)
+                .checked_mul(height as usize)
+                .is_none()
+        {
+            return Err(CameraError::ImageTooLarge);
+        }
+        let view = self.lookat - self.lookfrom;
+        if view.near_zero() || !view.length_squared().is_finite() {
+            return Err(CameraError::LookfromIsLookat);
+        }
+        let sin_squared = Vector3::cross(
+            &Vector3::unit_vector(&self.vup),
+            &Vector3::unit_vector(&view),
+        )
+        .length_squared();
+        if sin_squared.is_nan() || sin_squared < 1e-16 {
+            return Err(CameraError::VupAlongView);
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Camera, CameraError> {
+        self.validate()?;
         let mut camera = Camera {
-            aspect_ratio,
-            image_width,
+            aspect_ratio: self.aspect_ratio,
+            image_width: self.image_width,
             image_height: 0,
-            samples_per_pixel,
+            samples_per_pixel: self.samples_per_pixel,
+            max_depth: self.max_depth,
+            min_depth: self.min_depth,
+            vfov: self.vfov,
+            lookfrom: self.lookfrom,
+            lookat: self.lookat,
+            vup: self.vup,
+            defocus_angle: self.defocus_angle,
+            focus_dist: self
+                .focus_dist
+                .unwrap_or_else(|| (self.lookfrom - self.lookat).length()),
+            transfer: self.transfer,
+            plain_ppm: self.plain_ppm,
+            background: self.background,
             center: Point3::new(0.0, 0.0, 0.0),
             pixel00_loc: Point3::new(0.0, 0.0, 0.0),
             pixel_delta_u: Vector3::new(0.0, 0.0, 0.0),
             pixel_delta_v: Vector3::new(0.0, 0.0, 0.0),
             pixel_samples_scale: 0.0,
-            max_depth,
+            u: Vector3::new(0.0, 0.0, 0.0),
+            v: Vector3::new(0.0, 0.0, 0.0),
+            w: Vector3::new(0.0, 0.0, 0.0),
+            defocus_disk_u: Vector3::new(0.0, 0.0, 0.0),
+            defocus_disk_v: Vector3::new(0.0, 0.0, 0.0),
+            threads: self.threads,
+            seed: self.seed,
+            progress: self.progress,
         };
         camera.initialize();
-        camera
+        Ok(camera)
+    }
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The image height for `image_width` pixels across, at least one pixel.
+fn image_height(image_width: i32, aspect_ratio: f64) -> i32 {
+    ((image_width as f64 / aspect_ratio) as i32).max(1)
+}
+
+impl Camera {
+    pub const MAX_IMAGE_SIZE: i32 = 1 << 16;
+
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder::new()
+    }
+
+    pub fn image_width(&self) -> usize {
+        self.image_width as usize
+    }
+
+    pub fn image_height(&self) -> usize {
+        self.image_height as usize
+    }
+
+    // Used to encode the output of `render_to_buffer` for display.
+    pub fn transfer_function(&self) -> TransferFunction {
+        self.transfer
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.background
     }
 
     fn initialize(&mut self) {
-        self.image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
-        self.image_height = if self.image_height < 1 {
-            1
-        } else {
-            self.image_height
-        };
+        self.image_height = image_height(self.image_width, self.aspect_ratio);
 
         self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
 
-        let viewport_height = 2.0;
-        let viewport_width = self.aspect_ratio * viewport_height;
-        let focal_length = 1.0;
+        self.center = self.lookfrom;
+
+        // determine viewport dimensions
+        let theta = self.vfov.to_radians();
+        let h = (theta / 2.0).tan();
+        let viewport_height = 2.0 * h * self.focus_dist;
+        let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
+
+        // calculate the u, v, w unit basis vectors for the camera coordinate frame
+        self.w = Vector3::unit(&(self.lookfrom - self.lookat));
+        self.u = Vector3::unit(&Vector3::cross(&self.vup, &self.w));
+        self.v = Vector3::cross(&self.w, &self.u);
 
-        self.center = Point3::new(0.0, 0.0, 0.0);
-        let viewport_u = Vector3::new(viewport_width, 0.0, 0.0);
-        let viewport_v = Vector3::new(0.0, -viewport_height, 0.0);
+        // vectors across the horizontal and down the vertical viewport edges
+        let viewport_u = viewport_width * self.u;
+        let viewport_v = viewport_height * -self.v;
 
         self.pixel_delta_u = viewport_u / self.image_width as f64;
         self.pixel_delta_v = viewport_v / self.image_height as f64;
 
-        let viewport_upper_left = self.center
-            - Vector3::new(0.0, 0.0, focal_length)
-            - viewport_u / 2.0
-            - viewport_v / 2.0;
+        let viewport_upper_left =
+            self.center - (self.focus_dist * self.w) - viewport_u / 2.0 - viewport_v / 2.0;
 
         self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+
+        // calculate the camera defocus disk basis vectors
+        let defocus_radius = self.focus_dist * (self.defocus_angle / 2.0).to_radians().tan();
+        self.defocus_disk_u = self.u * defocus_radius;
+        self.defocus_disk_v = self.v * defocus_radius;
     }
 
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        let offset = self.sample_square();
+    fn get_ray(&self, i: i32, j: i32, rng: &mut Sampler) -> Ray {
+        let offset = self.sample_square(rng);
         let pixel_sample = self.pixel00_loc
             + (i as f64 + offset.x) * self.pixel_delta_u
             + (j as f64 + offset.y) * self.pixel_delta_v;
 
-        let ray_origin = self.center;
+        let ray_origin = if self.defocus_angle <= 0.0 {
+            self.center
+        } else {
+            self.defocus_disk_sample(rng)
+        };
         let ray_direction = pixel_sample - ray_origin;
 
         Ray::new(ray_origin, ray_direction)
     }
 
-    fn sample_square(&self) -> Vector3 {
-        let mut rng = rand::thread_rng();
-        Vector3::new(rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5, 0.0)
+    fn sample_square(&self, rng: &mut Sampler) -> Vector3 {
+        Vector3::new(
+            random::random_double(rng) - 0.5,
+            random::random_double(rng) - 0.5,
+            0.0,
+        )
+    }
+
+    fn defocus_disk_sample(&self, rng: &mut Sampler) -> Point3 {
+        // returns a random point in the camera defocus disk
+        let p = Vector3::random_in_unit_disk(rng);
+        self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
+    }
+
+    fn tiles(&self) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        for y0 in (0..self.image_height).step_by(TILE_SIZE as usize) {
+            for x0 in (0..self.image_width).step_by(TILE_SIZE as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: x0.saturating_add(TILE_SIZE).min(self.image_width),
+                    y1: y0.saturating_add(TILE_SIZE).min(self.image_height),
+                });
+            }
+        }
+        tiles
     }
 
-    pub fn render(&self, world: &HittableList) {
-        let mut image_buffer: Vec<String> = vec![format!(
-            "P3\n{} {}\n255\n",
-            self.image_width, self.image_height
-        )];
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        seed: u64,
+    ) -> Vec<Color> {
+        let integrator = Integrator::new(world, lights, &self.background, self.max_depth)
+            .min_depth(self.min_depth);
+        let mut pixels = Vec::with_capacity(((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as usize);
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                let pixel = j as u64 * self.image_width as u64 + i as u64;
 
-        for j in 0..self.image_height {
-            for i in 0..self.image_width {
                 let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color = pixel_color + r.color(self.max_depth, world);
+                for sample in 0..self.samples_per_pixel {
+                    let mut rng = random::sampler(seed, pixel, sample as u64);
+                    let r = self.get_ray(i, j, &mut rng);
+                    pixel_color = pixel_color + integrator.radiance(&r, &mut rng);
                 }
-                render_pixel(&mut image_buffer, pixel_color * self.pixel_samples_scale);
+                pixels.push(pixel_color * self.pixel_samples_scale);
             }
         }
+        pixels
+    }
+
+    // Renders the world into linear radiance without writing any file.
+    // `lights` holds copies of the emitters worth sampling directly; it may
+    // be empty.
+    pub fn render_to_buffer(&self, world: &dyn Hittable, lights: &dyn Hittable) -> Framebuffer {
+        self.render_with_stats(world, lights).0
+    }
+
+    // Like `render_to_buffer`, also returning what the render cost.
+    pub fn render_with_stats(
+        &self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+    ) -> (Framebuffer, RenderStats) {
+        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let tiles = self.tiles();
+        let next_tile = AtomicUsize::new(0);
+        let finished_tiles = AtomicUsize::new(0);
+        let start = Instant::now();
+
+        let (rendered, counters): (Vec<RenderedTiles>, Vec<Counters>) = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads.min(tiles.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let before = progress::counters();
+                        let mut done = Vec::new();
+                        loop {
+                            let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                            let Some(tile) = tiles.get(index) else {
+                                break;
+                            };
+                            done.push((index, self.render_tile(tile, world, lights, seed)));
+                            if let Some(observer) = &self.progress {
+                                let finished = finished_tiles.fetch_add(1, Ordering::Relaxed);
+                                observer.tile_finished(finished + 1, tiles.len(), start.elapsed());
+                            }
+                        }
+                        (done, progress::counters() - before)
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .unzip()
+        });
+
+        let pixel_count = self.image_width as u64 * self.image_height as u64;
+        let stats = RenderStats {
+            tiles: tiles.len(),
+            primary_rays: pixel_count * self.samples_per_pixel as u64,
+            total_rays: counters.iter().map(|c| c.rays).sum(),
+            intersection_tests: counters.iter().map(|c| c.intersection_tests).sum(),
+            elapsed: start.elapsed(),
+        };
+        if let Some(observer) = &self.progress {
+            observer.render_finished(&stats);
+        }
+
+        let mut framebuffer =
+            Framebuffer::new(self.image_width as usize, self.image_height as usize);
+        for (index, pixels) in rendered.into_iter().flatten() {
+            let tile = &tiles[index];
+            let tile_width = (tile.x1 - tile.x0) as usize;
+            for (n, pixel) in pixels.into_iter().enumerate() {
+                let x = tile.x0 as usize + n % tile_width;
+                let y = tile.y0 as usize + n / tile_width;
+                framebuffer.set(x, y, pixel);
+            }
+        }
+        (framebuffer, stats)
+    }
+
+    pub fn render(
+        &self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        path: &Path,
+    ) -> io::Result<()> {
+        let framebuffer = self.render_to_buffer(world, lights);
+        if self.plain_ppm {
+            write_plain_image(path, &framebuffer, self.transfer)
+        } else {
+            write_image(path, &framebuffer, self.transfer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{
+        hittable_list::HittableList,
+        material::{Dielectric, Lambertian, Metal},
+        sphere::Sphere,
+    };
+
+    fn scene() -> HittableList {
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0))),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point3::new(-0.6, 0.0, -1.0),
+            0.5,
+            Arc::new(Dielectric::new(1.5)),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.6, 0.0, -1.0),
+            0.5,
+            Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3)),
+        )));
+        world
+    }
+
+    fn test_camera(image_width: i32, samples_per_pixel: i32) -> CameraBuilder {
+        Camera::builder()
+            .aspect_ratio(16.0 / 9.0)
+            .image_width(image_width)
+            .samples_per_pixel(samples_per_pixel)
+            .max_depth(5)
+    }
+
+    fn bits(framebuffer: &Framebuffer) -> Vec<[u64; 3]> {
+        framebuffer
+            .pixels
+            .iter()
+            .map(|c| [c.red.to_bits(), c.green.to_bits(), c.blue.to_bits()])
+            .collect()
+    }
+
+    #[test]
+    fn test_default_camera_looks_down_negative_z() {
+        let camera = Camera::builder()
+            .aspect_ratio(2.0)
+            .image_width(200)
+            .build()
+            .unwrap();
+
+        assert_eq!(camera.u, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(camera.v, Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(camera.w, Vector3::new(0.0, 0.0, 1.0));
+        // a 90 degree field of view at focal length 1 spans a 2 unit viewport
+        assert!((camera.pixel_delta_v.y * camera.image_height as f64 + 2.0).abs() < 1e-12);
+        assert!((camera.pixel_delta_u.x * camera.image_width as f64 - 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_positioned_camera_basis_is_orthonormal() {
+        let camera = Camera::builder()
+            .lookfrom(Point3::new(-2.0, 2.0, 1.0))
+            .lookat(Point3::new(0.0, 0.0, -1.0))
+            .vup(Vector3::new(0.0, 1.0, 0.0))
+            .vfov(20.0)
+            .build()
+            .unwrap();
+
+        for (a, b) in [
+            (camera.u, camera.v),
+            (camera.v, camera.w),
+            (camera.w, camera.u),
+        ] {
+            assert!(Vector3::dot(&a, &b).abs() < 1e-12);
+        }
+        for axis in [camera.u, camera.v, camera.w] {
+            assert!((axis.length() - 1.0).abs() < 1e-12);
+        }
+        assert_eq!(camera.center, Point3::new(-2.0, 2.0, 1.0));
+        let forward = Vector3::unit(&(Point3::new(0.0, 0.0, -1.0) - camera.center));
+        assert!((forward + camera.w).near_zero());
+    }
+
+    #[test]
+    fn test_degenerate_view_is_an_error() {
+        let at = Point3::new(1.0, 2.0, 3.0);
+        assert_eq!(
+            Camera::builder().lookfrom(at).lookat(at).build().err(),
+            Some(CameraError::LookfromIsLookat)
+        );
+        for vup in [Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 2.0)] {
+            assert_eq!(
+                Camera::builder().vup(vup).build().err(),
+                Some(CameraError::VupAlongView)
+            );
+        }
+    }
+
+    #[test]
+    fn test_oversized_image_is_an_error() {
+        let builder = Camera::builder().image_width(Camera::MAX_IMAGE_SIZE);
+        assert_eq!(builder.image_height(), Camera::MAX_IMAGE_SIZE);
+        assert!(builder.validate().is_ok());
+
+        assert_eq!(
+            Camera::builder().image_width(i32::MAX).build().err(),
+            Some(CameraError::ImageTooLarge)
+        );
+        assert_eq!(
+            Camera::builder()
+                .image_width(400)
+                .aspect_ratio(1e-9)
+                .build()
+                .err(),
+            Some(CameraError::ImageTooLarge)
+        );
+    }
+
+    #[test]
+    fn test_center_pixel_ray_points_at_lookat() {
+        let lookat = Point3::new(1.0, 0.5, -3.0);
+        let camera = Camera::builder()
+            .aspect_ratio(1.0)
+            .image_width(101)
+            .lookfrom(Point3::new(4.0, 3.0, 2.0))
+            .lookat(lookat)
+            .vfov(40.0)
+            .build()
+            .unwrap();
+
+        let pixel_center =
+            camera.pixel00_loc + 50.0 * camera.pixel_delta_u + 50.0 * camera.pixel_delta_v;
+
+        assert!((pixel_center - lookat).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_zero_defocus_angle_is_a_pinhole() {
+        let world = scene();
+        let pinhole = test_camera(40, 2).seed(5).build().unwrap();
+        let zero_angle = test_camera(40, 2)
+            .seed(5)
+            .defocus_angle(0.0)
+            .focus_dist(1.0)
+            .build()
+            .unwrap();
+
+        let mut rng = random::sampler(0, 0, 0);
+        assert_eq!(
+            *zero_angle.get_ray(3, 4, &mut rng).origin(),
+            zero_angle.center
+        );
+        assert_eq!(
+            bits(&pinhole.render_to_buffer(&world, &HittableList::new())),
+            bits(&zero_angle.render_to_buffer(&world, &HittableList::new()))
+        );
+    }
+
+    #[test]
+    fn test_defocus_ray_origins_lie_on_disk() {
+        let camera = test_camera(40, 1)
+            .defocus_angle(10.0)
+            .focus_dist(3.4)
+            .build()
+            .unwrap();
+        let radius = 3.4 * 5f64.to_radians().tan();
+
+        let mut rng = random::sampler(1, 0, 0);
+        for _ in 0..100 {
+            let ray = camera.get_ray(20, 11, &mut rng);
+            let offset = *ray.origin() - camera.center;
+            assert!(offset.length() <= radius + 1e-12);
+            assert!(Vector3::dot(&offset, &camera.w).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_tiles_cover_image() {
+        let camera = test_camera(100, 1).build().unwrap();
+        let covered: i32 = camera
+            .tiles()
+            .iter()
+            .map(|t| (t.x1 - t.x0) * (t.y1 - t.y0))
+            .sum();
+
+        assert_eq!(covered, 100 * 56);
+    }
+
+    #[test]
+    fn test_seeded_render_is_independent_of_thread_count() {
+        let world = scene();
+        let single = test_camera(70, 4).seed(1234).threads(1).build().unwrap();
+        let multi = test_camera(70, 4).seed(1234).threads(4).build().unwrap();
+        let single = single.render_to_buffer(&world, &HittableList::new());
+        let multi = multi.render_to_buffer(&world, &HittableList::new());
+
+        assert_eq!(single.pixels.len(), 70 * 39);
+        assert_eq!(bits(&single), bits(&multi));
+    }
+
+    #[test]
+    fn test_seeded_render_is_reproducible() {
+        let world = scene();
+        let a = test_camera(40, 3)
+            .seed(99)
+            .build()
+            .unwrap()
+            .render_to_buffer(&world, &HittableList::new());
+        let b = test_camera(40, 3)
+            .seed(99)
+            .build()
+            .unwrap()
+            .render_to_buffer(&world, &HittableList::new());
+
+        assert_eq!(bits(&a), bits(&b));
+    }
+
+    #[test]
+    fn test_different_seeds_differ() {
+        let world = scene();
+        let a = test_camera(40, 2)
+            .seed(1)
+            .build()
+            .unwrap()
+            .render_to_buffer(&world, &HittableList::new());
+        let b = test_camera(40, 2)
+            .seed(2)
+            .build()
+            .unwrap()
+            .render_to_buffer(&world, &HittableList::new());
+
+        assert_ne!(bits(&a), bits(&b));
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        tiles: Mutex<Vec<(usize, usize)>>,
+        stats: Mutex<Option<RenderStats>>,
+    }
+
+    impl ProgressObserver for Recorder {
+        fn tile_finished(&self, done: usize, total: usize, _elapsed: std::time::Duration) {
+            self.tiles.lock().unwrap().push((done, total));
+        }
+
+        fn render_finished(&self, stats: &RenderStats) {
+            *self.stats.lock().unwrap() = Some(*stats);
+        }
+    }
+
+    #[test]
+    fn test_progress_and_stats() {
+        let world = scene();
+        let spheres = scene().into_objects().len() as u64;
+        let recorder = Arc::new(Recorder::default());
+        let camera = test_camera(70, 4)
+            .seed(5)
+            .threads(3)
+            .progress(recorder.clone())
+            .build()
+            .unwrap();
+        let (_, stats) = camera.render_with_stats(&world, &HittableList::new());
+
+        // every tile is reported once, counting up to the total
+        let mut tiles = recorder.tiles.lock().unwrap().clone();
+        tiles.sort();
+        let total = camera.tiles().len();
+        assert_eq!(
+            tiles,
+            (1..=total).map(|done| (done, total)).collect::<Vec<_>>()
+        );
 
-        let mut file = File::create("test.ppm").unwrap();
-        file.write_all(image_buffer.concat().as_bytes()).unwrap();
+        assert_eq!(*recorder.stats.lock().unwrap(), Some(stats));
+        assert_eq!(stats.tiles, total);
+        assert_eq!(stats.primary_rays, 70 * 39 * 4);
+        // bounces add to the camera rays, and a plain list tests every ray
+        // against every sphere
+        assert!(stats.total_rays > stats.primary_rays);
+        assert_eq!(stats.intersection_tests, stats.total_rays * spheres);
     }
 }
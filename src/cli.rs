@@ -0,0 +1,403 @@
+use std::{fmt, path::PathBuf};
+
+use raytracing::{
+    render::{writer_for_path, TransferFunction},
+    scenes::{parse_aspect_ratio, RenderSettings, BUILTIN_SCENES},
+    Camera,
+};
+
+pub const USAGE: &str = "Usage: raytracing [OPTIONS]
+
+Options:
+  --width <PIXELS>        image width [default: 1920]
+  --aspect <RATIO>        aspect ratio as W:H or a number [default: 16:9]
+  --spp <SAMPLES>         samples per pixel [default: 100]
+  --max-depth <BOUNCES>   maximum number of ray bounces [default: 10]
+  --min-depth <BOUNCES>   bounces before Russian roulette may end a path
+                          [default: 3]
+  --seed <SEED>           seed making the render reproducible
+  --threads <COUNT>       worker threads [default: all cores]
+  --transfer <FUNCTION>   srgb, linear, gamma or gamma=<exponent> [default: srgb]
+  --output <PATH>         .png, .ppm or .pnm (binary P6), .hdr, .pfm or .exr
+                          [default: image.png]
+  --plain-ppm             write .ppm and .pnm output as plain (P3) text
+  --scene <NAME|FILE>     built-in scene, .json scene file or .gltf/.glb asset
+                          [default: default]
+  -q, --quiet             no progress or statistics on stderr
+  -h, --help              print this help
+
+Values given here override the render settings of a scene file.";
+
+// Render settings given on the command line override those of the scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub settings: RenderSettings,
+    pub threads: Option<usize>,
+    pub scene: String,
+    pub quiet: bool,
+    pub plain_ppm: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            settings: RenderSettings::default(),
+            threads: None,
+            scene: String::from("default"),
+            quiet: false,
+            plain_ppm: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Render(Options),
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue {
+        option: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+            CliError::MissingValue(option) => write!(f, "option '{}' requires a value", option),
+            CliError::InvalidValue {
+                option,
+                value,
+                reason,
+            } => write!(f, "invalid value '{}' for '{}': {}", value, option, reason),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+fn invalid(option: &str, value: &str, reason: impl Into<String>) -> CliError {
+    CliError::InvalidValue {
+        option: option.to_string(),
+        value: value.to_string(),
+        reason: reason.into(),
+    }
+}
+
+// Parses a strictly positive integer that must fit in `max`. Negative and
+// zero values get their own message rather than a generic parse error.
+fn positive(option: &str, value: &str, max: i64) -> Result<i64, CliError> {
+    let number: i64 = value
+        .parse()
+        .map_err(|_| invalid(option, value, "expected a whole number"))?;
+    if number <= 0 {
+        return Err(invalid(option, value, "must be greater than zero"));
+    }
+    if number > max {
+        return Err(invalid(option, value, format!("must be at most {}", max)));
+    }
+    Ok(number)
+}
+
+pub fn parse_args<I>(args: I) -> Result<Command, CliError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Options::default();
+    let mut aspect = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help);
+        }
+        if arg == "-q" || arg == "--quiet" {
+            options.quiet = true;
+            continue;
+        }
+        if arg == "--plain-ppm" {
+            options.plain_ppm = true;
+            continue;
+        }
+
+        // accept both `--option value` and `--option=value`
+        let (option, inline_value) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => {
+                (option.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        if !option.starts_with("--") {
+            return Err(CliError::UnknownOption(arg));
+        }
+        let known = [
+            "--width",
+            "--aspect",
+            "--spp",
+            "--max-depth",
+            "--min-depth",
+            "--seed",
+            "--threads",
+            "--transfer",
+            "--output",
+            "--scene",
+        ];
+        if !known.contains(&option.as_str()) {
+            return Err(CliError::UnknownOption(option));
+        }
+
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(CliError::MissingValue(option)),
+        };
+
+        let settings = &mut options.settings;
+        match option.as_str() {
+            "--width" => {
+                settings.width =
+                    Some(positive(&option, &value, Camera::MAX_IMAGE_SIZE as i64)? as i32)
+            }
+            "--aspect" => {
+                settings.aspect_ratio = Some(
+                    parse_aspect_ratio(&value)
+                        .map_err(|reason| invalid(&option, &value, reason))?,
+                );
+                aspect = Some(value);
+            }
+            "--spp" => {
+                settings.samples_per_pixel =
+                    Some(positive(&option, &value, i32::MAX as i64)? as i32)
+            }
+            "--max-depth" => {
+                settings.max_depth = Some(positive(&option, &value, i32::MAX as i64)? as i32)
+            }
+            "--min-depth" => {
+                settings.min_depth = Some(positive(&option, &value, i32::MAX as i64)? as i32)
+            }
+            "--seed" => {
+                settings.seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid(&option, &value, "expected a non-negative integer"))?,
+                )
+            }
+            "--threads" => {
+                options.threads = Some(positive(&option, &value, u16::MAX as i64)? as usize)
+            }
+            "--transfer" => {
+                settings.transfer = Some(value.parse().map_err(|_: String| {
+                    invalid(
+                        &option,
+                        &value,
+                        "expected srgb, linear, gamma or gamma=<exponent>",
+                    )
+                })?)
+            }
+            "--output" => {
+                let path = PathBuf::from(&value);
+                writer_for_path(&path, TransferFunction::default())
+                    .map_err(|err| invalid(&option, &value, err.to_string()))?;
+                settings.output = Some(path);
+            }
+            "--scene" => {
+                let is_file = [".json", ".gltf", ".glb"]
+                    .iter()
+                    .any(|ext| value.ends_with(ext));
+                if !BUILTIN_SCENES.contains(&value.as_str()) && !is_file {
+                    return Err(invalid(
+                        &option,
+                        &value,
+                        format!(
+                            "expected one of {}, a .json scene file or a .gltf/.glb asset",
+                            BUILTIN_SCENES.join(", ")
+                        ),
+                    ));
+                }
+                options.scene = value;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // the height follows from the width, so a tiny ratio can still make the
+    // image too tall to render
+    if let Some(aspect) = aspect {
+        let settings = &options.settings;
+        let height = Camera::builder()
+            .image_width(settings.width())
+            .aspect_ratio(settings.aspect_ratio())
+            .image_height();
+        if height > Camera::MAX_IMAGE_SIZE {
+            return Err(invalid(
+                "--aspect",
+                &aspect,
+                format!(
+                    "makes the image taller than {} pixels",
+                    Camera::MAX_IMAGE_SIZE
+                ),
+            ));
+        }
+    }
+
+    Ok(Command::Render(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn options(args: &[&str]) -> Options {
+        match parse(args) {
+            Ok(Command::Render(options)) => options,
+            other => panic!("expected options, got {:?}", other),
+        }
+    }
+
+    fn reason(args: &[&str]) -> String {
+        match parse(args) {
+            Err(CliError::InvalidValue { reason, .. }) => reason,
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(options(&[]), Options::default());
+    }
+
+    #[test]
+    fn test_all_options() {
+        let options = options(&[
+            "--width",
+            "640",
+            "--aspect",
+            "4:3",
+            "--spp=16",
+            "--max-depth",
+            "64",
+            "--min-depth=5",
+            "--seed",
+            "42",
+            "--threads",
+            "3",
+            "--transfer",
+            "gamma=2.4",
+            "--output",
+            "out.exr",
+            "--scene",
+            "spheres",
+        ]);
+
+        let settings = &options.settings;
+        assert_eq!(settings.width, Some(640));
+        assert!((settings.aspect_ratio.unwrap() - 4.0 / 3.0).abs() < 1e-12);
+        assert_eq!(settings.samples_per_pixel, Some(16));
+        assert_eq!(settings.max_depth, Some(64));
+        assert_eq!(settings.min_depth, Some(5));
+        assert_eq!(settings.seed, Some(42));
+        assert_eq!(options.threads, Some(3));
+        assert_eq!(settings.transfer, Some(TransferFunction::Gamma(2.4)));
+        assert_eq!(settings.output, Some(PathBuf::from("out.exr")));
+        assert_eq!(options.scene, "spheres");
+        assert!(!options.quiet);
+        assert!(!options.plain_ppm);
+    }
+
+    #[test]
+    fn test_flags_take_no_value() {
+        let options = options(&["--quiet", "--spp", "4"]);
+        assert!(options.quiet);
+        assert_eq!(options.settings.samples_per_pixel, Some(4));
+        assert!(self::options(&["-q"]).quiet);
+        assert!(self::options(&["--plain-ppm", "--output", "a.ppm"]).plain_ppm);
+    }
+
+    #[test]
+    fn test_aspect_as_number() {
+        assert_eq!(options(&["--aspect", "2"]).settings.aspect_ratio, Some(2.0));
+        assert_eq!(
+            options(&["--aspect", "3/2"]).settings.aspect_ratio,
+            Some(1.5)
+        );
+    }
+
+    #[test]
+    fn test_help() {
+        assert_eq!(parse(&["--help"]), Ok(Command::Help));
+        assert_eq!(parse(&["--width", "10", "-h"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_rejects_zero_and_negative_values() {
+        assert_eq!(reason(&["--width", "0"]), "must be greater than zero");
+        assert_eq!(reason(&["--spp", "-4"]), "must be greater than zero");
+        assert_eq!(reason(&["--max-depth", "0"]), "must be greater than zero");
+        assert_eq!(reason(&["--threads", "0"]), "must be greater than zero");
+        assert_eq!(reason(&["--aspect", "-16:9"]), "must be a positive ratio");
+        assert_eq!(reason(&["--aspect", "16:0"]), "must be a positive ratio");
+    }
+
+    #[test]
+    fn test_rejects_malformed_values() {
+        assert_eq!(reason(&["--width", "wide"]), "expected a whole number");
+        assert_eq!(reason(&["--spp", "1.5"]), "expected a whole number");
+        assert_eq!(reason(&["--aspect", "a:b"]), "expected W:H or a number");
+        assert_eq!(reason(&["--seed", "-1"]), "expected a non-negative integer");
+        assert_eq!(reason(&["--width", "99999999999"]), "must be at most 65536");
+        assert_eq!(reason(&["--width", "65537"]), "must be at most 65536");
+        assert_eq!(
+            reason(&["--aspect", "1e-9", "--width", "400"]),
+            "makes the image taller than 65536 pixels"
+        );
+        assert_eq!(
+            options(&["--width", "400", "--aspect", "1:100"])
+                .settings
+                .aspect_ratio,
+            Some(0.01)
+        );
+        assert!(reason(&["--output", "image.jpg"]).contains("unsupported image format"));
+        assert!(reason(&["--scene", "moon"]).contains("expected one of default, spheres"));
+        assert_eq!(options(&["--scene", "room.json"]).scene, "room.json");
+        assert_eq!(options(&["--scene", "car.glb"]).scene, "car.glb");
+        assert!(reason(&["--transfer", "log"]).starts_with("expected srgb"));
+    }
+
+    #[test]
+    fn test_unknown_and_incomplete_options() {
+        assert_eq!(
+            parse(&["--colour", "red"]),
+            Err(CliError::UnknownOption("--colour".to_string()))
+        );
+        assert_eq!(
+            parse(&["render"]),
+            Err(CliError::UnknownOption("render".to_string()))
+        );
+        assert_eq!(
+            parse(&["--width"]),
+            Err(CliError::MissingValue("--width".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_error_messages() {
+        assert_eq!(
+            parse(&["--width", "0"]).unwrap_err().to_string(),
+            "invalid value '0' for '--width': must be greater than zero"
+        );
+        assert_eq!(
+            parse(&["--spp"]).unwrap_err().to_string(),
+            "option '--spp' requires a value"
+        );
+    }
+}
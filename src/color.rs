@@ -1,6 +1,6 @@
 use std::ops::{Add, Mul};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Color {
     pub red: f64,
     pub green: f64,
@@ -37,6 +37,18 @@ impl Mul<f64> for Color {
     }
 }
 
+impl Mul for Color {
+    type Output = Color;
+
+    fn mul(self, rhs: Color) -> Self::Output {
+        Color {
+            red: self.red * rhs.red,
+            green: self.green * rhs.green,
+            blue: self.blue * rhs.blue,
+        }
+    }
+}
+
 impl PartialEq for Color {
     fn eq(&self, other: &Self) -> bool {
         (self.red - other.red).abs() < f64::EPSILON
@@ -0,0 +1,363 @@
+use std::{f64::consts::PI, fmt, io, path::Path};
+
+use crate::{
+    color::Color,
+    hdr::invalid_data,
+    matrix::Matrix4,
+    random::{self, Sampler},
+    render::{read_image, Framebuffer},
+    sphere::sphere_uv,
+    vector::Vector3,
+};
+
+// Light arriving from every direction, read from an equirectangular image:
+// columns run once around the vertical axis and rows from straight up to
+// straight down, laid out as `sphere_uv` maps the unit sphere. Directions
+// are drawn in proportion to the brightness of the pixels they come from,
+// so a small bright sun is found by shadow rays rather than by luck.
+pub struct EnvironmentMap {
+    image: Framebuffer,
+    intensity: f64,
+    rotation: f64,
+    // world to image space, and back
+    to_local: Matrix4,
+    to_world: Matrix4,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    // Turns the image `rotation` degrees around the vertical axis and scales
+    // its radiance by `intensity`. Panics if the image is empty.
+    pub fn new(image: Framebuffer, rotation: f64, intensity: f64) -> Self {
+        assert!(
+            image.width > 0 && image.height > 0,
+            "an environment map needs at least one pixel"
+        );
+        // rows near the poles cover less of the sphere
+        let mut weights = Vec::with_capacity(image.width * image.height);
+        for y in 0..image.height {
+            let sin_theta = (PI * (y as f64 + 0.5) / image.height as f64).sin();
+            for x in 0..image.width {
+                weights.push(luminance(image.get(x, y)) * sin_theta);
+            }
+        }
+        let axis = Vector3::new(0.0, 1.0, 0.0);
+        EnvironmentMap {
+            distribution: Distribution2D::new(&weights, image.width, image.height),
+            image,
+            intensity,
+            rotation,
+            to_local: Matrix4::rotation(axis, -rotation),
+            to_world: Matrix4::rotation(axis, rotation),
+        }
+    }
+
+    // Reads any image `render::read_image` understands, though only
+    // .hdr, .pfm and .exr hold more than the 0 to 1 range of a display.
+    pub fn load(path: &Path, rotation: f64, intensity: f64) -> io::Result<Self> {
+        let image = read_image(path)?;
+        if image.width == 0 || image.height == 0 {
+            return Err(invalid_data(format!("{} is empty", path.display())));
+        }
+        Ok(EnvironmentMap::new(image, rotation, intensity))
+    }
+
+    pub fn image(&self) -> &Framebuffer {
+        &self.image
+    }
+
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    pub fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    // Radiance arriving along `-direction`, that is seen looking towards
+    // `direction`. Pixels are not blended, so the lookup agrees exactly with
+    // the density directions are drawn with.
+    pub fn color(&self, direction: &Vector3) -> Color {
+        let (u, v) = self.local_uv(direction);
+        let (x, y) = self.pixel(u, v);
+        self.image.get(x, y) * self.intensity
+    }
+
+    // Density per unit solid angle with which `random` picks `direction`.
+    pub fn pdf_value(&self, direction: &Vector3) -> f64 {
+        let (u, v) = self.local_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (x, y) = self.pixel(u, v);
+        // the image covers 2 pi by pi radians, squeezed by sin(theta)
+        self.distribution.pdf(x, y) / (2.0 * PI * PI * sin_theta)
+    }
+
+    // A unit direction towards the environment.
+    pub fn random(&self, rng: &mut Sampler) -> Vector3 {
+        let (x, y) = self
+            .distribution
+            .sample(random::random_double(rng), random::random_double(rng));
+        // the inverse of `sphere_uv`, with image rows running downwards
+        let phi = 2.0 * PI * x;
+        let theta = PI * (1.0 - y);
+        let local = Vector3::new(
+            -phi.cos() * theta.sin(),
+            -theta.cos(),
+            phi.sin() * theta.sin(),
+        );
+        self.to_world.transform_vector(local)
+    }
+
+    fn local_uv(&self, direction: &Vector3) -> (f64, f64) {
+        sphere_uv(&Vector3::unit_vector(
+            &self.to_local.transform_vector(*direction),
+        ))
+    }
+
+    fn pixel(&self, u: f64, v: f64) -> (usize, usize) {
+        let (width, height) = (self.image.width, self.image.height);
+        let x = ((u * width as f64) as usize).min(width - 1);
+        let y = (((1.0 - v) * height as f64) as usize).min(height - 1);
+        (x, y)
+    }
+}
+
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.image.width)
+            .field("height", &self.image.height)
+            .field("rotation", &self.rotation)
+            .field("intensity", &self.intensity)
+            .finish()
+    }
+}
+
+fn luminance(color: Color) -> f64 {
+    0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+}
+
+// A step function over [0, 1) with one step per weight, sampled by
+// inverting its running sum. All-zero weights count as equal ones.
+struct Distribution1D {
+    weights: Vec<f64>,
+    // cdf[i] is the share of the total before step i, ending in 1
+    cdf: Vec<f64>,
+    total: f64,
+}
+
+impl Distribution1D {
+    fn new(weights: &[f64]) -> Self {
+        let n = weights.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        let mut sum = 0.0;
+        cdf.push(0.0);
+        for &weight in weights {
+            sum += weight;
+            cdf.push(sum);
+        }
+        for (i, value) in cdf.iter_mut().enumerate() {
+            *value = if sum > 0.0 {
+                *value / sum
+            } else {
+                i as f64 / n as f64
+            };
+        }
+        Distribution1D {
+            weights: weights.to_vec(),
+            cdf,
+            total: sum,
+        }
+    }
+
+    // Density at any point of step `i`, relative to the length of [0, 1).
+    fn pdf(&self, i: usize) -> f64 {
+        let n = self.weights.len() as f64;
+        if self.total > 0.0 {
+            self.weights[i] / self.total * n
+        } else {
+            1.0
+        }
+    }
+
+    // Maps a uniform `xi` to a point of [0, 1) and the step it lies in.
+    fn sample(&self, xi: f64) -> (f64, usize) {
+        let n = self.weights.len();
+        let i = (self.cdf.partition_point(|&c| c <= xi).max(1) - 1).min(n - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let offset = if width > 0.0 {
+            ((xi - self.cdf[i]) / width).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (((i as f64 + offset) / n as f64).min(1.0 - f64::EPSILON), i)
+    }
+}
+
+// A step function over the unit square, sampled by choosing a row by its
+// total and then a column within it.
+struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // `weights` holds `height` rows of `width` values.
+    fn new(weights: &[f64], width: usize, height: usize) -> Self {
+        let rows: Vec<Distribution1D> = weights.chunks(width).map(Distribution1D::new).collect();
+        let totals: Vec<f64> = rows.iter().map(|row| row.total).collect();
+        debug_assert_eq!(totals.len(), height);
+        Distribution2D {
+            marginal: Distribution1D::new(&totals),
+            rows,
+        }
+    }
+
+    fn pdf(&self, x: usize, y: usize) -> f64 {
+        self.marginal.pdf(y) * self.rows[y].pdf(x)
+    }
+
+    fn sample(&self, xi_x: f64, xi_y: f64) -> (f64, f64) {
+        let (y, row) = self.marginal.sample(xi_y);
+        let (x, _) = self.rows[row].sample(xi_x);
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Black apart from one bright pixel above the horizon.
+    fn sun_map(rotation: f64) -> EnvironmentMap {
+        let mut image = Framebuffer::new(16, 8);
+        for y in 0..8 {
+            for x in 0..16 {
+                image.set(x, y, Color::new(0.01, 0.01, 0.01));
+            }
+        }
+        image.set(5, 2, Color::new(500.0, 400.0, 300.0));
+        EnvironmentMap::new(image, rotation, 1.0)
+    }
+
+    #[test]
+    fn test_distribution_1d() {
+        let d = Distribution1D::new(&[1.0, 0.0, 3.0]);
+        assert_eq!(d.sample(0.1).1, 0);
+        assert_eq!(d.sample(0.3).1, 2);
+        assert_eq!(d.sample(0.999).1, 2);
+        // the empty middle step is never chosen
+        assert_eq!(d.sample(0.25).1, 2);
+        assert!((d.pdf(0) - 0.75).abs() < 1e-12);
+        assert!((d.pdf(2) - 2.25).abs() < 1e-12);
+        let (x, _) = d.sample(0.125);
+        assert!((x - 1.0 / 6.0).abs() < 1e-12);
+
+        let flat = Distribution1D::new(&[0.0, 0.0]);
+        assert_eq!(flat.pdf(1), 1.0);
+        assert_eq!(flat.sample(0.7).1, 1);
+    }
+
+    #[test]
+    fn test_samples_favour_bright_pixels() {
+        let map = sun_map(0.0);
+        let mut rng = random::sampler(1, 0, 0);
+        let sun = Color::new(500.0, 400.0, 300.0);
+        let mut bright = 0;
+        for _ in 0..1000 {
+            let direction = map.random(&mut rng);
+            assert!((direction.length() - 1.0).abs() < 1e-9);
+            if map.color(&direction) == sun {
+                bright += 1;
+            }
+        }
+        assert!(bright > 950, "{} samples on the sun", bright);
+    }
+
+    #[test]
+    fn test_pdf_integrates_to_one() {
+        // averaging pdf / (1 / 4 pi) over uniform directions estimates the
+        // integral of the pdf over the sphere
+        let map = sun_map(30.0);
+        let mut rng = random::sampler(2, 0, 0);
+        let n = 200_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let direction = Vector3::random_unit_vector(&mut rng);
+            sum += map.pdf_value(&direction) * 4.0 * PI;
+        }
+        let integral = sum / n as f64;
+        assert!((integral - 1.0).abs() < 0.05, "integral {}", integral);
+    }
+
+    #[test]
+    fn test_pdf_matches_random() {
+        // the density of drawn directions, per solid angle, is the pdf:
+        // weighting each sample by color / pdf estimates the total power,
+        // which must agree with summing the pixels times their solid angle
+        let map = sun_map(0.0);
+        let (width, height) = (map.image.width, map.image.height);
+        let mut expected = 0.0;
+        for y in 0..height {
+            let (top, bottom) = (
+                PI * y as f64 / height as f64,
+                PI * (y + 1) as f64 / height as f64,
+            );
+            let solid_angle = 2.0 * PI / width as f64 * (top.cos() - bottom.cos());
+            for x in 0..width {
+                expected += map.image.get(x, y).red * solid_angle;
+            }
+        }
+        let mut rng = random::sampler(3, 0, 0);
+        let n = 20_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let direction = map.random(&mut rng);
+            sum += map.color(&direction).red / map.pdf_value(&direction);
+        }
+        let estimate = sum / n as f64;
+        assert!(
+            (estimate - expected).abs() < 0.02 * expected,
+            "{} != {}",
+            estimate,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_rotation_turns_the_map() {
+        let still = sun_map(0.0);
+        let turned = sun_map(90.0);
+        let direction = Vector3::new(0.3, 0.5, -0.8);
+        let rotated =
+            Matrix4::rotation(Vector3::new(0.0, 1.0, 0.0), 90.0).transform_vector(direction);
+        assert_eq!(turned.color(&rotated), still.color(&direction));
+        assert!((turned.pdf_value(&rotated) - still.pdf_value(&direction)).abs() < 1e-9);
+
+        let mut image = Framebuffer::new(1, 1);
+        image.set(0, 0, Color::new(0.5, 0.5, 0.5));
+        let bright = EnvironmentMap::new(image, 0.0, 4.0);
+        assert_eq!(bright.color(&direction), Color::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn test_rows_run_from_up_to_down() {
+        let mut image = Framebuffer::new(4, 2);
+        for x in 0..4 {
+            image.set(x, 0, Color::new(1.0, 0.0, 0.0));
+            image.set(x, 1, Color::new(0.0, 0.0, 1.0));
+        }
+        let map = EnvironmentMap::new(image, 0.0, 1.0);
+        assert_eq!(
+            map.color(&Vector3::new(0.1, 1.0, 0.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            map.color(&Vector3::new(0.1, -1.0, 0.0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+    }
+}
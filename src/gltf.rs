@@ -0,0 +1,1378 @@
+use std::{collections::HashMap, fs, io, path::Path, sync::Arc};
+
+use crate::{
+    camera::{Camera, CameraBuilder},
+    color::Color,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    json::{self, Json},
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    matrix::Matrix4,
+    mesh::{Face, Mesh, MeshData, MeshError},
+    point::Point3,
+    render::read_png,
+    scenes::{RenderSettings, Scene},
+    texture::ImageTexture,
+    transformed::Transformed,
+    vector::Vector3,
+};
+
+// The renderable parts of a glTF 2.0 asset. Every node showing a mesh adds a
+// transformed instance of it, so `world` is in scene space while the mesh
+// data is shared.
+pub struct Gltf {
+    pub world: HittableList,
+    // the first perspective camera of the scene, if there is one
+    pub camera: Option<GltfCamera>,
+    pub materials: Vec<GltfMaterial>,
+    // embedded and referenced images, still encoded
+    pub images: Vec<GltfImage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfCamera {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vector3,
+    // vertical field of view in degrees
+    pub vfov: f64,
+    pub aspect_ratio: Option<f64>,
+}
+
+// A metallic-roughness material with its factors in linear space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color: Color,
+    // 1.0 unless the material is alpha blended
+    pub alpha: f64,
+    pub metallic: f64,
+    pub roughness: f64,
+    // already scaled by KHR_materials_emissive_strength
+    pub emissive: Color,
+    pub transmission: f64,
+    pub ior: f64,
+    // index into `Gltf::images`
+    pub base_color_texture: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfImage {
+    pub name: String,
+    pub mime_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+impl GltfMaterial {
+    // The closest of our materials: emissive surfaces become lights,
+    // transmissive or blended ones glass, mostly metallic ones metal with the
+    // roughness as fuzz, and everything else diffuse. A diffuse material
+    // takes its albedo from the base colour texture, which must be a PNG,
+    // tinted by the base colour.
+    pub fn to_material(&self, images: &[GltfImage]) -> io::Result<Arc<dyn Material>> {
+        let e = self.emissive;
+        Ok(if e.red > 0.0 || e.green > 0.0 || e.blue > 0.0 {
+            Arc::new(DiffuseLight::new(e))
+        } else if self.transmission > 0.5 || self.alpha < 0.5 {
+            Arc::new(Dielectric::new(self.ior))
+        } else if self.metallic >= 0.5 {
+            Arc::new(Metal::new(self.base_color, self.roughness))
+        } else if let Some(image) = self.base_color_texture.map(|index| &images[index]) {
+            let mut texture = read_png(&image.data)?;
+            for pixel in &mut texture.pixels {
+                *pixel = *pixel * self.base_color;
+            }
+            Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+                texture,
+            ))))
+        } else {
+            Arc::new(Lambertian::new(self.base_color))
+        })
+    }
+}
+
+impl GltfCamera {
+    pub fn builder(&self) -> CameraBuilder {
+        Camera::builder()
+            .lookfrom(self.lookfrom)
+            .lookat(self.lookat)
+            .vup(self.vup)
+            .vfov(self.vfov)
+    }
+}
+
+impl Gltf {
+    // Turns the asset into a scene, framing the whole world from the front
+    // when the asset has no camera.
+    pub fn into_scene(self) -> Scene {
+        let mut settings = RenderSettings::default();
+        let camera = match self.camera {
+            Some(camera) => {
+                settings.aspect_ratio = camera.aspect_ratio;
+                camera.builder()
+            }
+            None => {
+                let bbox = self.world.bounding_box();
+                let (center, radius) = if bbox.x.min <= bbox.x.max {
+                    let min = Point3::new(bbox.x.min, bbox.y.min, bbox.z.min);
+                    let max = Point3::new(bbox.x.max, bbox.y.max, bbox.z.max);
+                    ((min + max) * 0.5, (max - min).length() * 0.5)
+                } else {
+                    (Point3::new(0.0, 0.0, 0.0), 1.0)
+                };
+                let vfov: f64 = 40.0;
+                let distance = radius / (vfov / 2.0).to_radians().sin();
+                Camera::builder()
+                    .lookfrom(center + Vector3::new(0.0, 0.0, distance))
+                    .lookat(center)
+                    .vfov(vfov)
+            }
+        };
+        Scene {
+            world: self.world,
+            lights: HittableList::new(),
+            camera,
+            settings,
+        }
+    }
+}
+
+// Loads a .gltf or .glb file. Primitives without a material use `material`.
+pub fn load_gltf(path: &Path, material: Arc<dyn Material>) -> Result<Gltf, MeshError> {
+    let bytes = fs::read(path).map_err(|err| MeshError::new(path, None, err.to_string()))?;
+    parse_gltf(&bytes, path, material)
+}
+
+// Parses either encoding of an asset. External buffers and images are read
+// relative to `path`.
+pub fn parse_gltf(
+    bytes: &[u8],
+    path: &Path,
+    material: Arc<dyn Material>,
+) -> Result<Gltf, MeshError> {
+    let (text, bin) = if bytes.starts_with(b"glTF") {
+        let (json, bin) =
+            split_glb(bytes).map_err(|message| MeshError::new(path, None, message))?;
+        let text = std::str::from_utf8(json)
+            .map_err(|_| MeshError::new(path, None, "JSON chunk is not valid UTF-8"))?;
+        (text, bin)
+    } else {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| MeshError::new(path, None, "not a glTF file, expected JSON or GLB"))?;
+        (text, None)
+    };
+    let root = json::parse(text).map_err(|err| {
+        MeshError::new(
+            path,
+            Some(err.line),
+            format!("column {}: {}", err.column, err.message),
+        )
+    })?;
+
+    let mut document = Document {
+        path,
+        root: &root,
+        buffers: Vec::new(),
+    };
+    if !matches!(
+        root.get("asset").and_then(|asset| asset.get("version")).and_then(Json::as_str),
+        Some(version) if version.starts_with("2.")
+    ) {
+        return document.error(&root, "expected a glTF 2.x asset");
+    }
+    document.buffers = document.load_buffers(bin)?;
+
+    let images = document.images()?;
+    let materials = document.materials(images.len())?;
+    let nodes = document.list(&root, "materials")?;
+    let importer = Importer {
+        document: &document,
+        materials: materials
+            .iter()
+            .zip(nodes)
+            .map(|(material, node)| {
+                material.to_material(&images).or_else(|err| {
+                    document.error(node, format!("material '{}': {}", material.name, err))
+                })
+            })
+            .collect::<Result<_, _>>()?,
+        default_material: material,
+    };
+    let (world, camera) = importer.scene()?;
+
+    Ok(Gltf {
+        world,
+        camera,
+        materials,
+        images,
+    })
+}
+
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+
+// Returns the JSON and the optional binary chunk of a GLB container.
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let word = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let version = word(4).ok_or("truncated GLB header")?;
+    if version != 2 {
+        return Err(format!("unsupported GLB version {}", version));
+    }
+    let length = word(8).ok_or("truncated GLB header")? as usize;
+    if length > bytes.len() {
+        return Err(format!(
+            "GLB file is {} bytes but its header says {}",
+            bytes.len(),
+            length
+        ));
+    }
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset < length {
+        let (Some(size), Some(kind)) = (word(offset), word(offset + 4)) else {
+            return Err(String::from("truncated GLB chunk header"));
+        };
+        let start = offset + 8;
+        let end = start + size as usize;
+        if end > length {
+            return Err(String::from("GLB chunk runs past the end of the file"));
+        }
+        match kind {
+            GLB_JSON if json.is_none() => json = Some(&bytes[start..end]),
+            GLB_BIN if bin.is_none() => bin = Some(&bytes[start..end]),
+            // extensions may add chunks of their own
+            _ => {}
+        }
+        // chunks are padded to four bytes
+        offset = (end + 3) & !3;
+    }
+    match json {
+        Some(json) => Ok((json, bin)),
+        None => Err(String::from("GLB file has no JSON chunk")),
+    }
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in text.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+// The parsed JSON together with the loaded buffers, and helpers that report
+// problems at the line of the offending value.
+struct Document<'a> {
+    path: &'a Path,
+    root: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+// The decoded values of an accessor, `components` per element.
+struct Accessor {
+    components: usize,
+    values: Vec<f64>,
+}
+
+impl<'a> Document<'a> {
+    fn error<T>(&self, node: &Json, message: impl Into<String>) -> Result<T, MeshError> {
+        Err(MeshError::new(self.path, Some(node.line), message))
+    }
+
+    // A top-level array such as "nodes"; missing arrays are empty.
+    fn list(&self, node: &'a Json, key: &str) -> Result<&'a [Json], MeshError> {
+        match node.get(key) {
+            None => Ok(&[]),
+            Some(value) => match value.as_array() {
+                Some(items) => Ok(items),
+                None => self.error(value, format!("expected '{}' to be an array", key)),
+            },
+        }
+    }
+
+    // An optional reference to item `what` of a list with `count` entries.
+    fn index(
+        &self,
+        node: &Json,
+        key: &str,
+        what: &str,
+        count: usize,
+    ) -> Result<Option<usize>, MeshError> {
+        match node.get(key) {
+            None => Ok(None),
+            Some(value) => self.reference(value, what, count).map(Some),
+        }
+    }
+
+    fn reference(&self, value: &Json, what: &str, count: usize) -> Result<usize, MeshError> {
+        match value.as_f64() {
+            Some(index) if index >= 0.0 && index.fract() == 0.0 && (index as usize) < count => {
+                Ok(index as usize)
+            }
+            Some(index) if index >= 0.0 && index.fract() == 0.0 => self.error(
+                value,
+                format!("{} {} out of range, {} defined", what, index, count),
+            ),
+            _ => self.error(value, format!("expected {} index", what)),
+        }
+    }
+
+    fn number(&self, node: &Json, key: &str, default: f64) -> Result<f64, MeshError> {
+        match node.get(key) {
+            None => Ok(default),
+            Some(value) => match value.as_f64() {
+                Some(number) => Ok(number),
+                None => self.error(value, format!("expected '{}' to be a number", key)),
+            },
+        }
+    }
+
+    // A count or byte offset, which must be a whole number no larger than
+    // `limit` so that the arithmetic done with it cannot overflow.
+    fn size(&self, node: &Json, key: &str, limit: usize) -> Result<usize, MeshError> {
+        let value = self.number(node, key, 0.0)?;
+        if value < 0.0 || value.fract() != 0.0 || value > limit as f64 {
+            return self.error(
+                node.get(key).unwrap_or(node),
+                format!("expected '{}' to be a whole number up to {}", key, limit),
+            );
+        }
+        Ok(value as usize)
+    }
+
+    fn numbers<const N: usize>(
+        &self,
+        node: &Json,
+        key: &str,
+        default: [f64; N],
+    ) -> Result<[f64; N], MeshError> {
+        let Some(value) = node.get(key) else {
+            return Ok(default);
+        };
+        let numbers: Option<Vec<f64>> = value
+            .as_array()
+            .and_then(|items| items.iter().map(Json::as_f64).collect());
+        match numbers.and_then(|numbers| numbers.try_into().ok()) {
+            Some(numbers) => Ok(numbers),
+            None => self.error(value, format!("expected '{}' to be {} numbers", key, N)),
+        }
+    }
+
+    fn string(&self, node: &'a Json, key: &str) -> Result<Option<&'a str>, MeshError> {
+        match node.get(key) {
+            None => Ok(None),
+            Some(value) => match value.as_str() {
+                Some(string) => Ok(Some(string)),
+                None => self.error(value, format!("expected '{}' to be a string", key)),
+            },
+        }
+    }
+
+    fn name(&self, node: &'a Json, fallback: &str, index: usize) -> Result<String, MeshError> {
+        Ok(match self.string(node, "name")? {
+            Some(name) => name.to_string(),
+            None => format!("{} {}", fallback, index),
+        })
+    }
+
+    // Resolves a data URI or a path relative to the asset.
+    fn uri(&self, node: &Json, uri: &str) -> Result<Vec<u8>, MeshError> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            return match data.split_once(";base64,") {
+                Some((_, encoded)) => match decode_base64(encoded) {
+                    Some(bytes) => Ok(bytes),
+                    None => self.error(node, "invalid base64 data in URI"),
+                },
+                None => self.error(node, "only base64 data URIs are supported"),
+            };
+        }
+        let file = self.path.parent().unwrap_or(Path::new("")).join(uri);
+        fs::read(&file).map_err(|err| {
+            MeshError::new(
+                self.path,
+                Some(node.line),
+                format!("cannot read '{}': {}", uri, err),
+            )
+        })
+    }
+
+    fn load_buffers(&self, bin: Option<&[u8]>) -> Result<Vec<Vec<u8>>, MeshError> {
+        let mut buffers = Vec::new();
+        for (index, buffer) in self.list(self.root, "buffers")?.iter().enumerate() {
+            let length = self.number(buffer, "byteLength", -1.0)?;
+            if length < 0.0 || length.fract() != 0.0 {
+                return self.error(buffer, "expected buffer to have a byteLength");
+            }
+            let data = match (self.string(buffer, "uri")?, bin) {
+                (Some(uri), _) => self.uri(buffer.get("uri").unwrap(), uri)?,
+                // the binary chunk of a GLB is the first buffer without a URI
+                (None, Some(bin)) if index == 0 => bin.to_vec(),
+                (None, _) => return self.error(buffer, "buffer has no data"),
+            };
+            if data.len() < length as usize {
+                return self.error(
+                    buffer,
+                    format!(
+                        "buffer {} has {} bytes, expected {}",
+                        index,
+                        data.len(),
+                        length
+                    ),
+                );
+            }
+            buffers.push(data);
+        }
+        Ok(buffers)
+    }
+
+    // The bytes of a buffer view and its stride, if it has one.
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), MeshError> {
+        let view = &self.list(self.root, "bufferViews")?[index];
+        let Some(buffer) = self.index(view, "buffer", "buffer", self.buffers.len())? else {
+            return self.error(view, "missing field 'buffer' in buffer view");
+        };
+        let buffer = &self.buffers[buffer];
+        let offset = self.size(view, "byteOffset", buffer.len())?;
+        let length = self.size(view, "byteLength", buffer.len())?;
+        let stride = match view.get("byteStride") {
+            Some(_) => Some(self.size(view, "byteStride", 252)?),
+            None => None,
+        };
+        if stride == Some(0) {
+            return self.error(view, "expected 'byteStride' to be positive");
+        }
+        match buffer.get(offset..offset + length) {
+            Some(bytes) => Ok((bytes, stride)),
+            None => self.error(view, format!("buffer view {} is out of bounds", index)),
+        }
+    }
+
+    // Decodes accessor `index`, which must have one of `types` ("VEC3" ...).
+    fn accessor(&self, node: &Json, index: usize, types: &[&str]) -> Result<Accessor, MeshError> {
+        let accessors = self.list(self.root, "accessors")?;
+        let Some(accessor) = accessors.get(index) else {
+            return self.error(
+                node,
+                format!(
+                    "accessor {} out of range, {} defined",
+                    index,
+                    accessors.len()
+                ),
+            );
+        };
+        if accessor.get("sparse").is_some() {
+            return self.error(accessor, "sparse accessors are not supported");
+        }
+
+        let kind = self.string(accessor, "type")?.unwrap_or("");
+        let components = match kind {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            _ => return self.error(accessor, format!("unsupported accessor type '{}'", kind)),
+        };
+        if !types.contains(&kind) {
+            return self.error(
+                node,
+                format!(
+                    "accessor {} has type {}, expected {}",
+                    index,
+                    kind,
+                    types.join(" or ")
+                ),
+            );
+        }
+        let component_type = self.number(accessor, "componentType", 0.0)? as u32;
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => {
+                return self.error(
+                    accessor,
+                    format!("unsupported component type {}", component_type),
+                )
+            }
+        };
+        let normalized = matches!(
+            accessor.get("normalized").map(|value| &value.value),
+            Some(json::Value::Bool(true))
+        );
+        let views = self.list(self.root, "bufferViews")?.len();
+        let Some(view) = self.index(accessor, "bufferView", "buffer view", views)? else {
+            // accessors without a view are all zeros; there can be no more
+            // of them than there are bytes that could index them
+            let bytes = self.buffers.iter().map(Vec::len).sum();
+            let count = self.size(accessor, "count", bytes)?;
+            return Ok(Accessor {
+                components,
+                values: vec![0.0; count * components],
+            });
+        };
+        let (bytes, stride) = self.buffer_view(view)?;
+        // every element starts at a different byte of the view
+        let count = self.size(accessor, "count", bytes.len())?;
+        let offset = self.size(accessor, "byteOffset", bytes.len())?;
+        let element = size * components;
+        let stride = stride.unwrap_or(element);
+        let end = match count {
+            0 => Some(offset),
+            _ => stride
+                .checked_mul(count - 1)
+                .and_then(|last| last.checked_add(offset + element)),
+        };
+        if !matches!(end, Some(end) if end <= bytes.len()) {
+            return self.error(
+                accessor,
+                format!("accessor {} reads past the end of its buffer view", index),
+            );
+        }
+
+        let mut values = Vec::with_capacity(count * components);
+        for i in 0..count {
+            for c in 0..components {
+                let at = offset + i * stride + c * size;
+                let b = &bytes[at..at + size];
+                let value = match component_type {
+                    5120 if normalized => (b[0] as i8 as f64 / 127.0).max(-1.0),
+                    5120 => b[0] as i8 as f64,
+                    5121 if normalized => b[0] as f64 / 255.0,
+                    5121 => b[0] as f64,
+                    5122 if normalized => {
+                        (i16::from_le_bytes([b[0], b[1]]) as f64 / 32767.0).max(-1.0)
+                    }
+                    5122 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    5123 if normalized => u16::from_le_bytes([b[0], b[1]]) as f64 / 65535.0,
+                    5123 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                };
+                values.push(value);
+            }
+        }
+        Ok(Accessor { components, values })
+    }
+
+    fn images(&self) -> Result<Vec<GltfImage>, MeshError> {
+        let views = self.list(self.root, "bufferViews")?.len();
+        let mut images = Vec::new();
+        for (index, image) in self.list(self.root, "images")?.iter().enumerate() {
+            let mut mime_type = self.string(image, "mimeType")?.map(str::to_string);
+            let data = if let Some(uri) = self.string(image, "uri")? {
+                if let Some((mime, _)) = uri
+                    .strip_prefix("data:")
+                    .and_then(|data| data.split_once(';'))
+                {
+                    mime_type.get_or_insert_with(|| mime.to_string());
+                }
+                self.uri(image.get("uri").unwrap(), uri)?
+            } else if let Some(view) = self.index(image, "bufferView", "buffer view", views)? {
+                self.buffer_view(view)?.0.to_vec()
+            } else {
+                return self.error(image, "image has neither a uri nor a bufferView");
+            };
+            images.push(GltfImage {
+                name: self.name(image, "image", index)?,
+                mime_type,
+                data,
+            });
+        }
+        Ok(images)
+    }
+
+    fn materials(&self, images: usize) -> Result<Vec<GltfMaterial>, MeshError> {
+        let textures = self.list(self.root, "textures")?;
+        let mut materials = Vec::new();
+        for (index, material) in self.list(self.root, "materials")?.iter().enumerate() {
+            let empty = Json {
+                value: json::Value::Object(Vec::new()),
+                line: material.line,
+            };
+            let pbr = material.get("pbrMetallicRoughness").unwrap_or(&empty);
+            let [r, g, b, a] = self.numbers(pbr, "baseColorFactor", [1.0; 4])?;
+            let [er, eg, eb] = self.numbers(material, "emissiveFactor", [0.0; 3])?;
+
+            let base_color_texture = match pbr.get("baseColorTexture") {
+                Some(info) => match self.index(info, "index", "texture", textures.len())? {
+                    Some(texture) => self.index(&textures[texture], "source", "image", images)?,
+                    None => return self.error(info, "missing field 'index' in texture info"),
+                },
+                None => None,
+            };
+
+            let extensions = material.get("extensions").unwrap_or(&empty);
+            let transmission = match extensions.get("KHR_materials_transmission") {
+                Some(extension) => self.number(extension, "transmissionFactor", 0.0)?,
+                None => 0.0,
+            };
+            let ior = match extensions.get("KHR_materials_ior") {
+                Some(extension) => self.number(extension, "ior", 1.5)?,
+                None => 1.5,
+            };
+            let strength = match extensions.get("KHR_materials_emissive_strength") {
+                Some(extension) => self.number(extension, "emissiveStrength", 1.0)?,
+                None => 1.0,
+            };
+
+            materials.push(GltfMaterial {
+                name: self.name(material, "material", index)?,
+                base_color: Color::new(r, g, b),
+                alpha: match self.string(material, "alphaMode")? {
+                    Some("BLEND") => a,
+                    _ => 1.0,
+                },
+                metallic: self.number(pbr, "metallicFactor", 1.0)?,
+                roughness: self.number(pbr, "roughnessFactor", 1.0)?,
+                emissive: Color::new(er, eg, eb) * strength,
+                transmission,
+                ior,
+                base_color_texture,
+            });
+        }
+        Ok(materials)
+    }
+}
+
+// Walks the node hierarchy of the default scene, instancing meshes and
+// picking up the first camera.
+struct Importer<'a> {
+    document: &'a Document<'a>,
+    materials: Vec<Arc<dyn Material>>,
+    default_material: Arc<dyn Material>,
+}
+
+impl Importer<'_> {
+    fn scene(&self) -> Result<(HittableList, Option<GltfCamera>), MeshError> {
+        let document = self.document;
+        let root = document.root;
+        let nodes = document.list(root, "nodes")?;
+        let scenes = document.list(root, "scenes")?;
+
+        // without scenes every node that is nobody's child is a root
+        let roots: Vec<usize> = match document.index(root, "scene", "scene", scenes.len())? {
+            Some(scene) => self.children(&scenes[scene], "nodes")?,
+            None if !scenes.is_empty() => self.children(&scenes[0], "nodes")?,
+            None => {
+                let mut is_child = vec![false; nodes.len()];
+                for node in nodes {
+                    for child in self.children(node, "children")? {
+                        is_child[child] = true;
+                    }
+                }
+                (0..nodes.len()).filter(|&i| !is_child[i]).collect()
+            }
+        };
+
+        let mut world = HittableList::new();
+        let mut camera = None;
+        let mut visiting = vec![false; nodes.len()];
+        let mut meshes = HashMap::new();
+        for node in roots {
+            self.visit(
+                node,
+                Matrix4::IDENTITY,
+                &mut visiting,
+                &mut meshes,
+                &mut world,
+                &mut camera,
+            )?;
+        }
+        Ok((world, camera))
+    }
+
+    fn children(&self, node: &Json, key: &str) -> Result<Vec<usize>, MeshError> {
+        let document = self.document;
+        let nodes = document.list(document.root, "nodes")?.len();
+        document
+            .list(node, key)?
+            .iter()
+            .map(|child| document.reference(child, "node", nodes))
+            .collect()
+    }
+
+    fn visit(
+        &self,
+        index: usize,
+        parent: Matrix4,
+        visiting: &mut Vec<bool>,
+        // meshes built so far by glTF index, None for those without triangles
+        meshes: &mut HashMap<usize, Option<Arc<Mesh>>>,
+        world: &mut HittableList,
+        camera: &mut Option<GltfCamera>,
+    ) -> Result<(), MeshError> {
+        let document = self.document;
+        let nodes = document.list(document.root, "nodes")?;
+        let node = &nodes[index];
+        visiting[index] = true;
+
+        let local = if node.get("matrix").is_some() {
+            Matrix4::from_columns(document.numbers(node, "matrix", [0.0; 16])?)
+        } else {
+            Matrix4::from_trs(
+                document.numbers(node, "translation", [0.0; 3])?,
+                document.numbers(node, "rotation", [0.0, 0.0, 0.0, 1.0])?,
+                document.numbers(node, "scale", [1.0; 3])?,
+            )
+        };
+        let transform = parent * local;
+
+        let mesh_nodes = document.list(document.root, "meshes")?;
+        if let Some(index) = document.index(node, "mesh", "mesh", mesh_nodes.len())? {
+            let mesh = match meshes.get(&index) {
+                Some(mesh) => mesh.clone(),
+                None => {
+                    let mesh = self.mesh(&mesh_nodes[index], index)?.map(Arc::new);
+                    meshes.insert(index, mesh.clone());
+                    mesh
+                }
+            };
+            // a transform that cannot be inverted flattens the mesh away
+            if let (Some(mesh), Some(_)) = (mesh, transform.inverse()) {
+                if transform == Matrix4::IDENTITY {
+                    world.add(Box::new(mesh));
+                } else {
+                    world.add(Box::new(Transformed::new(mesh, transform)));
+                }
+            }
+        }
+
+        let cameras = document.list(document.root, "cameras")?;
+        if let Some(index) = document.index(node, "camera", "camera", cameras.len())? {
+            if camera.is_none() {
+                *camera = self.camera(&cameras[index], &transform)?;
+            }
+        }
+
+        for child in document.list(node, "children")? {
+            let child_index = document.reference(child, "node", nodes.len())?;
+            if visiting[child_index] {
+                return document.error(child, format!("node {} is its own ancestor", child_index));
+            }
+            self.visit(child_index, transform, visiting, meshes, world, camera)?;
+        }
+        visiting[index] = false;
+        Ok(())
+    }
+
+    // glTF cameras look down -z with +y up. Orthographic ones are skipped.
+    fn camera(&self, node: &Json, transform: &Matrix4) -> Result<Option<GltfCamera>, MeshError> {
+        let document = self.document;
+        let Some(perspective) = node.get("perspective") else {
+            return Ok(None);
+        };
+        let yfov = document.number(perspective, "yfov", 0.0)?;
+        if yfov <= 0.0 || yfov >= std::f64::consts::PI {
+            return document.error(perspective, "expected 'yfov' between 0 and pi");
+        }
+        let aspect_ratio = match perspective.get("aspectRatio") {
+            Some(value) => {
+                let aspect_ratio = document.number(perspective, "aspectRatio", 0.0)?;
+                if !aspect_ratio.is_finite() || aspect_ratio <= 0.0 {
+                    return document.error(value, "expected 'aspectRatio' to be positive");
+                }
+                Some(aspect_ratio)
+            }
+            None => None,
+        };
+
+        let lookfrom = transform.transform_point(Point3::new(0.0, 0.0, 0.0));
+        let forward = transform.transform_vector(Vector3::new(0.0, 0.0, -1.0));
+        let camera = GltfCamera {
+            lookfrom,
+            lookat: lookfrom + Vector3::unit_vector(&forward),
+            vup: transform.transform_vector(Vector3::new(0.0, 1.0, 0.0)),
+            vfov: yfov.to_degrees(),
+            aspect_ratio,
+        };
+        // a node that scales the camera flat leaves it without a view
+        if let Err(err) = camera.builder().validate() {
+            return document.error(node, format!("invalid camera: {}", err));
+        }
+        Ok(Some(camera))
+    }
+
+    // A mesh with all of its triangle primitives in its own space, or None if
+    // it has no triangles.
+    fn mesh(&self, node: &Json, index: usize) -> Result<Option<Mesh>, MeshError> {
+        let document = self.document;
+        let mut data = MeshData {
+            groups: vec![document.name(node, "mesh", index)?],
+            ..MeshData::default()
+        };
+        // glTF material index to the index in `data.materials`
+        let mut materials: HashMap<Option<usize>, u32> = HashMap::new();
+
+        for primitive in document.list(node, "primitives")? {
+            let mode = document.number(primitive, "mode", 4.0)?;
+            if !(4.0..=6.0).contains(&mode) {
+                // points and lines have no surface to render
+                continue;
+            }
+            let Some(attributes) = primitive.get("attributes") else {
+                return document.error(primitive, "missing field 'attributes' in primitive");
+            };
+            if attributes.get("POSITION").is_none() {
+                return document.error(attributes, "primitive has no POSITION attribute");
+            }
+            let accessors = document.list(document.root, "accessors")?.len();
+            let attribute = |name: &str, types: &[&str]| -> Result<Option<Accessor>, MeshError> {
+                match document.index(attributes, name, "accessor", accessors)? {
+                    Some(accessor) => Ok(Some(document.accessor(
+                        attributes.get(name).unwrap(),
+                        accessor,
+                        types,
+                    )?)),
+                    None => Ok(None),
+                }
+            };
+            let positions = attribute("POSITION", &["VEC3"])?.unwrap();
+            let count = positions.values.len() / 3;
+            let normals = attribute("NORMAL", &["VEC3"])?;
+            let uvs = attribute("TEXCOORD_0", &["VEC2"])?;
+            for (name, accessor) in [("NORMAL", &normals), ("TEXCOORD_0", &uvs)] {
+                if let Some(accessor) = accessor {
+                    if accessor.values.len() / accessor.components != count {
+                        return document.error(
+                            attributes.get(name).unwrap(),
+                            format!("{} has a different count than POSITION", name),
+                        );
+                    }
+                }
+            }
+
+            let indices: Vec<u32> =
+                match document.index(primitive, "indices", "accessor", accessors)? {
+                    Some(accessor) => {
+                        let value = primitive.get("indices").unwrap();
+                        let indices = document.accessor(value, accessor, &["SCALAR"])?;
+                        if let Some(&bad) = indices.values.iter().find(|&&i| i as usize >= count) {
+                            return document.error(
+                                value,
+                                format!("vertex index {} out of range, {} vertices", bad, count),
+                            );
+                        }
+                        indices.values.iter().map(|&i| i as u32).collect()
+                    }
+                    None => (0..count as u32).collect(),
+                };
+
+            let material =
+                document.index(primitive, "material", "material", self.materials.len())?;
+            let material = *materials.entry(material).or_insert_with(|| {
+                data.materials.push(match material {
+                    Some(material) => Arc::clone(&self.materials[material]),
+                    None => Arc::clone(&self.default_material),
+                });
+                data.materials.len() as u32 - 1
+            });
+
+            let position_base = data.positions.len() as u32;
+            let normal_base = data.normals.len() as u32;
+            let uv_base = data.uvs.len() as u32;
+            for p in positions.values.chunks(3) {
+                data.positions.push(Point3::new(p[0], p[1], p[2]));
+            }
+            if let Some(normals) = &normals {
+                for n in normals.values.chunks(3) {
+                    data.normals
+                        .push(Vector3::unit_vector(&Vector3::new(n[0], n[1], n[2])));
+                }
+            }
+            if let Some(uvs) = &uvs {
+                // glTF puts the texture origin at the top left, we use the
+                // bottom left like OBJ
+                for uv in uvs.values.chunks(2) {
+                    data.uvs.push((uv[0], 1.0 - uv[1]));
+                }
+            }
+
+            for [a, b, c] in triangles(&indices, mode as u32) {
+                if a == b || b == c || a == c {
+                    continue;
+                }
+                let corners = [a, b, c];
+                let mut face = Face::new(corners.map(|i| position_base + i));
+                face.normals = normals.as_ref().map(|_| corners.map(|i| normal_base + i));
+                face.uvs = uvs.as_ref().map(|_| corners.map(|i| uv_base + i));
+                face.material = material;
+                data.faces.push(face);
+            }
+        }
+
+        if data.faces.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Mesh::new(data)))
+    }
+}
+
+// Splits triangle lists, strips and fans into triangles.
+fn triangles(indices: &[u32], mode: u32) -> Vec<[u32; 3]> {
+    match mode {
+        5 => (0..indices.len().saturating_sub(2))
+            .map(|i| match i % 2 {
+                0 => [indices[i], indices[i + 1], indices[i + 2]],
+                _ => [indices[i + 1], indices[i], indices[i + 2]],
+            })
+            .collect(),
+        6 => (1..indices.len().saturating_sub(1))
+            .map(|i| [indices[0], indices[i], indices[i + 1]])
+            .collect(),
+        _ => indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::HitRecord,
+        interval::Interval,
+        random,
+        ray::Ray,
+        render::{Framebuffer, ImageWriter, PngWriter, TransferFunction},
+    };
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut text = String::new();
+        for chunk in bytes.chunks(3) {
+            let b = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let bits = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    text.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    text.push('=');
+                }
+            }
+        }
+        text
+    }
+
+    // A triangle in the xy plane facing +z, with normals and u16 indices.
+    fn triangle_buffer() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for value in [-1.0f32, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0] {
+            bytes.extend(value.to_le_bytes());
+        }
+        for _ in 0..3 {
+            for value in [0.0f32, 0.0, 1.0] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        for index in [0u16, 1, 2, 0] {
+            bytes.extend(index.to_le_bytes());
+        }
+        bytes
+    }
+
+    // An asset around the triangle buffer; `rest` adds nodes, cameras and so
+    // on, and `uri` is the buffer's member (empty for GLB).
+    fn document(uri: &str, rest: &str) -> String {
+        format!(
+            r#"{{
+  "asset": {{"version": "2.0"}},
+  "buffers": [{{"byteLength": 80{}}}],
+  "bufferViews": [
+    {{"buffer": 0, "byteLength": 72}},
+    {{"buffer": 0, "byteOffset": 72, "byteLength": 6}}
+  ],
+  "accessors": [
+    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}},
+    {{"bufferView": 0, "byteOffset": 36, "componentType": 5126, "count": 3, "type": "VEC3"}},
+    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+  ],
+  "meshes": [{{"name": "triangle", "primitives": [
+    {{"attributes": {{"POSITION": 0, "NORMAL": 1}}, "indices": 2}}
+  ]}}],
+{}
+}}"#,
+            uri, rest
+        )
+    }
+
+    fn gltf(rest: &str) -> String {
+        let uri = format!(
+            r#", "uri": "data:application/octet-stream;base64,{}""#,
+            base64(&triangle_buffer())
+        );
+        document(&uri, rest)
+    }
+
+    fn grey() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    fn parse(text: &str) -> Result<Gltf, MeshError> {
+        parse_gltf(text.as_bytes(), Path::new("asset.gltf"), grey())
+    }
+
+    fn hit(gltf: &Gltf, origin: Point3, direction: Vector3) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        let r = Ray::new(origin, direction);
+        gltf.world
+            .hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec)
+            .then_some(rec)
+    }
+
+    fn assert_near(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_parse_gltf_translated_triangle() {
+        let gltf = parse(&gltf(
+            r#""scenes": [{"nodes": [0]}], "nodes": [{"mesh": 0, "translation": [0, 0, -2]}]"#,
+        ))
+        .unwrap();
+
+        let rec = hit(
+            &gltf,
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-9);
+        assert_near(rec.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert!(gltf.camera.is_none());
+    }
+
+    #[test]
+    fn test_parse_gltf_node_hierarchy() {
+        // without scenes both root nodes are instanced: the child of the
+        // scaled parent ends up at z = -2, the matrix node moves along x
+        let gltf = parse(&gltf(
+            r#""nodes": [
+    {"scale": [2, 2, 2], "children": [1]},
+    {"mesh": 0, "translation": [0, 0, -1]},
+    {"mesh": 0, "matrix": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 5, 0, 0, 1]}
+  ]"#,
+        ))
+        .unwrap();
+
+        let rec = hit(
+            &gltf,
+            Point3::new(1.5, -1.5, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-9);
+        let rec = hit(
+            &gltf,
+            Point3::new(5.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        assert!(hit(
+            &gltf,
+            Point3::new(3.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_parse_gltf_flattened_node_is_skipped() {
+        let gltf = parse(&gltf(
+            r#""nodes": [{"mesh": 0, "scale": [1, 0, 1]}, {"mesh": 0, "translation": [0, 0, -1]}]"#,
+        ))
+        .unwrap();
+        let rec = hit(
+            &gltf,
+            Point3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-9);
+        assert_eq!(gltf.world.into_objects().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_gltf_mirrored_node_keeps_normals_outward() {
+        let gltf = parse(&gltf(r#""nodes": [{"mesh": 0, "scale": [1, 1, -1]}]"#)).unwrap();
+        let rec = hit(
+            &gltf,
+            Point3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        // the normal is mirrored to -z, so the ray arrives from the back
+        assert!(!rec.front_face);
+        assert_near(rec.normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_parse_gltf_camera() {
+        let gltf = parse(&gltf(
+            r#""cameras": [
+    {"type": "orthographic", "orthographic": {"xmag": 1, "ymag": 1, "znear": 0.1, "zfar": 10}},
+    {"type": "perspective", "perspective": {"yfov": 0.5, "aspectRatio": 1.5, "znear": 0.1}}
+  ],
+  "nodes": [
+    {"children": [1, 2], "translation": [0, 1, 0]},
+    {"camera": 0},
+    {"camera": 1, "translation": [0, 0, 5], "rotation": [0, 0.7071067811865476, 0, 0.7071067811865476]}
+  ]"#,
+        ))
+        .unwrap();
+
+        // rotated a quarter turn about y, the camera looks down -x
+        let camera = gltf.camera.clone().unwrap();
+        assert_near(camera.lookfrom, Point3::new(0.0, 1.0, 5.0));
+        assert_near(camera.lookat, Point3::new(-1.0, 1.0, 5.0));
+        assert_near(camera.vup, Vector3::new(0.0, 1.0, 0.0));
+        assert!((camera.vfov - 0.5f64.to_degrees()).abs() < 1e-9);
+
+        let scene = gltf.into_scene();
+        assert_eq!(scene.settings.aspect_ratio, Some(1.5));
+    }
+
+    #[test]
+    fn test_parse_gltf_materials_and_images() {
+        let png = [0x89, b'P', b'N', b'G', 1, 2, 3];
+        let text = gltf(&format!(
+            r#""materials": [
+    {{"name": "red", "pbrMetallicRoughness": {{"baseColorFactor": [1, 0, 0, 1],
+      "metallicFactor": 0, "baseColorTexture": {{"index": 0}}}}, "emissiveFactor": [0, 0, 0.5],
+      "extensions": {{"KHR_materials_emissive_strength": {{"emissiveStrength": 4}}}}}},
+    {{"pbrMetallicRoughness": {{"baseColorFactor": [1, 1, 1, 0.2]}}, "alphaMode": "BLEND",
+      "extensions": {{"KHR_materials_ior": {{"ior": 1.33}}}}}}
+  ],
+  "textures": [{{"source": 1}}],
+  "images": [
+    {{"bufferView": 1, "mimeType": "application/octet-stream"}},
+    {{"name": "albedo", "uri": "data:image/png;base64,{}"}}
+  ],
+  "nodes": [{{"mesh": 0}}]"#,
+            base64(&png)
+        ));
+        let gltf = parse(&text).unwrap();
+
+        assert_eq!(gltf.materials.len(), 2);
+        let red = &gltf.materials[0];
+        assert_eq!(red.name, "red");
+        assert_eq!(red.base_color, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(red.metallic, 0.0);
+        assert_eq!(red.roughness, 1.0);
+        assert_eq!(red.emissive, Color::new(0.0, 0.0, 2.0));
+        assert_eq!(red.base_color_texture, Some(1));
+        let glass = &gltf.materials[1];
+        assert_eq!(glass.name, "material 1");
+        assert_eq!((glass.alpha, glass.ior), (0.2, 1.33));
+
+        assert_eq!(gltf.images[0].data, [0, 0, 1, 0, 2, 0]);
+        assert_eq!(gltf.images[1].name, "albedo");
+        assert_eq!(gltf.images[1].mime_type.as_deref(), Some("image/png"));
+        assert_eq!(gltf.images[1].data, png);
+    }
+
+    #[test]
+    fn test_parse_gltf_base_color_texture() {
+        let mut image = Framebuffer::new(1, 1);
+        image.set(0, 0, Color::new(1.0, 1.0, 1.0));
+        let mut png = Vec::new();
+        PngWriter {
+            transfer: TransferFunction::Srgb,
+        }
+        .write(&mut png, &image)
+        .unwrap();
+        let textured = |png: &[u8]| {
+            gltf(&format!(
+                r#""materials": [
+    {{"pbrMetallicRoughness": {{"baseColorFactor": [0.5, 0.25, 1, 1],
+      "metallicFactor": 0, "baseColorTexture": {{"index": 0}}}}}}
+  ],
+  "textures": [{{"source": 0}}],
+  "images": [{{"uri": "data:image/png;base64,{}"}}],
+  "nodes": [{{"mesh": 0}}]"#,
+                base64(png)
+            ))
+            .replace(r#""indices": 2"#, r#""indices": 2, "material": 0"#)
+        };
+
+        let gltf = parse(&textured(&png)).unwrap();
+        let rec = hit(
+            &gltf,
+            Point3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(rec.p, rec.normal);
+        assert!(rec.mat.as_ref().unwrap().scatter(
+            &Ray::new(Point3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0)),
+            &rec,
+            &mut attenuation,
+            &mut scattered,
+            &mut random::sampler(0, 0, 0),
+        ));
+        assert_eq!(attenuation, Color::new(0.5, 0.25, 1.0));
+
+        let text = textured(b"not a png");
+        let line = text
+            .lines()
+            .position(|line| line.contains("baseColorFactor"));
+        let err = parse(&text).err().unwrap();
+        assert_eq!(err.line, line.map(|line| line + 1));
+        assert_eq!(err.message, "material 'material 0': missing PNG signature");
+    }
+
+    #[test]
+    fn test_parse_glb() {
+        let mut json =
+            document("", r#""nodes": [{"mesh": 0, "translation": [0, 0, -3]}]"#).into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let bin = triangle_buffer();
+
+        let mut glb = Vec::new();
+        glb.extend(b"glTF");
+        glb.extend(2u32.to_le_bytes());
+        glb.extend(((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(GLB_JSON.to_le_bytes());
+        glb.extend(&json);
+        glb.extend((bin.len() as u32).to_le_bytes());
+        glb.extend(GLB_BIN.to_le_bytes());
+        glb.extend(&bin);
+
+        let gltf = parse_gltf(&glb, Path::new("asset.glb"), grey()).unwrap();
+        let rec = hit(
+            &gltf,
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        )
+        .unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9);
+
+        glb[4] = 1;
+        let err = parse_gltf(&glb, Path::new("asset.glb"), grey())
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "asset.glb: unsupported GLB version 1");
+    }
+
+    #[test]
+    fn test_parse_gltf_errors() {
+        let error = |rest: &str, needle: &str| {
+            let text = gltf(rest);
+            let line = text.lines().position(|line| line.contains(needle)).unwrap() + 1;
+            let err = parse(&text).err().unwrap();
+            assert_eq!(err.line, Some(line), "{}", err);
+            err.message
+        };
+
+        assert_eq!(
+            error(r#""nodes": [{"mesh": 3}]"#, r#""mesh": 3"#),
+            "mesh 3 out of range, 1 defined"
+        );
+        assert_eq!(
+            error(
+                r#""scenes": [{"nodes": [0]}],
+  "nodes": [{"children": [1]},
+    {"children": [0], "mesh": 0}]"#,
+                r#""children": [0]"#
+            ),
+            "node 0 is its own ancestor"
+        );
+        assert_eq!(
+            error(
+                r#""nodes": [{"mesh": 0, "rotation": [0, 0, 1]}]"#,
+                "rotation"
+            ),
+            "expected 'rotation' to be 4 numbers"
+        );
+        assert_eq!(
+            error(
+                r#""cameras": [{"type": "perspective", "perspective": {"yfov": 0.5}}],
+  "nodes": [{"camera": 0, "scale": [1, 0, 1]}]"#,
+                "yfov"
+            ),
+            "invalid camera: vup is zero or parallel to the view direction"
+        );
+        assert_eq!(
+            error(
+                r#""cameras": [{"type": "perspective",
+    "perspective": {"yfov": 0.5, "aspectRatio": 0}}],
+  "nodes": [{"camera": 0}]"#,
+                "aspectRatio"
+            ),
+            "expected 'aspectRatio' to be positive"
+        );
+
+        // sizes too large for the buffers are errors, not overflows
+        let huge = |from: &str, to: &str| {
+            let text = gltf(r#""nodes": [{"mesh": 0}]"#).replace(from, to);
+            parse(&text).err().unwrap().message
+        };
+        assert_eq!(
+            huge(r#""byteOffset": 72"#, r#""byteOffset": 1e30"#),
+            "expected 'byteOffset' to be a whole number up to 80"
+        );
+        assert_eq!(
+            huge(
+                r#""bufferView": 1, "componentType": 5123, "count": 3"#,
+                r#""componentType": 5123, "count": 1e30"#
+            ),
+            "expected 'count' to be a whole number up to 80"
+        );
+        assert_eq!(
+            huge(r#""byteOffset": 36, "#, r#""byteOffset": 70, "#),
+            "accessor 1 reads past the end of its buffer view"
+        );
+        assert_eq!(
+            huge(
+                r#""count": 3, "type": "SCALAR""#,
+                r#""count": 3.5, "type": "SCALAR""#
+            ),
+            "expected 'count' to be a whole number up to 6"
+        );
+        assert_eq!(
+            parse(r#"{"asset": {"version": "1.0"}}"#)
+                .err()
+                .unwrap()
+                .message,
+            "expected a glTF 2.x asset"
+        );
+    }
+
+    #[test]
+    fn test_triangles_from_strips_and_fans() {
+        let indices = [0, 1, 2, 3, 4];
+        assert_eq!(triangles(&indices, 5), [[0, 1, 2], [2, 1, 3], [2, 3, 4]]);
+        assert_eq!(triangles(&indices, 6), [[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        assert_eq!(triangles(&indices, 4), [[0, 1, 2]]);
+    }
+}
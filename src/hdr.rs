@@ -0,0 +1,961 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{
+    color::Color,
+    render::{Framebuffer, ImageWriter},
+};
+
+// Radiance RGBE picture, written for `.hdr` paths.
+pub struct RgbeWriter;
+
+// Portable float map, written for `.pfm` paths.
+pub struct PfmWriter;
+
+// Single part scanline OpenEXR with 32-bit float RGB channels, written for
+// `.exr` paths.
+pub struct ExrWriter {
+    pub compression: ExrCompression,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExrCompression {
+    None,
+    Zip,
+}
+
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+pub(crate) fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let (r, g, b) = (
+        color.red.max(0.0),
+        color.green.max(0.0),
+        color.blue.max(0.0),
+    );
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // split v into a mantissa in [0.5, 1) and a power of two
+    let mut exponent = v.log2().floor() as i32 + 1;
+    let mut mantissa = v / 2f64.powi(exponent);
+    if mantissa >= 1.0 {
+        mantissa /= 2.0;
+        exponent += 1;
+    } else if mantissa < 0.5 {
+        mantissa *= 2.0;
+        exponent -= 1;
+    }
+    if exponent > 127 {
+        return [255, 255, 255, 255];
+    }
+    if exponent < -128 {
+        return [0, 0, 0, 0];
+    }
+
+    let scale = mantissa * 256.0 / v;
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+fn from_rgbe(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let f = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * f,
+        (rgbe[1] as f64 + 0.5) * f,
+        (rgbe[2] as f64 + 0.5) * f,
+    )
+}
+
+// Run length encodes one component of an RGBE scanline: runs are stored as
+// `128 + count, value`, everything else as `count, values...`.
+fn encode_rle_component(data: &[u8], out: &mut Vec<u8>) {
+    let run_at = |i: usize, limit: usize| {
+        let mut run = 1;
+        while i + run < data.len() && run < limit && data[i + run] == data[i] {
+            run += 1;
+        }
+        run
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let run = run_at(i, 127);
+        if run >= 4 {
+            out.push(128 + run as u8);
+            out.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        let start = i;
+        while i < data.len() && i - start < 128 && run_at(i, 4) < 4 {
+            i += 1;
+        }
+        out.push((i - start) as u8);
+        out.extend_from_slice(&data[start..i]);
+    }
+}
+
+impl ImageWriter for RgbeWriter {
+    fn write(&self, out: &mut dyn Write, image: &Framebuffer) -> io::Result<()> {
+        write!(
+            out,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            image.height, image.width
+        )?;
+
+        let run_length_encode = (8..=0x7fff).contains(&image.width);
+        for row in image.pixels.chunks(image.width.max(1)) {
+            let scanline: Vec<[u8; 4]> = row.iter().map(|color| to_rgbe(*color)).collect();
+            if !run_length_encode {
+                out.write_all(scanline.as_flattened())?;
+                continue;
+            }
+
+            let mut encoded = vec![2, 2, (image.width >> 8) as u8, image.width as u8];
+            for component in 0..4 {
+                let data: Vec<u8> = scanline.iter().map(|rgbe| rgbe[component]).collect();
+                encode_rle_component(&data, &mut encoded);
+            }
+            out.write_all(&encoded)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn read_rgbe(data: &[u8]) -> io::Result<Framebuffer> {
+    let mut pos = 0;
+    let next_line = |pos: &mut usize| -> io::Result<String> {
+        let end = data[*pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| invalid_data("truncated Radiance header"))?;
+        let line = String::from_utf8_lossy(&data[*pos..*pos + end]).into_owned();
+        *pos += end + 1;
+        Ok(line)
+    };
+
+    let magic = next_line(&mut pos)?;
+    if !magic.starts_with("#?") {
+        return Err(invalid_data("missing Radiance signature"));
+    }
+    loop {
+        let line = next_line(&mut pos)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!(
+                    "unsupported Radiance format {}",
+                    format
+                )));
+            }
+        }
+    }
+
+    let resolution = next_line(&mut pos)?;
+    let fields: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match fields.as_slice() {
+        ["-Y", height, "+X", width] => (height.parse::<usize>(), width.parse::<usize>()),
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported Radiance resolution line '{}'",
+                resolution
+            )))
+        }
+    };
+    let (height, width) = match (height, width) {
+        (Ok(height), Ok(width)) => (height, width),
+        _ => return Err(invalid_data("invalid Radiance resolution")),
+    };
+
+    let truncated = || invalid_data("truncated Radiance pixel data");
+    // a run length encoded scanline still takes two bytes per 127 pixels of
+    // each component, so the remaining data bounds the image size
+    let most = (data.len() - pos).saturating_mul(16);
+    if width > most
+        || height > most
+        || !matches!(width.checked_mul(height), Some(pixels) if pixels <= most)
+    {
+        return Err(truncated());
+    }
+    let mut image = Framebuffer::new(width, height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for y in 0..height {
+        let header = data
+            .get(pos..)
+            .and_then(|rest| rest.get(..4))
+            .ok_or_else(truncated)?;
+        let encoded = (8..=0x7fff).contains(&width)
+            && header[0] == 2
+            && header[1] == 2
+            && header[2] & 0x80 == 0;
+
+        if encoded {
+            if ((header[2] as usize) << 8 | header[3] as usize) != width {
+                return Err(invalid_data("Radiance scanline width mismatch"));
+            }
+            pos += 4;
+            for component in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = *data.get(pos).ok_or_else(truncated)? as usize;
+                    pos += 1;
+                    if count > 128 {
+                        let run = count - 128;
+                        let value = *data.get(pos).ok_or_else(truncated)?;
+                        pos += 1;
+                        if x + run > width {
+                            return Err(invalid_data("Radiance run overflows scanline"));
+                        }
+                        for pixel in &mut scanline[x..x + run] {
+                            pixel[component] = value;
+                        }
+                        x += run;
+                    } else {
+                        if count == 0 || x + count > width {
+                            return Err(invalid_data("invalid Radiance literal run"));
+                        }
+                        let values = data
+                            .get(pos..)
+                            .and_then(|rest| rest.get(..count))
+                            .ok_or_else(truncated)?;
+                        pos += count;
+                        for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                            pixel[component] = *value;
+                        }
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            let bytes = data
+                .get(pos..)
+                .and_then(|rest| rest.get(..width * 4))
+                .ok_or_else(truncated)?;
+            pos += width * 4;
+            for (pixel, rgbe) in scanline.iter_mut().zip(bytes.chunks_exact(4)) {
+                pixel.copy_from_slice(rgbe);
+            }
+        }
+
+        for (x, rgbe) in scanline.iter().enumerate() {
+            image.set(x, y, from_rgbe(*rgbe));
+        }
+    }
+
+    Ok(image)
+}
+
+impl ImageWriter for PfmWriter {
+    fn write(&self, out: &mut dyn Write, image: &Framebuffer) -> io::Result<()> {
+        // a negative scale marks little endian data, rows go bottom to top
+        write!(out, "PF\n{} {}\n-1.0\n", image.width, image.height)?;
+        for row in image.pixels.chunks(image.width.max(1)).rev() {
+            for color in row {
+                out.write_all(&(color.red as f32).to_le_bytes())?;
+                out.write_all(&(color.green as f32).to_le_bytes())?;
+                out.write_all(&(color.blue as f32).to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn read_pfm(data: &[u8]) -> io::Result<Framebuffer> {
+    let mut pos = 0;
+    let mut tokens = Vec::new();
+    while tokens.len() < 4 {
+        while data.get(pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        let start = pos;
+        while data.get(pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid_data("truncated PFM header"));
+        }
+        tokens.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+    }
+    // exactly one whitespace character separates the header from the data
+    pos += 1;
+
+    let channels = match tokens[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("missing PFM signature")),
+    };
+    let width: usize = tokens[1]
+        .parse()
+        .map_err(|_| invalid_data("invalid PFM width"))?;
+    let height: usize = tokens[2]
+        .parse()
+        .map_err(|_| invalid_data("invalid PFM height"))?;
+    let scale: f64 = tokens[3]
+        .parse()
+        .map_err(|_| invalid_data("invalid PFM scale"))?;
+    let little_endian = scale < 0.0;
+
+    let truncated = || invalid_data("truncated PFM pixel data");
+    let rest = data.get(pos..).ok_or_else(truncated)?;
+    if width > rest.len() || height > rest.len() {
+        return Err(truncated());
+    }
+    let floats = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels * 4))
+        .and_then(|expected| rest.get(..expected))
+        .ok_or_else(truncated)?;
+    let values: Vec<f64> = floats
+        .chunks_exact(4)
+        .map(|bytes| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            if little_endian {
+                f32::from_le_bytes(bytes) as f64
+            } else {
+                f32::from_be_bytes(bytes) as f64
+            }
+        })
+        .collect();
+
+    let mut image = Framebuffer::new(width, height);
+    for (row, y) in (0..height).rev().enumerate() {
+        for x in 0..width {
+            let i = (row * width + x) * channels;
+            let color = if channels == 3 {
+                Color::new(values[i], values[i + 1], values[i + 2])
+            } else {
+                Color::new(values[i], values[i], values[i])
+            };
+            image.set(x, y, color);
+        }
+    }
+    Ok(image)
+}
+
+fn write_exr_attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.extend_from_slice(kind.as_bytes());
+    out.push(0);
+    out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+// The ZIP compressor first splits the bytes into even and odd positions,
+// then delta encodes them, before deflating.
+fn exr_zip_compress(raw: &[u8]) -> io::Result<Vec<u8>> {
+    let half = raw.len().div_ceil(2);
+    let mut reordered = vec![0u8; raw.len()];
+    for (i, byte) in raw.iter().enumerate() {
+        if i % 2 == 0 {
+            reordered[i / 2] = *byte;
+        } else {
+            reordered[half + i / 2] = *byte;
+        }
+    }
+
+    let mut previous = reordered.first().copied().unwrap_or(0);
+    for byte in reordered.iter_mut().skip(1) {
+        let current = *byte;
+        *byte = current.wrapping_sub(previous).wrapping_add(128);
+        previous = current;
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&reordered)?;
+    encoder.finish()
+}
+
+fn exr_zip_decompress(compressed: &[u8], raw_size: usize) -> io::Result<Vec<u8>> {
+    let mut reordered = Vec::with_capacity(raw_size);
+    ZlibDecoder::new(compressed)
+        .take(raw_size as u64 + 1)
+        .read_to_end(&mut reordered)?;
+    if reordered.len() != raw_size {
+        return Err(invalid_data("corrupt OpenEXR ZIP block"));
+    }
+
+    for i in 1..reordered.len() {
+        reordered[i] = reordered[i - 1]
+            .wrapping_add(reordered[i])
+            .wrapping_sub(128);
+    }
+
+    let half = raw_size.div_ceil(2);
+    let mut raw = vec![0u8; raw_size];
+    for (i, byte) in raw.iter_mut().enumerate() {
+        *byte = if i % 2 == 0 {
+            reordered[i / 2]
+        } else {
+            reordered[half + i / 2]
+        };
+    }
+    Ok(raw)
+}
+
+impl ImageWriter for ExrWriter {
+    fn write(&self, out: &mut dyn Write, image: &Framebuffer) -> io::Result<()> {
+        let (compression, lines_per_block) = match self.compression {
+            ExrCompression::None => (0u8, 1),
+            ExrCompression::Zip => (3u8, 16),
+        };
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&EXR_MAGIC);
+        header.extend_from_slice(&2u32.to_le_bytes());
+
+        // channels are stored in alphabetical order
+        let mut channels = Vec::new();
+        for name in ["B", "G", "R"] {
+            channels.extend_from_slice(name.as_bytes());
+            channels.push(0);
+            channels.extend_from_slice(&2i32.to_le_bytes()); // FLOAT
+            channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear, reserved
+            channels.extend_from_slice(&1i32.to_le_bytes()); // xSampling
+            channels.extend_from_slice(&1i32.to_le_bytes()); // ySampling
+        }
+        channels.push(0);
+        write_exr_attribute(&mut header, "channels", "chlist", &channels);
+        write_exr_attribute(&mut header, "compression", "compression", &[compression]);
+
+        let mut window = Vec::new();
+        for value in [0, 0, image.width as i32 - 1, image.height as i32 - 1] {
+            window.extend_from_slice(&value.to_le_bytes());
+        }
+        write_exr_attribute(&mut header, "dataWindow", "box2i", &window);
+        write_exr_attribute(&mut header, "displayWindow", "box2i", &window);
+        write_exr_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+        write_exr_attribute(
+            &mut header,
+            "pixelAspectRatio",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        write_exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+        write_exr_attribute(
+            &mut header,
+            "screenWindowWidth",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        header.push(0);
+
+        let mut blocks = Vec::new();
+        for (index, rows) in image
+            .pixels
+            .chunks(image.width.max(1) * lines_per_block)
+            .enumerate()
+        {
+            let mut raw = Vec::with_capacity(rows.len() * 12);
+            for row in rows.chunks(image.width.max(1)) {
+                for channel in [|c: &Color| c.blue, |c: &Color| c.green, |c: &Color| c.red] {
+                    for color in row {
+                        raw.extend_from_slice(&(channel(color) as f32).to_le_bytes());
+                    }
+                }
+            }
+
+            let data = match self.compression {
+                ExrCompression::None => raw,
+                ExrCompression::Zip => {
+                    // blocks that do not shrink are stored uncompressed
+                    let compressed = exr_zip_compress(&raw)?;
+                    if compressed.len() < raw.len() {
+                        compressed
+                    } else {
+                        raw
+                    }
+                }
+            };
+
+            let mut block = Vec::with_capacity(data.len() + 8);
+            block.extend_from_slice(&((index * lines_per_block) as i32).to_le_bytes());
+            block.extend_from_slice(&(data.len() as i32).to_le_bytes());
+            block.extend_from_slice(&data);
+            blocks.push(block);
+        }
+
+        let mut offset = (header.len() + blocks.len() * 8) as u64;
+        out.write_all(&header)?;
+        for block in &blocks {
+            out.write_all(&offset.to_le_bytes())?;
+            offset += block.len() as u64;
+        }
+        for block in &blocks {
+            out.write_all(block)?;
+        }
+        Ok(())
+    }
+}
+
+fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half >> 15) as u32) << 31;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    let bits = match (exponent, mantissa) {
+        (0, 0) => sign,
+        (0, _) => {
+            // subnormal half, renormalize
+            let mut exponent = 127 - 15 + 1;
+            let mut mantissa = mantissa;
+            while mantissa & 0x400 == 0 {
+                mantissa <<= 1;
+                exponent -= 1;
+            }
+            sign | (exponent << 23) | ((mantissa & 0x3ff) << 13)
+        }
+        (0x1f, _) => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+struct ExrReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ExrReader<'a> {
+    fn bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..count))
+            .ok_or_else(|| invalid_data("truncated OpenEXR file"))?;
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        let bytes = self.bytes(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let end = self
+            .data
+            .get(self.pos..)
+            .unwrap_or_default()
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| invalid_data("truncated OpenEXR header"))?;
+        let string = String::from_utf8_lossy(&self.data[self.pos..self.pos + end]).into_owned();
+        self.pos += end + 1;
+        Ok(string)
+    }
+}
+
+pub fn read_exr(data: &[u8]) -> io::Result<Framebuffer> {
+    let mut reader = ExrReader { data, pos: 0 };
+    if reader.bytes(4)? != EXR_MAGIC {
+        return Err(invalid_data("missing OpenEXR signature"));
+    }
+    let version = reader.i32()?;
+    if version & 0xff != 2 || version & 0x1200 != 0 {
+        return Err(invalid_data(
+            "only single part scanline OpenEXR files are supported",
+        ));
+    }
+
+    let mut channels: Vec<(String, i32)> = Vec::new();
+    let mut compression = None;
+    let mut data_window = None;
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let _kind = reader.string()?;
+        let size = reader.i32()?;
+        let value = reader.bytes(size.max(0) as usize)?;
+        let mut attribute = ExrReader {
+            data: value,
+            pos: 0,
+        };
+        match name.as_str() {
+            "channels" => loop {
+                let channel = attribute.string()?;
+                if channel.is_empty() {
+                    break;
+                }
+                let pixel_type = attribute.i32()?;
+                attribute.bytes(12)?;
+                channels.push((channel, pixel_type));
+            },
+            "compression" => compression = value.first().copied(),
+            "dataWindow" => {
+                data_window = Some([
+                    attribute.i32()?,
+                    attribute.i32()?,
+                    attribute.i32()?,
+                    attribute.i32()?,
+                ])
+            }
+            _ => {}
+        }
+    }
+
+    let [x_min, y_min, x_max, y_max] =
+        data_window.ok_or_else(|| invalid_data("OpenEXR file has no dataWindow"))?;
+    let width = (x_max as i64 - x_min as i64 + 1).max(0) as usize;
+    let height = (y_max as i64 - y_min as i64 + 1).max(0) as usize;
+    let lines_per_block = match compression {
+        Some(0) | Some(2) => 1,
+        Some(3) => 16,
+        Some(other) => {
+            return Err(invalid_data(format!(
+                "unsupported OpenEXR compression {}",
+                other
+            )))
+        }
+        None => return Err(invalid_data("OpenEXR file has no compression attribute")),
+    };
+
+    // deflate expands data at most 1032 times, so the file bounds the image
+    let too_large = || invalid_data("OpenEXR data window is too large");
+    let most = data.len().saturating_mul(1032);
+    if width > most
+        || height > most
+        || !matches!(width.checked_mul(height), Some(pixels) if pixels <= most)
+    {
+        return Err(too_large());
+    }
+
+    let sample_size = |pixel_type: i32| if pixel_type == 1 { 2 } else { 4 };
+    let line_size = channels
+        .iter()
+        .try_fold(0usize, |sum, (_, pixel_type)| {
+            sum.checked_add(width.checked_mul(sample_size(*pixel_type))?)
+        })
+        .ok_or_else(too_large)?;
+
+    let block_count = height.div_ceil(lines_per_block);
+    if block_count > (data.len() - reader.pos) / 8 {
+        return Err(invalid_data("truncated OpenEXR offset table"));
+    }
+    let mut offsets = Vec::with_capacity(block_count);
+    for _ in 0..block_count {
+        offsets.push(reader.u64()? as usize);
+    }
+
+    let mut image = Framebuffer::new(width, height);
+    for offset in offsets {
+        let mut block = ExrReader { data, pos: offset };
+        let first_line = (block.i32()? as i64 - y_min as i64).max(0) as usize;
+        let size = block.i32()?.max(0) as usize;
+        let lines = lines_per_block.min(height.saturating_sub(first_line));
+        let raw_size = lines.checked_mul(line_size).ok_or_else(too_large)?;
+        let stored = block.bytes(size)?;
+        let raw = if size < raw_size {
+            exr_zip_decompress(stored, raw_size)?
+        } else {
+            stored.to_vec()
+        };
+
+        let mut samples = ExrReader { data: &raw, pos: 0 };
+        for line in 0..lines {
+            let y = first_line + line;
+            for (name, pixel_type) in &channels {
+                for x in 0..width {
+                    let bytes = samples.bytes(sample_size(*pixel_type))?;
+                    let value = match pixel_type {
+                        0 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                        1 => half_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])) as f64,
+                        _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                    };
+                    let mut color = image.get(x, y);
+                    match name.as_str() {
+                        "R" => color.red = value,
+                        "G" => color.green = value,
+                        "B" => color.blue = value,
+                        "Y" => color = Color::new(value, value, value),
+                        _ => {}
+                    }
+                    image.set(x, y, color);
+                }
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+// Reads a high dynamic range image, picking the format from the extension.
+pub fn read_hdr_image(path: &Path) -> io::Result<Framebuffer> {
+    let data = fs::read(path)?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("hdr") => read_rgbe(&data),
+        Some("pfm") => read_pfm(&data),
+        Some("exr") => read_exr(&data),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "unsupported high dynamic range image {}, expected .hdr, .pfm or .exr",
+                path.display()
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A gradient with values well outside [0, 1], wide enough to exercise
+    // run length encoding and multiple EXR blocks.
+    fn hdr_image() -> Framebuffer {
+        let mut image = Framebuffer::new(37, 21);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let color = if y == 5 {
+                    Color::new(3.5, 3.5, 3.5)
+                } else {
+                    Color::new(
+                        x as f64 * 0.75,
+                        y as f64 * 12.5 + 0.001,
+                        1.0 / (x + y + 1) as f64,
+                    )
+                };
+                image.set(x, y, color);
+            }
+        }
+        image
+    }
+
+    fn encode(writer: &dyn ImageWriter, image: &Framebuffer) -> Vec<u8> {
+        let mut out = Vec::new();
+        writer.write(&mut out, image).unwrap();
+        out
+    }
+
+    fn assert_close(expected: &Framebuffer, actual: &Framebuffer, tolerance: f64) {
+        assert_eq!(expected.width, actual.width);
+        assert_eq!(expected.height, actual.height);
+        for (e, a) in expected.pixels.iter().zip(&actual.pixels) {
+            for (e, a) in [(e.red, a.red), (e.green, a.green), (e.blue, a.blue)] {
+                assert!(
+                    (e - a).abs() <= tolerance * e.abs().max(1e-3),
+                    "expected {} got {}",
+                    e,
+                    a
+                );
+            }
+        }
+    }
+
+    // RGBE shares one exponent between the channels, so the error is
+    // relative to the brightest channel of each pixel.
+    fn assert_rgbe_close(expected: &Framebuffer, actual: &Framebuffer) {
+        assert_eq!(expected.width, actual.width);
+        assert_eq!(expected.height, actual.height);
+        for (e, a) in expected.pixels.iter().zip(&actual.pixels) {
+            let tolerance = e.red.max(e.green).max(e.blue) / 128.0;
+            for (e, a) in [(e.red, a.red), (e.green, a.green), (e.blue, a.blue)] {
+                assert!((e - a).abs() <= tolerance, "expected {} got {}", e, a);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rgbe_round_trip() {
+        let image = hdr_image();
+        let decoded = read_rgbe(&encode(&RgbeWriter, &image)).unwrap();
+
+        assert_rgbe_close(&image, &decoded);
+    }
+
+    #[test]
+    fn test_rgbe_round_trip_narrow_image_is_flat() {
+        let mut image = Framebuffer::new(3, 2);
+        image.set(1, 1, Color::new(100.0, 0.5, 0.0));
+        let encoded = encode(&RgbeWriter, &image);
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 3\n";
+
+        assert_eq!(encoded.len(), header.len() + 3 * 2 * 4);
+        assert_rgbe_close(&image, &read_rgbe(&encoded).unwrap());
+    }
+
+    #[test]
+    fn test_rgbe_clamps_negative_values() {
+        assert_eq!(to_rgbe(Color::new(-1.0, -2.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(from_rgbe([0, 0, 0, 0]), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_pfm_round_trip() {
+        let image = hdr_image();
+        let encoded = encode(&PfmWriter, &image);
+        let decoded = read_pfm(&encoded).unwrap();
+
+        assert!(encoded.starts_with(b"PF\n37 21\n-1.0\n"));
+        assert_close(&image, &decoded, 1e-6);
+    }
+
+    #[test]
+    fn test_pfm_big_endian_greyscale() {
+        let mut data = b"Pf\n2 1\n1.0\n".to_vec();
+        data.extend_from_slice(&0.25f32.to_be_bytes());
+        data.extend_from_slice(&8.0f32.to_be_bytes());
+
+        let decoded = read_pfm(&data).unwrap();
+
+        assert_eq!(decoded.get(0, 0), Color::new(0.25, 0.25, 0.25));
+        assert_eq!(decoded.get(1, 0), Color::new(8.0, 8.0, 8.0));
+    }
+
+    #[test]
+    fn test_exr_round_trip_uncompressed() {
+        let image = hdr_image();
+        let writer = ExrWriter {
+            compression: ExrCompression::None,
+        };
+        let decoded = read_exr(&encode(&writer, &image)).unwrap();
+
+        assert_close(&image, &decoded, 1e-6);
+    }
+
+    #[test]
+    fn test_exr_round_trip_zip() {
+        let image = hdr_image();
+        let uncompressed = encode(
+            &ExrWriter {
+                compression: ExrCompression::None,
+            },
+            &image,
+        );
+        let zip = encode(
+            &ExrWriter {
+                compression: ExrCompression::Zip,
+            },
+            &image,
+        );
+        let decoded = read_exr(&zip).unwrap();
+
+        assert!(zip.len() < uncompressed.len());
+        assert_close(&image, &decoded, 1e-6);
+    }
+
+    #[test]
+    fn test_exr_zip_predictor_round_trip() {
+        let raw: Vec<u8> = (0..1001u32).map(|i| (i * 7 % 251) as u8).collect();
+        let compressed = exr_zip_compress(&raw).unwrap();
+
+        assert_eq!(exr_zip_decompress(&compressed, raw.len()).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_read_truncated_images() {
+        let image = hdr_image();
+        let exr = ExrWriter {
+            compression: ExrCompression::Zip,
+        };
+        type Reader = fn(&[u8]) -> io::Result<Framebuffer>;
+        let readers: [(&dyn ImageWriter, Reader); 3] = [
+            (&RgbeWriter, read_rgbe),
+            (&PfmWriter, read_pfm),
+            (&exr, read_exr),
+        ];
+
+        for (writer, read) in readers {
+            let encoded = encode(writer, &image);
+            for len in (0..encoded.len()).step_by(7) {
+                assert!(read(&encoded[..len]).is_err(), "read {} bytes", len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_oversized_headers() {
+        let rgbe = b"#?RADIANCE\n\n-Y 4294967296 +X 4294967296\n\x02\x02\x00\x08";
+        assert!(read_rgbe(rgbe).is_err());
+        assert!(read_rgbe(b"#?RADIANCE\n\n-Y 18446744073709551615 +X 0\n").is_err());
+
+        assert!(read_pfm(b"PF\n18446744073709551615 2\n-1.0\n\0\0\0\0").is_err());
+        assert!(read_pfm(b"Pf\n0 18446744073709551615\n-1.0\n").is_err());
+        assert!(read_pfm(b"PF\n1 1\n-1.0").is_err());
+
+        let mut exr = encode(
+            &ExrWriter {
+                compression: ExrCompression::None,
+            },
+            &hdr_image(),
+        );
+        let window = b"dataWindow\0box2i\0";
+        let start = exr
+            .windows(window.len())
+            .position(|bytes| bytes == window)
+            .unwrap()
+            + window.len()
+            + 4;
+        for (i, value) in [i32::MIN, i32::MIN, i32::MAX, i32::MAX].iter().enumerate() {
+            exr[start + i * 4..start + i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        assert!(read_exr(&exr).is_err());
+
+        // an empty data window with a huge height must not size the offsets
+        for (i, value) in [0, i32::MIN, -1, i32::MAX].iter().enumerate() {
+            exr[start + i * 4..start + i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        assert!(read_exr(&exr).is_err());
+    }
+
+    #[test]
+    fn test_half_to_f32() {
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x3555), 0.333_251_95);
+        assert_eq!(half_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_read_hdr_image_from_files() {
+        let image = hdr_image();
+        let dir = std::env::temp_dir().join(format!("raytracing-hdr-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for name in ["image.hdr", "image.pfm", "image.exr"] {
+            let path = dir.join(name);
+            crate::render::write_image(&path, &image, Default::default()).unwrap();
+            let decoded = read_hdr_image(&path).unwrap();
+            assert_rgbe_close(&image, &decoded);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(read_hdr_image(Path::new("image.png")).is_err());
+    }
+}
@@ -1,5 +1,10 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
 use crate::interval::Interval;
-use crate::Ray;
+use crate::material::Material;
+use crate::random::Sampler;
+use crate::ray::Ray;
 use crate::{point::Point3, vector::Vector3};
 
 #[derive(Clone)]
@@ -7,7 +12,17 @@ pub struct HitRecord {
     pub p: Point3,
     pub normal: Vector3,
     pub t: f64,
+    // surface coordinates of the hit, used for texture lookups
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
+    pub mat: Option<Arc<dyn Material>>,
+}
+
+impl Default for HitRecord {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HitRecord {
@@ -16,7 +31,10 @@ impl HitRecord {
             p: Point3::new(0.0, 0.0, 0.0),
             normal: Vector3::new(0.0, 0.0, 0.0),
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
             front_face: false,
+            mat: None,
         }
     }
 
@@ -30,6 +48,49 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool;
+
+    fn bounding_box(&self) -> Aabb;
+
+    // Whether the object implements `pdf_value` and `random`, which only
+    // objects that are sampled as lights need.
+    fn is_samplable(&self) -> bool {
+        false
+    }
+
+    // Density, over solid angle as seen from `origin`, with which `random`
+    // picks `direction`.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vector3) -> f64 {
+        0.0
+    }
+
+    // A direction from `origin` towards a random point on the object.
+    fn random(&self, _origin: &Point3, _rng: &mut Sampler) -> Vector3 {
+        Vector3::new(1.0, 0.0, 0.0)
+    }
+}
+
+// Lets several owners, such as instances placed by `Transformed`, share one
+// object.
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+        (**self).hit(r, int, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn is_samplable(&self) -> bool {
+        (**self).is_samplable()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut Sampler) -> Vector3 {
+        (**self).random(origin, rng)
+    }
 }
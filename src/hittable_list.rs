@@ -1,21 +1,43 @@
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
-use crate::Ray;
+use crate::random::{self, Sampler};
+use crate::ray::Ray;
+use crate::{point::Point3, vector::Vector3};
 
 pub struct HittableList {
     objects: Vec<Box<dyn Hittable>>,
+    // indices of the objects light sampling picks from
+    samplable: Vec<usize>,
+    bbox: Aabb,
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HittableList {
     pub fn new() -> Self {
         HittableList {
             objects: Vec::new(),
+            samplable: Vec::new(),
+            bbox: Aabb::EMPTY,
         }
     }
 
     pub fn add(&mut self, object: Box<dyn Hittable>) {
+        self.bbox = Aabb::surrounding(&self.bbox, &object.bounding_box());
+        if object.is_samplable() {
+            self.samplable.push(self.objects.len());
+        }
         self.objects.push(object);
     }
+
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
+    }
 }
 
 impl Hittable for HittableList {
@@ -34,4 +56,36 @@ impl Hittable for HittableList {
 
         hit_anything
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_samplable(&self) -> bool {
+        !self.samplable.is_empty()
+    }
+
+    // Sampling picks one of the samplable objects uniformly, so the density
+    // is the average of theirs. The others are left out, rather than taking
+    // a share of the samples they cannot make use of.
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3) -> f64 {
+        if self.samplable.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .samplable
+            .iter()
+            .map(|&index| self.objects[index].pdf_value(origin, direction))
+            .sum();
+        sum / self.samplable.len() as f64
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut Sampler) -> Vector3 {
+        if self.samplable.is_empty() {
+            return Vector3::new(1.0, 0.0, 0.0);
+        }
+        let pick = (random::random_double(rng) * self.samplable.len() as f64) as usize;
+        let index = self.samplable[pick.min(self.samplable.len() - 1)];
+        self.objects[index].random(origin, rng)
+    }
 }
@@ -0,0 +1,628 @@
+use crate::{
+    camera::Background,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    progress,
+    random::{self, Sampler},
+    ray::Ray,
+};
+
+// Path tracing with next-event estimation: at every diffuse bounce one of
+// `lights` is sampled directly with a shadow ray, and the light found by the
+// scattered ray is weighted against that with the power heuristic, so that
+// each strategy counts where it is the better one. With no lights this is
+// plain bounce-until-you-hit-a-light path tracing. Backgrounds with bright
+// parts, environment maps and the sun of a physical sky, are sampled the
+// same way, as one more light.
+//
+// Paths are followed in a loop carrying their throughput. Past `min_depth`
+// bounces they are cut short by Russian roulette, which survivors make up
+// for, so long paths cost little without darkening the image; `max_depth`
+// is only a safety net.
+pub struct Integrator<'a> {
+    world: &'a dyn Hittable,
+    lights: &'a dyn Hittable,
+    background: &'a Background,
+    max_depth: i32,
+    min_depth: i32,
+}
+
+impl<'a> Integrator<'a> {
+    pub const DEFAULT_MIN_DEPTH: i32 = 3;
+
+    pub fn new(
+        world: &'a dyn Hittable,
+        lights: &'a dyn Hittable,
+        background: &'a Background,
+        max_depth: i32,
+    ) -> Self {
+        Integrator {
+            world,
+            lights,
+            background,
+            max_depth,
+            min_depth: Self::DEFAULT_MIN_DEPTH,
+        }
+    }
+
+    // Number of bounces every path makes, unless it leaves the scene, before
+    // Russian roulette may end it.
+    pub fn min_depth(mut self, min_depth: i32) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    // Radiance arriving at the origin of `r` from its direction.
+    pub fn radiance(&self, r: &Ray, rng: &mut Sampler) -> Color {
+        let mut radiance = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = Ray::new(*r.origin(), *r.direction());
+        // density with which the previous bounce scattered into `ray`, None
+        // for camera rays and specular bounces, which light sampling never
+        // competes with
+        let mut bsdf_pdf: Option<f64> = None;
+
+        for depth in 0..self.max_depth {
+            let mut rec = HitRecord::new();
+            progress::count_ray();
+            if !self
+                .world
+                .hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec)
+            {
+                let mut background = self.background.color(&ray);
+                if let Some(bsdf_pdf) = bsdf_pdf {
+                    let light_pdf = self.background.pdf_value(ray.direction());
+                    background = background * power_heuristic(bsdf_pdf, light_pdf);
+                }
+                radiance = radiance + throughput * background;
+                break;
+            }
+            let Some(mat) = rec.mat.clone() else {
+                break;
+            };
+
+            let mut emitted = mat.emitted(rec.u, rec.v, &rec.p);
+            if let Some(bsdf_pdf) = bsdf_pdf {
+                let light_pdf = self.lights.pdf_value(ray.origin(), ray.direction());
+                emitted = emitted * power_heuristic(bsdf_pdf, light_pdf);
+            }
+            radiance = radiance + throughput * emitted;
+
+            let mut scattered = Ray::new(rec.p, rec.normal);
+            let mut attenuation = Color::new(0.0, 0.0, 0.0);
+            if !mat.scatter(&ray, &rec, &mut attenuation, &mut scattered, rng) {
+                break;
+            }
+
+            let pdf = mat.scattering_pdf(&ray, &rec, &scattered);
+            if pdf > 0.0 {
+                let direct = self.sample_light(&ray, &rec, mat.as_ref(), attenuation, rng)
+                    + self.sample_background(&ray, &rec, mat.as_ref(), attenuation, rng);
+                radiance = radiance + throughput * direct;
+                bsdf_pdf = Some(pdf);
+            } else {
+                bsdf_pdf = None;
+            }
+            throughput = throughput * attenuation;
+
+            if depth + 1 >= self.min_depth {
+                let survival = survival_probability(throughput);
+                if random::random_double(rng) >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+            ray = scattered;
+        }
+        radiance
+    }
+
+    // Light reaching `rec` along a direction picked by the lights. The
+    // attenuation of diffuse materials does not depend on the direction, so
+    // the one from scattering is reused.
+    fn sample_light(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        mat: &dyn Material,
+        attenuation: Color,
+        rng: &mut Sampler,
+    ) -> Color {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let direction = self.lights.random(&rec.p, rng);
+        let light_pdf = self.lights.pdf_value(&rec.p, &direction);
+        if light_pdf <= 0.0 {
+            return black;
+        }
+        let shadow = Ray::new(rec.p, direction);
+        let bsdf_pdf = mat.scattering_pdf(r_in, rec, &shadow);
+        if bsdf_pdf <= 0.0 {
+            return black;
+        }
+
+        let mut light = HitRecord::new();
+        progress::count_ray();
+        if !self
+            .world
+            .hit(&shadow, Interval::new(0.001, f64::INFINITY), &mut light)
+        {
+            return black;
+        }
+        let Some(light_mat) = &light.mat else {
+            return black;
+        };
+        let emitted = light_mat.emitted(light.u, light.v, &light.p);
+        attenuation * emitted * (bsdf_pdf * power_heuristic(light_pdf, bsdf_pdf) / light_pdf)
+    }
+
+    // Light reaching `rec` from the background along a direction drawn from
+    // its brightest parts, such as the sun, if the scene does not block it.
+    fn sample_background(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        mat: &dyn Material,
+        attenuation: Color,
+        rng: &mut Sampler,
+    ) -> Color {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let Some(direction) = self.background.random(rng) else {
+            return black;
+        };
+        let light_pdf = self.background.pdf_value(&direction);
+        if light_pdf <= 0.0 {
+            return black;
+        }
+        let shadow = Ray::new(rec.p, direction);
+        let bsdf_pdf = mat.scattering_pdf(r_in, rec, &shadow);
+        if bsdf_pdf <= 0.0 {
+            return black;
+        }
+        let mut blocker = HitRecord::new();
+        progress::count_ray();
+        if self
+            .world
+            .hit(&shadow, Interval::new(0.001, f64::INFINITY), &mut blocker)
+        {
+            return black;
+        }
+        attenuation
+            * self.background.color(&shadow)
+            * (bsdf_pdf * power_heuristic(light_pdf, bsdf_pdf) / light_pdf)
+    }
+}
+
+// Chance of carrying on with a path of the given throughput: dim paths,
+// which add little, are likely to stop. The cap keeps the weight of the
+// survivors bounded even for materials that reflect everything.
+fn survival_probability(throughput: Color) -> f64 {
+    throughput
+        .red
+        .max(throughput.green)
+        .max(throughput.blue)
+        .clamp(0.05, 0.95)
+}
+
+// Weight of a sample drawn with density `pdf` when `other` could have drawn
+// it too.
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f64::consts::PI, sync::Arc};
+
+    use super::*;
+    use crate::{
+        aabb::Aabb,
+        bvh::BvhNode,
+        environment::EnvironmentMap,
+        hittable_list::HittableList,
+        material::Metal,
+        material::{DiffuseLight, Lambertian},
+        point::Point3,
+        quad::Quad,
+        render::Framebuffer,
+        sky::PhysicalSky,
+        sphere::Sphere,
+        triangle::Triangle,
+        vector::Vector3,
+    };
+
+    struct MockHittable {
+        should_hit: bool,
+        normal: Vector3,
+    }
+
+    impl Hittable for MockHittable {
+        fn hit(&self, _ray: &Ray, _int: Interval, rec: &mut HitRecord) -> bool {
+            if self.should_hit {
+                rec.normal = self.normal;
+                rec.p = Point3::new(1.0, 1.0, 1.0);
+                rec.mat = Some(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))));
+                true
+            } else {
+                false
+            }
+        }
+
+        fn bounding_box(&self) -> Aabb {
+            Aabb::new(Interval::UNIVERSE, Interval::UNIVERSE, Interval::UNIVERSE)
+        }
+    }
+
+    fn radiance(world: &dyn Hittable, background: &Background, depth: i32, r: &Ray) -> Color {
+        let lights = HittableList::new();
+        Integrator::new(world, &lights, background, depth)
+            .radiance(r, &mut random::sampler(0, 0, 0))
+    }
+
+    #[test]
+    fn test_radiance_no_hit() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let world = MockHittable {
+            should_hit: false,
+            normal: Vector3::new(0.0, 0.0, 0.0),
+        };
+        let color = radiance(&world, &Background::Sky, 1, &ray);
+        assert!((color.red - 0.5).abs() < f64::EPSILON);
+        assert!((color.green - 0.7).abs() < f64::EPSILON);
+        assert!((color.blue - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_radiance_hit() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let world = MockHittable {
+            should_hit: true,
+            normal: Vector3::new(0.0, 0.0, 1.0),
+        };
+        let color = radiance(&world, &Background::Sky, 1, &ray);
+        assert!(color.red >= 0.0 && color.red <= 0.5);
+        assert!(color.green >= 0.0 && color.green <= 0.5);
+        assert!(color.blue >= 0.0 && color.blue <= 0.5);
+    }
+
+    #[test]
+    fn test_radiance_is_reproducible_from_seed() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let world = MockHittable {
+            should_hit: true,
+            normal: Vector3::new(0.0, 0.0, 1.0),
+        };
+        let lights = HittableList::new();
+        let integrator = Integrator::new(&world, &lights, &Background::Sky, 5);
+        let a = integrator.radiance(&ray, &mut random::sampler(3, 1, 4));
+        let b = integrator.radiance(&ray, &mut random::sampler(3, 1, 4));
+
+        assert_eq!(a.red.to_bits(), b.red.to_bits());
+        assert_eq!(a.green.to_bits(), b.green.to_bits());
+        assert_eq!(a.blue.to_bits(), b.blue.to_bits());
+    }
+
+    #[test]
+    fn test_radiance_solid_background() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let world = MockHittable {
+            should_hit: false,
+            normal: Vector3::new(0.0, 0.0, 0.0),
+        };
+        let background = Background::Solid(Color::new(0.2, 0.3, 0.4));
+        assert_eq!(
+            radiance(&world, &background, 1, &ray),
+            Color::new(0.2, 0.3, 0.4)
+        );
+    }
+
+    #[test]
+    fn test_radiance_light_seen_directly() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let light = Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            0.5,
+            Arc::new(DiffuseLight::new(Color::new(3.0, 2.0, 1.0))),
+        );
+        assert_eq!(
+            radiance(&light, &Background::BLACK, 5, &ray),
+            Color::new(3.0, 2.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_radiance_max_depth() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let world = MockHittable {
+            should_hit: true,
+            normal: Vector3::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(
+            radiance(&world, &Background::Sky, 0, &ray),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_power_heuristic() {
+        assert_eq!(power_heuristic(1.0, 0.0), 1.0);
+        assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+        assert!((power_heuristic(1.0, 1.0) - 0.5).abs() < 1e-12);
+        assert!((power_heuristic(2.0, 1.0) + power_heuristic(1.0, 2.0) - 1.0).abs() < 1e-12);
+    }
+
+    // A grey floor under a small bright sphere, seen straight down.
+    fn small_light_scene() -> (HittableList, HittableList) {
+        let grey = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let emit = Color::new(50.0, 50.0, 50.0);
+        let (a, b, c, d) = (
+            Point3::new(-10.0, 0.0, -10.0),
+            Point3::new(10.0, 0.0, -10.0),
+            Point3::new(10.0, 0.0, 10.0),
+            Point3::new(-10.0, 0.0, 10.0),
+        );
+        let mut world = HittableList::new();
+        world.add(Box::new(Triangle::new(a, c, b, grey.clone())));
+        world.add(Box::new(Triangle::new(a, d, c, grey)));
+        let light = |emit| {
+            Sphere::new(
+                Point3::new(0.5, 2.0, 0.0),
+                0.1,
+                Arc::new(DiffuseLight::new(emit)),
+            )
+        };
+        world.add(Box::new(light(emit)));
+
+        let mut lights = HittableList::new();
+        lights.add(Box::new(light(emit)));
+        (world, lights)
+    }
+
+    // Mean and variance of the red channel over `samples` independent paths.
+    fn estimate(integrator: &Integrator, r: &Ray, samples: u64) -> (f64, f64) {
+        let values: Vec<f64> = (0..samples)
+            .map(|sample| {
+                integrator
+                    .radiance(r, &mut random::sampler(11, 0, sample))
+                    .red
+            })
+            .collect();
+        let mean = values.iter().sum::<f64>() / samples as f64;
+        let variance =
+            values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / (samples - 1) as f64;
+        (mean, variance)
+    }
+
+    #[test]
+    fn test_light_sampling_lowers_variance_without_bias() {
+        let (world, lights) = small_light_scene();
+        let no_lights = HittableList::new();
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let samples = 20_000;
+
+        let naive = Integrator::new(&world, &no_lights, &Background::BLACK, 4);
+        let nee = Integrator::new(&world, &lights, &Background::BLACK, 4);
+        let (naive_mean, naive_variance) = estimate(&naive, &r, samples);
+        let (nee_mean, nee_variance) = estimate(&nee, &r, samples);
+
+        // both converge to the same value, within four standard errors
+        let error = ((naive_variance + nee_variance) / samples as f64).sqrt();
+        assert!(
+            (naive_mean - nee_mean).abs() < 4.0 * error,
+            "naive {} vs nee {} (standard error {})",
+            naive_mean,
+            nee_mean,
+            error
+        );
+        assert!(
+            nee_variance * 20.0 < naive_variance,
+            "variance naive {} vs nee {}",
+            naive_variance,
+            nee_variance
+        );
+    }
+
+    #[test]
+    fn test_light_sampling_through_a_bvh() {
+        let (world, lights) = small_light_scene();
+        // the empty list cannot be sampled, so the light keeps every sample
+        let (_, mut objects) = small_light_scene();
+        objects.add(Box::new(HittableList::new()));
+        let bvh = BvhNode::new(objects);
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        let list = Integrator::new(&world, &lights, &Background::BLACK, 4);
+        let tree = Integrator::new(&world, &bvh, &Background::BLACK, 4);
+
+        let samples = 5_000;
+        let (list_mean, list_variance) = estimate(&list, &r, samples);
+        let (tree_mean, tree_variance) = estimate(&tree, &r, samples);
+
+        let error = ((list_variance + tree_variance) / samples as f64).sqrt();
+        assert!((list_mean - tree_mean).abs() < 4.0 * error);
+        assert!(
+            tree_variance < 2.0 * list_variance,
+            "variance list {} vs bvh {}",
+            list_variance,
+            tree_variance
+        );
+    }
+
+    // A diffuse room with a small lamp in it, where most of the light
+    // reaches the camera after many bounces.
+    fn closed_room() -> (HittableList, HittableList) {
+        let lamp = || {
+            Sphere::new(
+                Point3::new(0.0, 0.5, 0.0),
+                0.1,
+                Arc::new(DiffuseLight::new(Color::new(10.0, 10.0, 10.0))),
+            )
+        };
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            2.0,
+            Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))),
+        )));
+        world.add(Box::new(lamp()));
+        let mut lights = HittableList::new();
+        lights.add(Box::new(lamp()));
+        (world, lights)
+    }
+
+    #[test]
+    fn test_russian_roulette_is_unbiased() {
+        let (world, lights) = closed_room();
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let samples = 20_000;
+
+        let full = Integrator::new(&world, &lights, &Background::BLACK, 64).min_depth(64);
+        let roulette = Integrator::new(&world, &lights, &Background::BLACK, 64).min_depth(1);
+        let (full_mean, full_variance) = estimate(&full, &r, samples);
+        let (roulette_mean, roulette_variance) = estimate(&roulette, &r, samples);
+
+        let error = ((full_variance + roulette_variance) / samples as f64).sqrt();
+        assert!(
+            (full_mean - roulette_mean).abs() < 4.0 * error,
+            "full {} vs roulette {} (standard error {})",
+            full_mean,
+            roulette_mean,
+            error
+        );
+        // the light reflected between the walls matters: one bounce alone
+        // would be far darker
+        let single = Integrator::new(&world, &lights, &Background::BLACK, 1);
+        let (single_mean, _) = estimate(&single, &r, samples);
+        assert!(full_mean > 2.0 * single_mean);
+    }
+
+    #[test]
+    fn test_deep_paths_do_not_grow_the_stack() {
+        // light bouncing between two facing mirrors forever
+        let mirror = Arc::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0));
+        let mut world = HittableList::new();
+        for z in [-1.0, 1.0] {
+            let (a, b, c) = (
+                Point3::new(-1.0, -1.0, z),
+                Point3::new(1.0, -1.0, z),
+                Point3::new(0.0, 1.0, z),
+            );
+            world.add(Box::new(Triangle::new(a, b, c, mirror.clone())));
+        }
+        let lights = HittableList::new();
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let integrator =
+            Integrator::new(&world, &lights, &Background::Sky, 200_000).min_depth(200_000);
+        assert_eq!(
+            integrator.radiance(&r, &mut random::sampler(0, 0, 0)),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_environment_sampling_finds_the_sun() {
+        // a dim sky with one bright pixel above the horizon over a large
+        // diffuse floor
+        let (width, height) = (16, 8);
+        let mut image = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set(x, y, Color::new(0.1, 0.1, 0.1));
+            }
+        }
+        image.set(5, 2, Color::new(200.0, 200.0, 200.0));
+        let background =
+            Background::Environment(Arc::new(EnvironmentMap::new(image.clone(), 0.0, 1.0)));
+        let mut world = HittableList::new();
+        world.add(Box::new(Quad::new(
+            Point3::new(-1000.0, 0.0, 1000.0),
+            Vector3::new(2000.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -2000.0),
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        )));
+        let lights = HittableList::new();
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        // albedo / pi times the cosine weighted integral of the upper half
+        // of the map, whose rows span angles from the zenith in steps of
+        // pi / height
+        let mut expected = 0.0;
+        for y in 0..height / 2 {
+            let (a, b) = (
+                PI * y as f64 / height as f64,
+                PI * (y + 1) as f64 / height as f64,
+            );
+            let weight = 2.0 * PI / width as f64 * (b.sin().powi(2) - a.sin().powi(2)) / 2.0;
+            for x in 0..width {
+                expected += image.get(x, y).red * weight;
+            }
+        }
+        expected *= 0.5 / PI;
+
+        let samples = 20_000;
+        let integrator = Integrator::new(&world, &lights, &background, 2).min_depth(2);
+        let (mean, variance) = estimate(&integrator, &r, samples);
+        let error = (variance / samples as f64).sqrt();
+        assert!(
+            (mean - expected).abs() < 4.0 * error,
+            "{} != {} (standard error {})",
+            mean,
+            expected,
+            error
+        );
+        // scattered rays alone would find the sun about once in fifty
+        // tries, giving a standard deviation several times the mean
+        assert!(
+            variance.sqrt() < mean,
+            "mean {} variance {}",
+            mean,
+            variance
+        );
+    }
+
+    #[test]
+    fn test_sun_is_sampled_directly() {
+        // a diffuse floor under the sky reflects as much as the model's own
+        // ground of the same albedo
+        let albedo = Color::new(0.5, 0.5, 0.5);
+        let sky = Arc::new(PhysicalSky::new(Vector3::new(0.4, 1.0, -0.3), 3.0, albedo));
+        let expected = sky.color(&Vector3::new(0.0, -1.0, 0.0)).green;
+        let background = Background::PhysicalSky(sky);
+        let mut world = HittableList::new();
+        world.add(Box::new(Quad::new(
+            Point3::new(-1000.0, 0.0, 1000.0),
+            Vector3::new(2000.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -2000.0),
+            Arc::new(Lambertian::new(albedo)),
+        )));
+        let lights = HittableList::new();
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        let samples = 4_000;
+        let integrator = Integrator::new(&world, &lights, &background, 2).min_depth(2);
+        let values: Vec<f64> = (0..samples)
+            .map(|sample| {
+                integrator
+                    .radiance(&r, &mut random::sampler(12, 0, sample))
+                    .green
+            })
+            .collect();
+        let mean = values.iter().sum::<f64>() / samples as f64;
+        // the sky is integrated numerically for the model's ground, so
+        // allow a percent on top of the noise
+        assert!(
+            (mean - expected).abs() < 0.01 * expected,
+            "{} != {}",
+            mean,
+            expected
+        );
+        // a sun this small would almost never be hit by chance
+        assert!(values.iter().all(|&v| v < 2.0 * expected));
+    }
+}
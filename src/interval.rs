@@ -11,6 +11,13 @@ impl Interval {
         Interval { min, max }
     }
 
+    pub fn enclosing(a: &Interval, b: &Interval) -> Self {
+        Interval {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }
+    }
+
     pub fn size(&self) -> f64 {
         self.max - self.min
     }
@@ -33,6 +40,11 @@ impl Interval {
         }
     }
 
+    pub fn expand(&self, delta: f64) -> Self {
+        let padding = delta / 2.0;
+        Interval::new(self.min - padding, self.max + padding)
+    }
+
     pub const EMPTY: Self = Interval {
         min: f64::INFINITY,
         max: f64::NEG_INFINITY,
@@ -49,3 +61,38 @@ impl Default for Interval {
         Self::EMPTY
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_enclosing() {
+        let a = Interval::new(-1.0, 2.0);
+        let b = Interval::new(0.0, 5.0);
+
+        let enclosing = Interval::enclosing(&a, &b);
+
+        assert_eq!(enclosing.min, -1.0);
+        assert_eq!(enclosing.max, 5.0);
+    }
+
+    #[test]
+    fn test_interval_enclosing_empty() {
+        let a = Interval::new(1.0, 2.0);
+
+        let enclosing = Interval::enclosing(&a, &Interval::EMPTY);
+
+        assert_eq!(enclosing.min, 1.0);
+        assert_eq!(enclosing.max, 2.0);
+    }
+
+    #[test]
+    fn test_interval_expand() {
+        let expanded = Interval::new(1.0, 2.0).expand(1.0);
+
+        assert_eq!(expanded.min, 0.5);
+        assert_eq!(expanded.max, 2.5);
+        assert_eq!(expanded.size(), 2.0);
+    }
+}
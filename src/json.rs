@@ -0,0 +1,425 @@
+use std::{fmt, iter::Peekable, str::Chars};
+
+// A minimal JSON reader that remembers the line every value and key starts
+// on, so that callers can point at the offending line when the content is
+// well-formed JSON but not what they expected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json {
+    pub value: Value,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<Member>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub key: String,
+    pub line: usize,
+    pub value: Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        self.as_object()?
+            .iter()
+            .find(|member| member.key == key)
+            .map(|member| &member.value)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.value {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match &self.value {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[Member]> {
+        match &self.value {
+            Value::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    // Name of the value's type for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self.value {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "an object",
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Json, ParseError> {
+    let mut parser = Parser {
+        chars: text.chars().peekable(),
+        line: 1,
+        column: 1,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        None => Ok(value),
+        Some(_) => Err(parser.error("unexpected content after the top-level value")),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+    // arrays and objects the parser is inside of
+    depth: usize,
+}
+
+impl Parser<'_> {
+    // Deep enough for any sensible document, shallow enough that the
+    // recursion cannot overflow the stack.
+    const MAX_DEPTH: usize = 128;
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.chars.peek() {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.chars.peek() {
+            Some(&c) if c == expected => {
+                self.next();
+                Ok(())
+            }
+            Some(&c) => Err(self.error(format!("expected '{}', found '{}'", expected, c))),
+            None => Err(self.error(format!("expected '{}', found end of input", expected))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, ParseError> {
+        self.skip_whitespace();
+        let line = self.line;
+        if matches!(self.chars.peek(), Some('{' | '[')) && self.depth == Self::MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        let value = match self.chars.peek() {
+            Some('{') => self.nested(Self::object)?,
+            Some('[') => self.nested(Self::array)?,
+            Some('"') => Value::String(self.string()?),
+            Some('-' | '0'..='9') => self.number()?,
+            Some('t' | 'f' | 'n') => self.literal()?,
+            Some(&c) => return Err(self.error(format!("unexpected character '{}'", c))),
+            None => return Err(self.error("unexpected end of input")),
+        };
+        Ok(Json { value, line })
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Value, ParseError>,
+    ) -> Result<Value, ParseError> {
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Value, ParseError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let line = self.line;
+            if self.chars.peek() != Some(&'"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            members.push(Member { key, line, value });
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(members)),
+                _ => return Err(self.error("expected ',' or '}' in object")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected ',' or ']' in array")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.next() {
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('/') => string.push('/'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('u') => string.push(self.unicode_escape()?),
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(self.error("control character in string"))
+                }
+                Some(c) => string.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid \\u escape"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            // surrogate pair
+            if self.next() != Some('\\') || self.next() != Some('u') {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))
+    }
+
+    fn number(&mut self) -> Result<Value, ParseError> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E') {
+                break;
+            }
+            text.push(c);
+            self.next();
+        }
+        match text.parse::<f64>() {
+            Ok(number) if is_json_number(&text) => Ok(Value::Number(number)),
+            _ => Err(self.error(format!("invalid number '{}'", text))),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Value, ParseError> {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_alphabetic() {
+                break;
+            }
+            word.push(c);
+            self.next();
+        }
+        match word.as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            "null" => Ok(Value::Null),
+            _ => Err(self.error(format!("unexpected literal '{}'", word))),
+        }
+    }
+}
+
+// Rust's float syntax accepts more than JSON does ("1.", ".5", "+1", "01").
+fn is_json_number(text: &str) -> bool {
+    fn digits(bytes: &[u8]) -> usize {
+        bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+    }
+
+    let bytes = text.as_bytes();
+    let mut i = usize::from(bytes.first() == Some(&b'-'));
+    let integer = digits(&bytes[i..]);
+    if integer == 0 || (integer > 1 && bytes[i] == b'0') {
+        return false;
+    }
+    i += integer;
+    if bytes.get(i) == Some(&b'.') {
+        let fraction = digits(&bytes[i + 1..]);
+        if fraction == 0 {
+            return false;
+        }
+        i += 1 + fraction;
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(bytes.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        let exponent = digits(&bytes[i..]);
+        if exponent == 0 {
+            return false;
+        }
+        i += exponent;
+    }
+    i == bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        let json = parse(r#"{"a": [1, -2.5e3, true, false, null], "b": {"c": "d"}}"#).unwrap();
+        let a = json.get("a").unwrap().as_array().unwrap();
+        assert_eq!(a[0].as_f64(), Some(1.0));
+        assert_eq!(a[1].as_f64(), Some(-2500.0));
+        assert_eq!(a[2].value, Value::Bool(true));
+        assert_eq!(a[3].value, Value::Bool(false));
+        assert_eq!(a[4].value, Value::Null);
+        assert_eq!(json.get("b").unwrap().get("c").unwrap().as_str(), Some("d"));
+        assert!(json.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_parse_string_escapes() {
+        let json = parse(r#""a\"b\\c\/\n\t\u00e9\ud83d\ude00""#).unwrap();
+        assert_eq!(json.as_str(), Some("a\"b\\c/\n\té\u{1f600}"));
+    }
+
+    #[test]
+    fn test_parse_tracks_lines() {
+        let json = parse("{\n  \"a\": 1,\n\n  \"b\":\n    [2]\n}").unwrap();
+        let members = json.as_object().unwrap();
+        assert_eq!(json.line, 1);
+        assert_eq!((members[0].line, members[0].value.line), (2, 2));
+        assert_eq!((members[1].line, members[1].value.line), (4, 5));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 7));
+        assert_eq!(error.message, "expected ':', found '2'");
+
+        for text in [
+            "",
+            "[1,]",
+            "1e",
+            "1.e5",
+            "-",
+            "{\"a\":1,}",
+            "01",
+            ".5",
+            "+1",
+            "1.",
+            "\"abc",
+            "tru",
+            "[1] 2",
+            "{1: 2}",
+            "\"\\x\"",
+            "\"\\ud800\"",
+        ] {
+            assert!(parse(text).is_err(), "{:?} should not parse", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_nesting_limit() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(&nested(128)).is_ok());
+
+        let error = parse(&format!("{{\n\"a\": {}}}", nested(100_000))).unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.message, "nesting too deep");
+    }
+}
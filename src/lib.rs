@@ -0,0 +1,70 @@
+// A path tracer following "Ray Tracing in One Weekend".
+//
+// Build a world from `Hittable`s (usually a `HittableList` wrapped in a
+// `BvhNode`), configure a `Camera` through `Camera::builder()`, then either
+// `render_to_buffer` to get the linear pixels or `render` to write an image
+// file. Both also take the lights to sample directly, a `HittableList` that
+// may be empty. Scenes can also come from `scenes::builtin` or `scene_file::load`,
+// and `render` reads and writes images. Everything else is re-exported here.
+
+mod aabb;
+mod bvh;
+mod camera;
+mod color;
+mod environment;
+mod gltf;
+mod hdr;
+mod hittable;
+mod hittable_list;
+mod integrator;
+mod interval;
+mod json;
+mod material;
+mod matrix;
+mod mesh;
+mod obj;
+mod perlin;
+mod ply;
+mod point;
+mod progress;
+mod quad;
+mod random;
+mod ray;
+pub mod render;
+pub mod scene_file;
+pub mod scenes;
+mod sky;
+mod sphere;
+mod texture;
+mod transformed;
+mod triangle;
+mod vector;
+
+pub use aabb::Aabb;
+pub use bvh::BvhNode;
+pub use camera::{Background, Camera, CameraBuilder, CameraError};
+pub use color::Color;
+pub use environment::EnvironmentMap;
+pub use gltf::{load_gltf, Gltf, GltfCamera, GltfImage, GltfMaterial};
+pub use hittable::{HitRecord, Hittable};
+pub use hittable_list::HittableList;
+pub use integrator::Integrator;
+pub use interval::Interval;
+pub use material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+pub use matrix::Matrix4;
+pub use mesh::{Face, Mesh, MeshData, MeshError};
+pub use obj::load_obj;
+pub use ply::load_ply;
+pub use point::Point3;
+pub use progress::{ProgressObserver, RenderStats, StderrProgress};
+pub use quad::{make_box, Quad};
+pub use random::Sampler;
+pub use ray::Ray;
+pub use render::{Framebuffer, TransferFunction};
+pub use scenes::{RenderSettings, Scene};
+pub use sky::PhysicalSky;
+pub use sphere::Sphere;
+pub use texture::{CheckerTexture, ImageTexture, NoiseStyle, NoiseTexture, SolidColor, Texture};
+pub use transformed::Transformed;
+pub use triangle::Triangle;
+pub use vector::Vector3;
@@ -1,44 +1,68 @@
-use std::time::Instant;
-
-use hittable_list::HittableList;
-use point::Point3;
-use ray::Ray;
-use sphere::Sphere;
-
-use crate::camera::Camera;
-
-mod camera;
-mod color;
-mod hittable;
-mod hittable_list;
-mod interval;
-mod point;
-mod ray;
-mod render;
-mod sphere;
-mod vector;
+use std::{env, path::Path, process, sync::Arc, time::Instant};
+
+use cli::Command;
+use raytracing::{scene_file, scenes, BvhNode, StderrProgress};
+
+mod cli;
 
 fn main() {
-    // image definitions
-    let aspect_ratio = 16.0 / 9.0;
-    let image_width = 1920;
-    let image_height = image_width as f64 / aspect_ratio;
+    let options = match cli::parse_args(env::args().skip(1)) {
+        Ok(Command::Render(options)) => options,
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    // anything that is not a built-in scene name is a scene file
+    let scene = match scenes::builtin(&options.scene) {
+        Some(scene) => scene,
+        None => match scene_file::load(Path::new(&options.scene)) {
+            Ok(scene) => scene,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+        },
+    };
+
+    let settings = options.settings.clone().or(scene.settings);
+    let output = settings.output();
+    let mut camera = settings.apply(scene.camera);
+    if let Some(threads) = options.threads {
+        camera = camera.threads(threads);
+    }
+    if options.plain_ppm {
+        camera = camera.plain_ppm(true);
+    }
+    if !options.quiet {
+        camera = camera.progress(Arc::new(StderrProgress::new()));
+    }
+    let camera = match camera.build() {
+        Ok(camera) => camera,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    };
+    let world = BvhNode::new(scene.world);
 
     println!(
-        "Rendering image with resolution: {}x{}",
-        image_width, image_height
+        "Rendering '{}' with resolution: {}x{}",
+        options.scene,
+        camera.image_width(),
+        camera.image_height()
     );
 
     let start_time = Instant::now();
-
-    // world setup
-    let mut world = HittableList::new();
-    world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5)));
-    world.add(Box::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0)));
-
-    // render
-    let camera = Camera::new(aspect_ratio, image_width, 100, 10);
-    camera.render(&world);
+    if let Err(err) = camera.render(&world, &scene.lights, &output) {
+        eprintln!("Failed to write {}: {}", output.display(), err);
+        process::exit(1);
+    }
 
     let elapsed_time = start_time.elapsed();
     println!("Rendering completed in {:.2?}", elapsed_time);
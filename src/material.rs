@@ -0,0 +1,312 @@
+use std::sync::Arc;
+
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    point::Point3,
+    random::{self, Sampler},
+    ray::Ray,
+    texture::{SolidColor, Texture},
+    vector::Vector3,
+};
+
+pub trait Material: Send + Sync {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+        rng: &mut Sampler,
+    ) -> bool;
+
+    // Density over solid angle with which `scatter` picks `scattered`. Zero
+    // marks specular materials: light sampling cannot produce their
+    // directions, so they are left to the scattered ray alone.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    // Light given off at a surface point, black for anything but lights.
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    // Whether `emitted` can be anything but black, making objects of this
+    // material worth sampling directly as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+}
+
+pub struct Lambertian {
+    pub tex: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Color) -> Self {
+        Lambertian::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Lambertian { tex }
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+        rng: &mut Sampler,
+    ) -> bool {
+        let mut scatter_direction = rec.normal + Vector3::random_unit_vector(rng);
+
+        // catch degenerate scatter direction
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal;
+        }
+
+        *scattered = Ray::new(rec.p, scatter_direction);
+        *attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+        true
+    }
+
+    // The cosine distribution that `scatter` samples.
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine = Vector3::dot(&rec.normal, &Vector3::unit_vector(scattered.direction()));
+        cosine.max(0.0) / std::f64::consts::PI
+    }
+}
+
+pub struct Metal {
+    pub albedo: Color,
+    pub fuzz: f64,
+}
+
+impl Metal {
+    pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Metal {
+            albedo,
+            fuzz: fuzz.min(1.0),
+        }
+    }
+}
+
+impl Material for Metal {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+        rng: &mut Sampler,
+    ) -> bool {
+        let reflected = Vector3::reflect(r_in.direction(), &rec.normal);
+        let reflected = Vector3::unit(&reflected) + self.fuzz * Vector3::random_unit_vector(rng);
+
+        *scattered = Ray::new(rec.p, reflected);
+        *attenuation = self.albedo;
+        Vector3::dot(scattered.direction(), &rec.normal) > 0.0
+    }
+}
+
+pub struct Dielectric {
+    // refractive index in vacuum or air, or the ratio of the material's
+    // refractive index over the refractive index of the enclosing media
+    pub refraction_index: f64,
+}
+
+impl Dielectric {
+    pub fn new(refraction_index: f64) -> Self {
+        Dielectric { refraction_index }
+    }
+
+    fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
+        // Schlick's approximation for reflectance
+        let r0 = (1.0 - refraction_index) / (1.0 + refraction_index);
+        let r0 = r0 * r0;
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+        rng: &mut Sampler,
+    ) -> bool {
+        *attenuation = Color::new(1.0, 1.0, 1.0);
+        let ri = if rec.front_face {
+            1.0 / self.refraction_index
+        } else {
+            self.refraction_index
+        };
+
+        let unit_direction = Vector3::unit(r_in.direction());
+        let cos_theta = Vector3::dot(&-unit_direction, &rec.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = ri * sin_theta > 1.0;
+        let direction = if cannot_refract
+            || Dielectric::reflectance(cos_theta, ri) > random::random_double(rng)
+        {
+            Vector3::reflect(&unit_direction, &rec.normal)
+        } else {
+            Vector3::refract(&unit_direction, &rec.normal, ri)
+        };
+
+        *scattered = Ray::new(rec.p, direction);
+        true
+    }
+}
+
+// An emitter that absorbs everything it is hit with.
+pub struct DiffuseLight {
+    pub tex: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        DiffuseLight::from_texture(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        DiffuseLight { tex }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _attenuation: &mut Color,
+        _scattered: &mut Ray,
+        _rng: &mut Sampler,
+    ) -> bool {
+        false
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.tex.value(u, v, p)
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::Point3;
+
+    fn record(normal: Vector3, front_face: bool) -> HitRecord {
+        let mut rec = HitRecord::new();
+        rec.p = Point3::new(0.0, 0.0, 0.0);
+        rec.normal = normal;
+        rec.front_face = front_face;
+        rec
+    }
+
+    #[test]
+    fn test_lambertian_scatter() {
+        let material = Lambertian::new(Color::new(0.1, 0.2, 0.3));
+        let r_in = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let rec = record(Vector3::new(0.0, 1.0, 0.0), true);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        let mut rng = random::sampler(0, 0, 0);
+
+        assert!(material.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut rng));
+        assert_eq!(attenuation, Color::new(0.1, 0.2, 0.3));
+        assert_eq!(*scattered.origin(), rec.p);
+        assert!(Vector3::dot(scattered.direction(), &rec.normal) >= 0.0);
+        let pdf = material.scattering_pdf(&r_in, &rec, &scattered);
+        assert!(pdf > 0.0 && pdf <= 1.0 / std::f64::consts::PI);
+    }
+
+    #[test]
+    fn test_metal_mirror_reflection() {
+        let material = Metal::new(Color::new(0.8, 0.8, 0.8), 0.0);
+        let r_in = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let rec = record(Vector3::new(0.0, 1.0, 0.0), true);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        let mut rng = random::sampler(0, 0, 0);
+
+        assert!(material.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut rng));
+        assert_eq!(attenuation, Color::new(0.8, 0.8, 0.8));
+        let expected = Vector3::unit(&Vector3::new(1.0, 1.0, 0.0));
+        assert!((*scattered.direction() - expected).near_zero());
+    }
+
+    #[test]
+    fn test_metal_fuzz_is_clamped() {
+        let material = Metal::new(Color::new(0.8, 0.8, 0.8), 3.0);
+        assert_eq!(material.fuzz, 1.0);
+    }
+
+    #[test]
+    fn test_dielectric_matching_index_passes_through() {
+        let material = Dielectric::new(1.0);
+        let r_in = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let rec = record(Vector3::new(0.0, 1.0, 0.0), true);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        let mut rng = random::sampler(0, 0, 0);
+
+        assert!(material.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut rng));
+        assert_eq!(attenuation, Color::new(1.0, 1.0, 1.0));
+        assert!((*scattered.direction() - Vector3::new(0.0, -1.0, 0.0)).near_zero());
+    }
+
+    #[test]
+    fn test_dielectric_total_internal_reflection() {
+        let material = Dielectric::new(1.5);
+        // grazing ray leaving the glass: 1.5 * sin(60°) > 1.0
+        let direction = Vector3::new(60f64.to_radians().sin(), -60f64.to_radians().cos(), 0.0);
+        let r_in = Ray::new(Point3::new(0.0, 1.0, 0.0), direction);
+        let rec = record(Vector3::new(0.0, 1.0, 0.0), false);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        let mut rng = random::sampler(0, 0, 0);
+
+        assert!(material.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut rng));
+        assert!(scattered.direction().y > 0.0);
+    }
+
+    #[test]
+    fn test_diffuse_light_emits_without_scattering() {
+        let material = DiffuseLight::new(Color::new(4.0, 4.0, 4.0));
+        let r_in = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let rec = record(Vector3::new(0.0, 1.0, 0.0), true);
+        let mut attenuation = Color::new(0.0, 0.0, 0.0);
+        let mut scattered = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0));
+        let mut rng = random::sampler(0, 0, 0);
+
+        assert!(!material.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut rng));
+        assert_eq!(
+            material.emitted(0.0, 0.0, &rec.p),
+            Color::new(4.0, 4.0, 4.0)
+        );
+        assert_eq!(
+            Lambertian::new(Color::new(1.0, 1.0, 1.0)).emitted(0.0, 0.0, &rec.p),
+            Color::new(0.0, 0.0, 0.0)
+        );
+        assert!(material.is_emissive());
+        assert!(!Lambertian::new(Color::new(1.0, 1.0, 1.0)).is_emissive());
+    }
+
+    #[test]
+    fn test_schlick_reflectance_at_normal_incidence() {
+        let reflectance = Dielectric::reflectance(1.0, 1.5);
+        assert!((reflectance - 0.04).abs() < 1e-12);
+    }
+}
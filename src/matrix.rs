@@ -0,0 +1,298 @@
+use std::ops::Mul;
+
+use crate::{point::Point3, vector::Vector3};
+
+// A row-major 4x4 matrix holding an affine transform, so the bottom row is
+// always 0 0 0 1. Points are column vectors, and `a * b` applies `b` first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4(pub [[f64; 4]; 4]);
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Matrix4::IDENTITY
+    }
+}
+
+impl Matrix4 {
+    pub const IDENTITY: Matrix4 = Matrix4([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    // The sixteen values column by column, as glTF stores them.
+    pub fn from_columns(values: [f64; 16]) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, value) in values.into_iter().enumerate() {
+            m[i % 4][i / 4] = value;
+        }
+        Matrix4(m)
+    }
+
+    pub fn translation(offset: Vector3) -> Matrix4 {
+        let mut m = Matrix4::IDENTITY;
+        m.0[0][3] = offset.x;
+        m.0[1][3] = offset.y;
+        m.0[2][3] = offset.z;
+        m
+    }
+
+    // Scales each axis by the matching component; negative ones mirror.
+    pub fn scaling(factors: Vector3) -> Matrix4 {
+        let mut m = Matrix4::IDENTITY;
+        m.0[0][0] = factors.x;
+        m.0[1][1] = factors.y;
+        m.0[2][2] = factors.z;
+        m
+    }
+
+    // Counter-clockwise rotation by `degrees` around `axis` when looking
+    // down the axis towards the origin.
+    pub fn rotation(axis: Vector3, degrees: f64) -> Matrix4 {
+        let Vector3 { x, y, z } = Vector3::unit_vector(&axis);
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let k = 1.0 - cos;
+        Matrix4([
+            [
+                cos + x * x * k,
+                x * y * k - z * sin,
+                x * z * k + y * sin,
+                0.0,
+            ],
+            [
+                y * x * k + z * sin,
+                cos + y * y * k,
+                y * z * k - x * sin,
+                0.0,
+            ],
+            [
+                z * x * k - y * sin,
+                z * y * k + x * sin,
+                cos + z * z * k,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Translation * rotation (a unit quaternion x, y, z, w) * scale.
+    pub fn from_trs(t: [f64; 3], r: [f64; 4], s: [f64; 3]) -> Matrix4 {
+        let [x, y, z, w] = r;
+        let rotation = [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ];
+        let mut m = Matrix4::IDENTITY.0;
+        for row in 0..3 {
+            for col in 0..3 {
+                m[row][col] = rotation[row][col] * s[col];
+            }
+            m[row][3] = t[row];
+        }
+        Matrix4(m)
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.0;
+        Point3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    // Directions ignore the translation.
+    pub fn transform_vector(&self, v: Vector3) -> Vector3 {
+        let m = &self.0;
+        Vector3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    // Normals transform with the inverse transpose. The cofactor matrix is
+    // that up to the determinant, and only its sign matters for directions,
+    // so the result points the right way but is not of unit length.
+    pub fn transform_normal(&self, n: Vector3) -> Vector3 {
+        let c = self.cofactors();
+        let sign = self.determinant().signum();
+        Vector3::new(
+            sign * (c[0][0] * n.x + c[0][1] * n.y + c[0][2] * n.z),
+            sign * (c[1][0] * n.x + c[1][1] * n.y + c[1][2] * n.z),
+            sign * (c[2][0] * n.x + c[2][1] * n.y + c[2][2] * n.z),
+        )
+    }
+
+    // Of the linear part; negative when the transform mirrors.
+    pub fn determinant(&self) -> f64 {
+        let m = &self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // None if the transform flattens space onto a plane, line or point.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let det = self.determinant();
+        if det.abs() < 1e-12 || !det.is_finite() {
+            return None;
+        }
+        // the inverse of the linear part is the transposed cofactor matrix
+        // over the determinant, and the translation is undone after it
+        let c = self.cofactors();
+        let mut linear = Matrix4::IDENTITY;
+        for (row, values) in linear.0.iter_mut().take(3).enumerate() {
+            for (col, value) in values.iter_mut().take(3).enumerate() {
+                *value = c[col][row] / det;
+            }
+        }
+        let m = &self.0;
+        let t = linear.transform_vector(Vector3::new(m[0][3], m[1][3], m[2][3]));
+        Some(Matrix4::translation(-t) * linear)
+    }
+
+    fn cofactors(&self) -> [[f64; 3]; 3] {
+        let m = &self.0;
+        let minor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+        [
+            [minor(1, 2, 1, 2), -minor(1, 2, 0, 2), minor(1, 2, 0, 1)],
+            [-minor(0, 2, 1, 2), minor(0, 2, 0, 2), -minor(0, 2, 0, 1)],
+            [minor(0, 1, 1, 2), -minor(0, 1, 0, 2), minor(0, 1, 0, 1)],
+        ]
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (row, values) in m.iter_mut().enumerate() {
+            for (col, value) in values.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[row][k] * other.0[k][col]).sum();
+            }
+        }
+        Matrix4(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_matrix_near(a: &Matrix4, b: &Matrix4) {
+        for row in 0..4 {
+            for col in 0..4 {
+                assert!(
+                    (a.0[row][col] - b.0[row][col]).abs() < 1e-9,
+                    "{:?} != {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_translation_moves_points_not_vectors() {
+        let m = Matrix4::translation(Vector3::new(1.0, 2.0, 3.0));
+        let v = Vector3::new(1.0, 1.0, 1.0);
+        assert_eq!(m.transform_point(v), Vector3::new(2.0, 3.0, 4.0));
+        assert_eq!(m.transform_vector(v), v);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let m = Matrix4::rotation(Vector3::new(0.0, 1.0, 0.0), 90.0);
+        assert_near(
+            m.transform_vector(Vector3::new(1.0, 0.0, 0.0)),
+            Vector3::new(0.0, 0.0, -1.0),
+        );
+        assert_near(
+            m.transform_vector(Vector3::new(0.0, 0.0, 1.0)),
+            Vector3::new(1.0, 0.0, 0.0),
+        );
+        assert!((m.determinant() - 1.0).abs() < 1e-12);
+
+        // the same rotation as a quaternion
+        let half = std::f64::consts::FRAC_PI_4;
+        let q = Matrix4::from_trs([0.0; 3], [0.0, half.sin(), 0.0, half.cos()], [1.0; 3]);
+        assert_matrix_near(&m, &q);
+    }
+
+    #[test]
+    fn test_composition_applies_right_first() {
+        let scale = Matrix4::scaling(Vector3::new(2.0, 2.0, 2.0));
+        let shift = Matrix4::translation(Vector3::new(1.0, 0.0, 0.0));
+        let p = Point3::new(1.0, 0.0, 0.0);
+        assert_eq!(
+            (shift * scale).transform_point(p),
+            Point3::new(3.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            (scale * shift).transform_point(p),
+            Point3::new(4.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Matrix4::translation(Vector3::new(1.0, -2.0, 3.0))
+            * Matrix4::rotation(Vector3::new(1.0, 2.0, 0.5), 33.0)
+            * Matrix4::scaling(Vector3::new(2.0, 0.5, -3.0));
+        let inverse = m.inverse().unwrap();
+        assert_matrix_near(&(m * inverse), &Matrix4::IDENTITY);
+        assert_matrix_near(&(inverse * m), &Matrix4::IDENTITY);
+
+        let flat = Matrix4::scaling(Vector3::new(1.0, 0.0, 1.0));
+        assert!(flat.inverse().is_none());
+    }
+
+    #[test]
+    fn test_from_columns() {
+        let values: [f64; 16] = std::array::from_fn(|i| i as f64);
+        let m = Matrix4::from_columns(values);
+        assert_eq!(m.0[0], [0.0, 4.0, 8.0, 12.0]);
+        assert_eq!(m.0[3], [3.0, 7.0, 11.0, 15.0]);
+    }
+
+    #[test]
+    fn test_normals_transform_with_inverse_transpose() {
+        let m = Matrix4::from_trs([1.0, 2.0, 3.0], [0.0, 0.0, 0.0, 1.0], [2.0, 1.0, 1.0]);
+        // a plane through the x and y axes tilted 45 degrees keeps its
+        // normal perpendicular after stretching along x
+        let n = m.transform_normal(Vector3::new(1.0, 1.0, 0.0));
+        let tangent = m.transform_vector(Vector3::new(1.0, -1.0, 0.0));
+        assert!(Vector3::dot(&n, &tangent).abs() < 1e-12);
+        assert_near(
+            m.transform_point(Point3::new(1.0, 1.0, 1.0)),
+            Point3::new(3.0, 3.0, 4.0),
+        );
+
+        // mirroring keeps normals on the side they were on
+        let mirror = Matrix4::scaling(Vector3::new(-1.0, 1.0, 1.0));
+        let n = mirror.transform_normal(Vector3::new(1.0, 0.0, 1.0));
+        assert!(n.z > 0.0 && n.x < 0.0);
+    }
+}
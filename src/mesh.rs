@@ -0,0 +1,419 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    aabb::Aabb,
+    bvh::BvhNode,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    interval::Interval,
+    material::Material,
+    point::Point3,
+    progress,
+    ray::Ray,
+    triangle::{self, triangle_bbox},
+    vector::Vector3,
+};
+
+// Vertex buffers shared by every triangle of a mesh. Faces index into the
+// buffers separately for positions, normals and texture coordinates, as OBJ
+// does; formats with a single index per vertex use the same one for all.
+#[derive(Default)]
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vector3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub faces: Vec<Face>,
+    pub materials: Vec<Arc<dyn Material>>,
+    // names of the groups faces belong to, in order of appearance
+    pub groups: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Face {
+    pub positions: [u32; 3],
+    pub normals: Option<[u32; 3]>,
+    pub uvs: Option<[u32; 3]>,
+    pub material: u32,
+    pub group: u32,
+}
+
+impl Face {
+    pub fn new(positions: [u32; 3]) -> Self {
+        Face {
+            positions,
+            normals: None,
+            uvs: None,
+            material: 0,
+            group: 0,
+        }
+    }
+}
+
+impl MeshData {
+    fn vertices(&self, face: &Face) -> [Point3; 3] {
+        face.positions.map(|i| self.positions[i as usize])
+    }
+
+    // Checks that every index points into its buffer, so that intersecting
+    // the mesh never has to.
+    pub fn validate(&self) -> Result<(), String> {
+        fn check(indices: &[u32; 3], len: usize, what: &str, face: usize) -> Result<(), String> {
+            match indices.iter().find(|&&i| i as usize >= len) {
+                Some(i) => Err(format!(
+                    "face {} uses {} {} but there are only {}",
+                    face, what, i, len
+                )),
+                None => Ok(()),
+            }
+        }
+
+        for (n, face) in self.faces.iter().enumerate() {
+            check(&face.positions, self.positions.len(), "vertex", n)?;
+            if let Some(normals) = &face.normals {
+                check(normals, self.normals.len(), "normal", n)?;
+            }
+            if let Some(uvs) = &face.uvs {
+                check(uvs, self.uvs.len(), "texture coordinate", n)?;
+            }
+            if face.material as usize >= self.materials.len() {
+                return Err(format!(
+                    "face {} uses material {} but there are only {}",
+                    n,
+                    face.material,
+                    self.materials.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// A triangle mesh. The faces reference the shared `MeshData` and are kept in
+// their own BVH so that the mesh acts as a single object in the world.
+pub struct Mesh {
+    data: Arc<MeshData>,
+    bvh: BvhNode,
+}
+
+struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+    bbox: Aabb,
+}
+
+impl Mesh {
+    // Panics if a face indexes outside the buffers; loaders validate first.
+    pub fn new(data: MeshData) -> Self {
+        if let Err(message) = data.validate() {
+            panic!("invalid mesh: {}", message);
+        }
+
+        let data = Arc::new(data);
+        let mut triangles = HittableList::new();
+        for (face, indices) in data.faces.iter().enumerate() {
+            triangles.add(Box::new(MeshTriangle {
+                mesh: Arc::clone(&data),
+                face,
+                bbox: triangle_bbox(&data.vertices(indices)),
+            }));
+        }
+
+        Mesh {
+            bvh: BvhNode::new(triangles),
+            data,
+        }
+    }
+
+    pub fn data(&self) -> &MeshData {
+        &self.data
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.data.faces.len()
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+        self.bvh.hit(r, int, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+        let mesh = &self.mesh;
+        let face = &mesh.faces[self.face];
+        let vertices = mesh.vertices(face);
+        progress::count_intersection_test();
+        let Some(hit) = triangle::intersect(&vertices, r, int) else {
+            return false;
+        };
+
+        let normals = face
+            .normals
+            .map(|indices| indices.map(|i| mesh.normals[i as usize]));
+        let uvs = face
+            .uvs
+            .map(|indices| indices.map(|i| mesh.uvs[i as usize]));
+        triangle::set_hit_record(rec, r, &hit, &vertices, normals, uvs);
+        rec.mat = Some(Arc::clone(&mesh.materials[face.material as usize]));
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+// Splits a simple polygon, convex or not, into triangles by ear clipping in
+// the plane of its Newell normal. Degenerate polygons fall back to a fan.
+pub fn triangulate(points: &[Point3]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n < 3 {
+        return Vec::new();
+    }
+    let fan = || (1..n - 1).map(|i| [0, i, i + 1]).collect();
+    if n == 3 {
+        return fan();
+    }
+
+    let mut normal = Vector3::new(0.0, 0.0, 0.0);
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        normal = normal
+            + Vector3::new(
+                (a.y - b.y) * (a.z + b.z),
+                (a.z - b.z) * (a.x + b.x),
+                (a.x - b.x) * (a.y + b.y),
+            );
+    }
+    if normal.near_zero() {
+        return fan();
+    }
+
+    // project onto the plane by dropping the normal's dominant axis, picking
+    // the remaining two so that the polygon winds counter-clockwise
+    let axis = (0..3)
+        .max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs()))
+        .unwrap();
+    let (u, v) = match (axis, normal[axis] > 0.0) {
+        (0, true) => (1, 2),
+        (0, false) => (2, 1),
+        (1, true) => (2, 0),
+        (1, false) => (0, 2),
+        (_, true) => (0, 1),
+        (_, false) => (1, 0),
+    };
+    let flat: Vec<(f64, f64)> = points.iter().map(|p| (p[u], p[v])).collect();
+    let cross = |o: usize, a: usize, b: usize| {
+        let (o, a, b) = (flat[o], flat[a], flat[b]);
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + m - 1) % m],
+                remaining[i],
+                remaining[(i + 1) % m],
+            );
+            cross(a, b, c) > 0.0
+                && remaining.iter().all(|&p| {
+                    p == a
+                        || p == b
+                        || p == c
+                        || cross(a, b, p) < 0.0
+                        || cross(b, c, p) < 0.0
+                        || cross(c, a, p) < 0.0
+                })
+        });
+        let Some(i) = ear else {
+            // self-intersecting or collinear leftovers, finish with a fan
+            break;
+        };
+        triangles.push([
+            remaining[(i + m - 1) % m],
+            remaining[i],
+            remaining[(i + 1) % m],
+        ]);
+        remaining.remove(i);
+    }
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+// Error from one of the mesh loaders, pointing at the offending line when
+// the format is text based.
+#[derive(Debug)]
+pub struct MeshError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl MeshError {
+    pub fn new(path: &Path, line: Option<usize>, message: impl Into<String>) -> Self {
+        MeshError {
+            path: path.to_path_buf(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, material::Lambertian, triangle::Triangle};
+
+    fn grey() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    // a unit square in the z = 0 plane made of two triangles
+    fn square() -> MeshData {
+        MeshData {
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            faces: vec![Face::new([0, 1, 2]), Face::new([0, 2, 3])],
+            materials: vec![grey()],
+            groups: vec![String::from("default")],
+            ..MeshData::default()
+        }
+    }
+
+    #[test]
+    fn test_mesh_matches_separate_triangles() {
+        let mesh = Mesh::new(square());
+        let p = &mesh.data().positions;
+        let first = Triangle::new(p[0], p[1], p[2], grey());
+        let second = Triangle::new(p[0], p[2], p[3], grey());
+
+        for (x, y) in [(0.7, 0.2), (0.2, 0.7), (0.5, 0.5), (1.5, 0.5)] {
+            let r = Ray::new(Point3::new(x, y, 1.0), Vector3::new(0.0, 0.0, -1.0));
+            let int = Interval::new(0.001, f64::INFINITY);
+            let (mut expected, mut rec) = (HitRecord::new(), HitRecord::new());
+
+            let hit = first.hit(&r, int, &mut expected) || second.hit(&r, int, &mut expected);
+            assert_eq!(mesh.hit(&r, int, &mut rec), hit);
+            if hit {
+                assert_eq!(rec.t, expected.t);
+                assert_eq!((rec.u, rec.v), (expected.u, expected.v));
+            }
+        }
+        assert_eq!(mesh.triangle_count(), 2);
+    }
+
+    #[test]
+    fn test_mesh_uses_shared_normals_and_uvs() {
+        let mut data = square();
+        data.normals = vec![Vector3::new(0.0, 0.0, 1.0)];
+        data.uvs = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        for face in &mut data.faces {
+            face.normals = Some([0, 0, 0]);
+            face.uvs = Some(face.positions);
+        }
+        let mesh = Mesh::new(data);
+
+        let r = Ray::new(Point3::new(0.25, 0.75, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::new();
+        assert!(mesh.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!((rec.u - 0.25).abs() < 1e-9 && (rec.v - 0.75).abs() < 1e-9);
+        assert_eq!(rec.normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_triangulate_convex_polygon() {
+        let square = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let triangles = triangulate(&square);
+        assert_eq!(triangles.len(), 2);
+        assert!(triangles.iter().all(|t| t.contains(&0) || t.contains(&2)));
+    }
+
+    #[test]
+    fn test_triangulate_concave_polygon() {
+        // an L shape in the x = 1 plane, starting at a corner from which a
+        // fan would cover part of the notch
+        let points: Vec<Point3> = [
+            (0.0, 0.0),
+            (0.0, 2.0),
+            (1.0, 2.0),
+            (1.0, 1.0),
+            (2.0, 1.0),
+            (2.0, 0.0),
+        ]
+        .iter()
+        .map(|&(y, z)| Point3::new(1.0, y, z))
+        .collect();
+        let points = [&points[1..], &points[..1]].concat();
+
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 4);
+
+        // the triangles tile the L exactly: total area 3 and none reaches
+        // into the notch
+        let area: f64 = triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                Vector3::cross(&(points[b] - points[a]), &(points[c] - points[a])).length() / 2.0
+            })
+            .sum();
+        assert!((area - 3.0).abs() < 1e-12);
+        let notch = Ray::new(Point3::new(2.0, 1.5, 1.1), Vector3::new(-1.0, 0.0, 0.0));
+        for &[a, b, c] in &triangles {
+            let vertices = [points[a], points[b], points[c]];
+            assert!(triangle::intersect(&vertices, &notch, Interval::new(0.0, 10.0)).is_none());
+        }
+    }
+
+    #[test]
+    fn test_mesh_validate_rejects_bad_indices() {
+        let mut data = square();
+        data.faces[1].positions = [0, 2, 4];
+        assert_eq!(
+            data.validate(),
+            Err(String::from("face 1 uses vertex 4 but there are only 4"))
+        );
+
+        let mut data = square();
+        data.faces[0].uvs = Some([0, 1, 2]);
+        assert!(data.validate().unwrap_err().contains("texture coordinate"));
+
+        let mut data = square();
+        data.faces[0].material = 1;
+        assert!(data.validate().unwrap_err().contains("material 1"));
+    }
+}
@@ -0,0 +1,477 @@
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use crate::{
+    color::Color,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    mesh::{triangulate, Face, Mesh, MeshData, MeshError},
+    point::Point3,
+    vector::Vector3,
+};
+
+// Loads a Wavefront OBJ file along with the MTL libraries it references.
+// Faces without a `usemtl` use `default_material`.
+pub fn load_obj(path: &Path, default_material: Arc<dyn Material>) -> Result<Mesh, MeshError> {
+    let text =
+        fs::read_to_string(path).map_err(|err| MeshError::new(path, None, err.to_string()))?;
+    let data = parse_obj(&text, path, default_material)?;
+    Ok(Mesh::new(data))
+}
+
+// Parses OBJ source. `path` names the file in errors, and `mtllib`
+// references are resolved relative to its directory.
+pub fn parse_obj(
+    text: &str,
+    path: &Path,
+    default_material: Arc<dyn Material>,
+) -> Result<MeshData, MeshError> {
+    let mut data = MeshData {
+        materials: vec![default_material],
+        groups: vec![String::from("default")],
+        ..MeshData::default()
+    };
+    let mut library: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    let mut material_indices: HashMap<String, u32> = HashMap::new();
+    let mut material = 0;
+    let mut group = 0;
+
+    for (line_number, line) in text.lines().enumerate() {
+        let error = |message: String| MeshError::new(path, Some(line_number + 1), message);
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = numbers(&args, "vertex").map_err(error)?;
+                data.positions.push(Point3::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = numbers(&args, "normal").map_err(error)?;
+                data.normals.push(Vector3::new(x, y, z));
+            }
+            "vt" => {
+                // the v and w coordinates are optional
+                let [u] = numbers(&args, "texture coordinate").map_err(error)?;
+                let v = match args.get(1) {
+                    Some(v) => number(v, "texture coordinate").map_err(error)?,
+                    None => 0.0,
+                };
+                data.uvs.push((u, v));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error(format!(
+                        "face needs at least 3 vertices, found {}",
+                        args.len()
+                    )));
+                }
+                let corners = args
+                    .iter()
+                    .map(|corner| face_corner(corner, &data))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                let points: Vec<Point3> = corners
+                    .iter()
+                    .map(|corner| data.positions[corner.0 as usize])
+                    .collect();
+
+                for [a, b, c] in triangulate(&points) {
+                    let [a, b, c] = [corners[a], corners[b], corners[c]];
+                    let uvs = match (a.1, b.1, c.1) {
+                        (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                        _ => None,
+                    };
+                    let normals = match (a.2, b.2, c.2) {
+                        (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+                        _ => None,
+                    };
+                    data.faces.push(Face {
+                        positions: [a.0, b.0, c.0],
+                        normals,
+                        uvs,
+                        material,
+                        group,
+                    });
+                }
+            }
+            "g" | "o" => {
+                let name = if args.is_empty() {
+                    String::from("default")
+                } else {
+                    args.join(" ")
+                };
+                group = match data.groups.iter().position(|g| *g == name) {
+                    Some(index) => index as u32,
+                    None => {
+                        data.groups.push(name);
+                        data.groups.len() as u32 - 1
+                    }
+                };
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(error(String::from("mtllib needs a file name")));
+                }
+                for name in &args {
+                    let mtl_path = path.parent().unwrap_or(Path::new("")).join(name);
+                    let text = fs::read_to_string(&mtl_path).map_err(|err| {
+                        error(format!("cannot read material library '{}': {}", name, err))
+                    })?;
+                    library.extend(parse_mtl(&text, &mtl_path)?);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                material = match material_indices.get(&name) {
+                    Some(&index) => index,
+                    None => {
+                        let Some(found) = library.get(&name) else {
+                            return Err(error(format!("unknown material '{}'", name)));
+                        };
+                        data.materials.push(Arc::clone(found));
+                        let index = data.materials.len() as u32 - 1;
+                        material_indices.insert(name, index);
+                        index
+                    }
+                };
+            }
+            // smoothing groups, lines, points and free-form geometry
+            _ => {}
+        }
+    }
+
+    Ok(data)
+}
+
+// Resolves one `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner to zero-based
+// indices. Negative indices count back from the latest element.
+fn face_corner(corner: &str, data: &MeshData) -> Result<(u32, Option<u32>, Option<u32>), String> {
+    let mut parts = corner.split('/');
+    let position = index(parts.next().unwrap_or(""), data.positions.len(), "vertex")?;
+    let uv = match parts.next() {
+        None | Some("") => None,
+        Some(part) => Some(index(part, data.uvs.len(), "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        None | Some("") => None,
+        Some(part) => Some(index(part, data.normals.len(), "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex '{}'", corner));
+    }
+    Ok((position, uv, normal))
+}
+
+fn index(text: &str, len: usize, what: &str) -> Result<u32, String> {
+    let value: i64 = text
+        .parse()
+        .map_err(|_| format!("invalid {} index '{}'", what, text))?;
+    let resolved = match value {
+        0 => None,
+        v if v > 0 => Some(v - 1),
+        v => Some(len as i64 + v),
+    };
+    match resolved {
+        Some(i) if i >= 0 && (i as usize) < len => Ok(i as u32),
+        _ => Err(format!(
+            "{} index {} out of range, {} defined so far",
+            what, value, len
+        )),
+    }
+}
+
+fn number(text: &str, what: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(format!("invalid number '{}' in {}", text, what)),
+    }
+}
+
+// Reads the first `N` numbers; any further values, such as vertex colours
+// after a position, are ignored.
+fn numbers<const N: usize>(args: &[&str], what: &str) -> Result<[f64; N], String> {
+    if args.len() < N {
+        return Err(format!(
+            "{} needs {} numbers, found {}",
+            what,
+            N,
+            args.len()
+        ));
+    }
+    let mut values = [0.0; N];
+    for (value, text) in values.iter_mut().zip(args) {
+        *value = number(text, what)?;
+    }
+    Ok(values)
+}
+
+#[derive(Default)]
+struct MtlEntry {
+    diffuse: Option<Color>,
+    specular: Option<Color>,
+    shininess: Option<f64>,
+    refraction_index: Option<f64>,
+    dissolve: Option<f64>,
+    illum: Option<i64>,
+    emission: Option<Color>,
+}
+
+impl MtlEntry {
+    // Picks the closest of our materials: an emissive colour makes a light,
+    // transparent illumination models or a dissolve below one become glass,
+    // the reflective models metal and everything else diffuse.
+    fn to_material(&self) -> Arc<dyn Material> {
+        let illum = self.illum.unwrap_or(2);
+        if let Some(emit) = self
+            .emission
+            .filter(|e| e.red > 0.0 || e.green > 0.0 || e.blue > 0.0)
+        {
+            Arc::new(DiffuseLight::new(emit))
+        } else if matches!(illum, 4 | 6 | 7 | 9) || self.dissolve.is_some_and(|d| d < 1.0) {
+            Arc::new(Dielectric::new(self.refraction_index.unwrap_or(1.5)))
+        } else if matches!(illum, 3 | 5) {
+            // Phong exponent to roughness, then used as the fuzz radius
+            let shininess = self.shininess.unwrap_or(0.0).max(0.0);
+            let fuzz = (2.0 / (shininess + 2.0)).sqrt().min(1.0);
+            let albedo = self.specular.unwrap_or(Color::new(1.0, 1.0, 1.0));
+            Arc::new(Metal::new(albedo, fuzz))
+        } else {
+            Arc::new(Lambertian::new(
+                self.diffuse.unwrap_or(Color::new(0.8, 0.8, 0.8)),
+            ))
+        }
+    }
+}
+
+// Parses an MTL material library into materials keyed by name.
+pub fn parse_mtl(text: &str, path: &Path) -> Result<HashMap<String, Arc<dyn Material>>, MeshError> {
+    let mut entries: Vec<(String, MtlEntry)> = Vec::new();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let error = |message: String| MeshError::new(path, Some(line_number + 1), message);
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(error(String::from("newmtl needs a name")));
+            }
+            entries.push((args.join(" "), MtlEntry::default()));
+            continue;
+        }
+        let known = ["Kd", "Ks", "Ke", "Ns", "Ni", "d", "Tr", "illum"];
+        if !known.contains(&keyword) {
+            // ambient colours and texture maps are not supported
+            continue;
+        }
+        let Some((_, entry)) = entries.last_mut() else {
+            return Err(error(format!("'{}' before any newmtl", keyword)));
+        };
+        let color =
+            |args: &[&str]| numbers::<3>(args, keyword).map(|[r, g, b]| Color::new(r, g, b));
+        match keyword {
+            "Kd" => entry.diffuse = Some(color(&args).map_err(error)?),
+            "Ks" => entry.specular = Some(color(&args).map_err(error)?),
+            "Ke" => entry.emission = Some(color(&args).map_err(error)?),
+            "Ns" => entry.shininess = Some(numbers::<1>(&args, keyword).map_err(error)?[0]),
+            "Ni" => entry.refraction_index = Some(numbers::<1>(&args, keyword).map_err(error)?[0]),
+            "d" => entry.dissolve = Some(numbers::<1>(&args, keyword).map_err(error)?[0]),
+            "Tr" => entry.dissolve = Some(1.0 - numbers::<1>(&args, keyword).map_err(error)?[0]),
+            _ => {
+                let [illum] = numbers::<1>(&args, keyword).map_err(error)?;
+                if illum.fract() != 0.0 || !(0.0..=10.0).contains(&illum) {
+                    return Err(error(format!("invalid illumination model '{}'", args[0])));
+                }
+                entry.illum = Some(illum as i64);
+            }
+        }
+    }
+
+    Ok(entries
+        .into_iter()
+        .map(|(name, entry)| (name, entry.to_material()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{hittable::HitRecord, hittable::Hittable, interval::Interval, ray::Ray};
+
+    fn grey() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    fn parse(text: &str) -> Result<MeshData, MeshError> {
+        parse_obj(text, Path::new("model.obj"), grey())
+    }
+
+    fn parse_error(text: &str) -> String {
+        match parse(text) {
+            Ok(_) => panic!("expected the OBJ to be rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("raytracing-obj-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_obj_corner_formats() {
+        let data = parse(
+            "# a quad and a triangle\n\
+             v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+             vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+             vn 0 0 1\n\
+             f 1/1/1 2/2/1 3/3/1 4/4/1\n\
+             f -4//-1 -3//-1 -2//-1\n\
+             f 1/1 2/2 3\n",
+        )
+        .unwrap();
+
+        assert_eq!(data.positions.len(), 4);
+        assert_eq!(data.faces.len(), 4);
+        assert!(data.faces[0].uvs.is_some());
+        assert_eq!(data.faces[0].normals, Some([0, 0, 0]));
+        assert_eq!(data.faces[2].positions, [0, 1, 2]);
+        assert_eq!(data.faces[2].uvs, None);
+        assert_eq!(data.faces[2].normals, Some([0, 0, 0]));
+        // a corner without texture coordinates drops them for its triangle
+        assert_eq!(data.faces[3].uvs, None);
+        assert!(data.validate().is_ok());
+    }
+
+    #[test]
+    fn test_parse_obj_groups() {
+        let data = parse(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+             f 1 2 3\n\
+             g left wing\nf 1 2 3\n\
+             o body\nf 1 2 3\n\
+             g left wing\nf 1 2 3\n",
+        )
+        .unwrap();
+
+        assert_eq!(data.groups, ["default", "left wing", "body"]);
+        let groups: Vec<u32> = data.faces.iter().map(|face| face.group).collect();
+        assert_eq!(groups, [0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn test_parse_obj_reports_malformed_lines() {
+        assert_eq!(
+            parse_error("v 0 0 0\nv 1 x 0\n"),
+            "model.obj:2: invalid number 'x' in vertex"
+        );
+        assert_eq!(
+            parse_error("v 0 0\n"),
+            "model.obj:1: vertex needs 3 numbers, found 2"
+        );
+        assert_eq!(
+            parse_error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n"),
+            "model.obj:4: vertex index 4 out of range, 3 defined so far"
+        );
+        assert_eq!(
+            parse_error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 0\n"),
+            "model.obj:4: vertex index 0 out of range, 3 defined so far"
+        );
+        assert_eq!(
+            parse_error("v 0 0 0\nv 1 0 0\nf 1 2\n"),
+            "model.obj:3: face needs at least 3 vertices, found 2"
+        );
+        assert_eq!(
+            parse_error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2/1 3/1\n"),
+            "model.obj:4: texture coordinate index 1 out of range, 0 defined so far"
+        );
+        assert_eq!(
+            parse_error("usemtl steel\n"),
+            "model.obj:1: unknown material 'steel'"
+        );
+    }
+
+    #[test]
+    fn test_load_obj_with_materials() {
+        let dir = temp_dir("materials");
+        fs::write(
+            dir.join("scene.mtl"),
+            "newmtl red\nKd 0.9 0.1 0.1\n\nnewmtl glass\nNi 1.45\nd 0.2\nillum 4\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("scene.obj"),
+            "mtllib scene.mtl\n\
+             v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\n\
+             f 1 2 3\n\
+             usemtl red\nf 1 3 4\n\
+             usemtl glass\nf 1 2 4\n\
+             usemtl red\nf 2 3 4\n",
+        )
+        .unwrap();
+
+        let mesh = load_obj(&dir.join("scene.obj"), grey()).unwrap();
+        let materials: Vec<u32> = mesh.data().faces.iter().map(|face| face.material).collect();
+        assert_eq!(materials, [0, 1, 2, 1]);
+        assert_eq!(mesh.data().materials.len(), 3);
+
+        let r = Ray::new(Point3::new(0.5, 0.2, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::new();
+        assert!(mesh.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_mtl_emissive_material_is_a_light() {
+        let materials = parse_mtl("newmtl lamp\nKd 1 1 1\nKe 5 4 3\n", Path::new("a.mtl")).unwrap();
+        let p = Point3::new(0.0, 0.0, 0.0);
+        assert_eq!(
+            materials["lamp"].emitted(0.0, 0.0, &p),
+            Color::new(5.0, 4.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn test_load_obj_missing_files() {
+        let dir = temp_dir("missing");
+        fs::write(dir.join("broken.obj"), "v 0 0 0\nmtllib nowhere.mtl\n").unwrap();
+
+        let err = load_obj(&dir.join("broken.obj"), grey()).err().unwrap();
+        assert_eq!(err.line, Some(2));
+        assert!(err
+            .message
+            .starts_with("cannot read material library 'nowhere.mtl'"));
+
+        let err = load_obj(&dir.join("absent.obj"), grey()).err().unwrap();
+        assert_eq!(err.line, None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_mtl_errors() {
+        let err = parse_mtl("Kd 1 1 1\n", Path::new("a.mtl")).err().unwrap();
+        assert_eq!(err.to_string(), "a.mtl:1: 'Kd' before any newmtl");
+
+        let err = parse_mtl("newmtl a\nKd 1 1\n", Path::new("a.mtl"))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "a.mtl:2: Kd needs 3 numbers, found 2");
+
+        let err = parse_mtl("newmtl a\nillum 2.5\n", Path::new("a.mtl"))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "a.mtl:2: invalid illumination model '2.5'");
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    point::Point3,
+    random::{self, Sampler},
+    vector::Vector3,
+};
+
+const POINT_COUNT: usize = 256;
+
+// Ken Perlin's gradient noise, as in "Ray Tracing: The Next Week": random
+// unit gradients on the integer lattice, blended with a smoothstep so the
+// result is smooth and roughly within [-1, 1].
+pub struct Perlin {
+    gradients: Vec<Vector3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    // The same seed always gives the same noise.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = random::sampler(seed, 0, 0);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| Vector3::unit_vector(&Vector3::random_range(&mut rng, -1.0, 1.0)))
+            .collect();
+        Perlin {
+            gradients,
+            perm_x: permutation(&mut rng),
+            perm_y: permutation(&mut rng),
+            perm_z: permutation(&mut rng),
+        }
+    }
+
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let (u, v, w) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+
+        let mut corners = [[[Vector3::new(0.0, 0.0, 0.0); 2]; 2]; 2];
+        for (di, plane) in corners.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    let index = self.perm_x[wrap(i + di as i64)]
+                        ^ self.perm_y[wrap(j + dj as i64)]
+                        ^ self.perm_z[wrap(k + dk as i64)];
+                    *corner = self.gradients[index];
+                }
+            }
+        }
+        interpolate(&corners, u, v, w)
+    }
+
+    // Sum of `depth` octaves of noise, each at twice the frequency and half
+    // the weight of the one before, folded to be non-negative.
+    pub fn turbulence(&self, p: &Point3, depth: usize) -> f64 {
+        let mut accum = 0.0;
+        let mut p = *p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(&p);
+            weight *= 0.5;
+            p = p * 2.0;
+        }
+        accum.abs()
+    }
+}
+
+fn wrap(i: i64) -> usize {
+    (i & (POINT_COUNT as i64 - 1)) as usize
+}
+
+// A random shuffle of 0..POINT_COUNT.
+fn permutation(rng: &mut Sampler) -> Vec<usize> {
+    let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+    for i in (1..POINT_COUNT).rev() {
+        let target = ((random::random_double(rng) * (i + 1) as f64) as usize).min(i);
+        p.swap(i, target);
+    }
+    p
+}
+
+fn interpolate(corners: &[[[Vector3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+    // Hermite smoothing hides the lattice
+    let (uu, vv, ww) = (
+        u * u * (3.0 - 2.0 * u),
+        v * v * (3.0 - 2.0 * v),
+        w * w * (3.0 - 2.0 * w),
+    );
+    let mut accum = 0.0;
+    for (i, plane) in corners.iter().enumerate() {
+        for (j, row) in plane.iter().enumerate() {
+            for (k, gradient) in row.iter().enumerate() {
+                let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                let weight = Vector3::new(u - fi, v - fj, w - fk);
+                accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                    * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                    * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                    * Vector3::dot(gradient, &weight);
+            }
+        }
+    }
+    accum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_is_bounded_and_reproducible() {
+        let a = Perlin::new(1);
+        let b = Perlin::new(1);
+        let mut rng = random::sampler(9, 0, 0);
+        for _ in 0..1000 {
+            let p = Vector3::random_range(&mut rng, -20.0, 20.0);
+            let n = a.noise(&p);
+            assert!((-1.5..=1.5).contains(&n), "noise {} at {:?}", n, p);
+            assert_eq!(n, b.noise(&p));
+            assert!(a.turbulence(&p, 7) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_noise_vanishes_on_lattice_and_is_continuous() {
+        let perlin = Perlin::new(4);
+        // every gradient is dotted with a zero offset at its own corner
+        assert_eq!(perlin.noise(&Point3::new(3.0, -2.0, 7.0)), 0.0);
+
+        let p = Point3::new(0.3, 1.7, -2.2);
+        let q = p + Vector3::new(1e-6, 1e-6, 1e-6);
+        assert!((perlin.noise(&p) - perlin.noise(&q)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_permutation_is_a_shuffle() {
+        let mut p = permutation(&mut random::sampler(3, 0, 0));
+        assert_ne!(p, (0..POINT_COUNT).collect::<Vec<_>>());
+        p.sort();
+        assert_eq!(p, (0..POINT_COUNT).collect::<Vec<_>>());
+    }
+}
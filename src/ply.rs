@@ -0,0 +1,779 @@
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use crate::{
+    color::Color,
+    material::{Lambertian, Material},
+    mesh::{triangulate, Face, Mesh, MeshData, MeshError},
+    point::Point3,
+    render::TransferFunction,
+    vector::Vector3,
+};
+
+// Loads a PLY mesh in any of its three encodings. Faces use `material`
+// unless the vertices carry colours, in which case each face gets a diffuse
+// material with the average colour of its corners.
+pub fn load_ply(path: &Path, material: Arc<dyn Material>) -> Result<Mesh, MeshError> {
+    let bytes = fs::read(path).map_err(|err| MeshError::new(path, None, err.to_string()))?;
+    let data = parse_ply(&bytes, path, material)?;
+    Ok(Mesh::new(data))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        !matches!(self, Scalar::F32 | Scalar::F64)
+    }
+
+    // The smallest and largest value of an integer type.
+    fn bounds(self) -> Option<(i64, i64)> {
+        Some(match self {
+            Scalar::I8 => (i8::MIN.into(), i8::MAX.into()),
+            Scalar::U8 => (0, u8::MAX.into()),
+            Scalar::I16 => (i16::MIN.into(), i16::MAX.into()),
+            Scalar::U16 => (0, u16::MAX.into()),
+            Scalar::I32 => (i32::MIN.into(), i32::MAX.into()),
+            Scalar::U32 => (0, u32::MAX.into()),
+            Scalar::F32 | Scalar::F64 => return None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    // the count type for list properties
+    list: Option<Scalar>,
+    scalar: Scalar,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    // Bytes one instance takes at least in a binary body, with empty lists.
+    fn min_size(&self) -> usize {
+        self.properties
+            .iter()
+            .map(|p| p.list.unwrap_or(p.scalar).size())
+            .sum()
+    }
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    // lines taken by the header, the ASCII body starts right after
+    lines: usize,
+    // byte offset of the binary body
+    body: usize,
+}
+
+fn parse_header(bytes: &[u8], path: &Path) -> Result<Header, MeshError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut line_number = 0;
+
+    loop {
+        let end = match bytes[offset..].iter().position(|&b| b == b'\n') {
+            Some(end) => offset + end,
+            None => return Err(MeshError::new(path, None, "missing end_header")),
+        };
+        line_number += 1;
+        let error = |message: String| MeshError::new(path, Some(line_number), message);
+        let line = std::str::from_utf8(&bytes[offset..end])
+            .map_err(|_| error(String::from("header is not valid text")))?
+            .trim();
+        offset = end + 1;
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if line_number == 1 {
+            if line != "ply" {
+                return Err(error(String::from("not a PLY file, expected 'ply'")));
+            }
+            continue;
+        }
+        match tokens.as_slice() {
+            ["format", name, version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(error(format!("unknown format '{}'", name))),
+                });
+                if *version != "1.0" {
+                    return Err(error(format!("unsupported version '{}'", version)));
+                }
+            }
+            ["comment", ..] | ["obj_info", ..] => {}
+            ["element", name, count] => {
+                let count = count
+                    .parse()
+                    .map_err(|_| error(format!("invalid element count '{}'", count)))?;
+                elements.push(Element {
+                    name: name.to_string(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            ["property", "list", count, item, name] => {
+                let property = Property {
+                    name: name.to_string(),
+                    list: Some(scalar(count, &error)?),
+                    scalar: scalar(item, &error)?,
+                };
+                if !property.list.is_some_and(Scalar::is_integer) {
+                    return Err(error(String::from("list count type must be an integer")));
+                }
+                element(&mut elements, &error)?.properties.push(property);
+            }
+            ["property", ty, name] => {
+                let property = Property {
+                    name: name.to_string(),
+                    list: None,
+                    scalar: scalar(ty, &error)?,
+                };
+                element(&mut elements, &error)?.properties.push(property);
+            }
+            ["end_header"] => break,
+            _ => return Err(error(format!("invalid header line '{}'", line))),
+        }
+    }
+
+    let Some(format) = format else {
+        return Err(MeshError::new(path, None, "missing format line"));
+    };
+    Ok(Header {
+        format,
+        elements,
+        lines: line_number,
+        body: offset,
+    })
+}
+
+fn scalar(name: &str, error: &impl Fn(String) -> MeshError) -> Result<Scalar, MeshError> {
+    Scalar::parse(name).ok_or_else(|| error(format!("unknown property type '{}'", name)))
+}
+
+fn element<'a>(
+    elements: &'a mut [Element],
+    error: &impl Fn(String) -> MeshError,
+) -> Result<&'a mut Element, MeshError> {
+    elements
+        .last_mut()
+        .ok_or_else(|| error(String::from("property before any element")))
+}
+
+// Reads values from the body in either encoding. ASCII elements each sit on
+// their own line, which is what errors point at.
+struct Body<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    offset: usize,
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    line: usize,
+    tokens: Vec<&'a str>,
+    token: usize,
+}
+
+impl<'a> Body<'a> {
+    fn begin(&mut self, element: &str, index: usize) -> Result<(), String> {
+        if self.format != Format::Ascii {
+            return Ok(());
+        }
+        loop {
+            let Some((number, line)) = self.lines.next() else {
+                return Err(format!("unexpected end of file in {} {}", element, index));
+            };
+            self.line = number;
+            self.tokens = line.split_whitespace().collect();
+            self.token = 0;
+            if !self.tokens.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn end(&self, element: &str) -> Result<(), String> {
+        if self.format == Format::Ascii && self.token != self.tokens.len() {
+            return Err(format!(
+                "expected {} values for {}, found {}",
+                self.token,
+                element,
+                self.tokens.len()
+            ));
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, scalar: Scalar, element: &str) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let Some(token) = self.tokens.get(self.token) else {
+                return Err(format!("too few values for {}", element));
+            };
+            self.token += 1;
+            if let Some((min, max)) = scalar.bounds() {
+                return match token.parse::<i64>() {
+                    Ok(value) if (min..=max).contains(&value) => Ok(value as f64),
+                    _ => Err(format!(
+                        "expected an integer from {} to {}, found '{}' in {}",
+                        min, max, token, element
+                    )),
+                };
+            }
+            return match token.parse::<f64>() {
+                Ok(value) if value.is_finite() => Ok(value),
+                _ => Err(format!("invalid number '{}' in {}", token, element)),
+            };
+        }
+
+        let size = scalar.size();
+        let Some(raw) = self.bytes.get(self.offset..self.offset + size) else {
+            return Err(format!("unexpected end of data in {}", element));
+        };
+        self.offset += size;
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(raw);
+        if self.format == Format::BinaryBigEndian {
+            buf[..size].reverse();
+        }
+        Ok(match scalar {
+            Scalar::I8 => buf[0] as i8 as f64,
+            Scalar::U8 => buf[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(buf),
+        })
+    }
+}
+
+// Reads one instance of `element`, returning its scalar values (list counts in
+// place of lists) and the indices of the `face_list` property, if any.
+fn read_element(
+    body: &mut Body,
+    element: &Element,
+    what: &str,
+    face_list: Option<usize>,
+    vertex_count: usize,
+) -> Result<(Vec<f64>, Vec<u32>), String> {
+    let mut values = Vec::with_capacity(element.properties.len());
+    let mut polygon = Vec::new();
+    for (n, property) in element.properties.iter().enumerate() {
+        let Some(count_type) = property.list else {
+            values.push(body.read(property.scalar, what)?);
+            continue;
+        };
+        let count = body.read(count_type, what)?;
+        for _ in 0..count as usize {
+            let item = body.read(property.scalar, what)?;
+            if Some(n) == face_list {
+                if item < 0.0 || item as usize >= vertex_count {
+                    return Err(format!(
+                        "vertex index {} out of range in {}, {} vertices",
+                        item, what, vertex_count
+                    ));
+                }
+                polygon.push(item as u32);
+            }
+        }
+        values.push(count);
+    }
+    Ok((values, polygon))
+}
+
+// Positions of the vertex properties we understand.
+#[derive(Default)]
+struct VertexLayout {
+    position: [Option<usize>; 3],
+    normal: [Option<usize>; 3],
+    uv: [Option<usize>; 2],
+    color: [Option<usize>; 3],
+}
+
+impl VertexLayout {
+    fn new(element: &Element) -> Self {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|p| p.list.is_none() && names.contains(&p.name.as_str()))
+        };
+        VertexLayout {
+            position: [find(&["x"]), find(&["y"]), find(&["z"])],
+            normal: [find(&["nx"]), find(&["ny"]), find(&["nz"])],
+            uv: [
+                find(&["u", "s", "texture_u", "texture_s"]),
+                find(&["v", "t", "texture_v", "texture_t"]),
+            ],
+            color: [
+                find(&["red", "diffuse_red", "r"]),
+                find(&["green", "diffuse_green", "g"]),
+                find(&["blue", "diffuse_blue", "b"]),
+            ],
+        }
+    }
+}
+
+// Parses a whole PLY file; `path` is only used in error messages.
+pub fn parse_ply(
+    bytes: &[u8],
+    path: &Path,
+    material: Arc<dyn Material>,
+) -> Result<MeshData, MeshError> {
+    let header = parse_header(bytes, path)?;
+
+    let vertex_element = header.elements.iter().find(|e| e.name == "vertex");
+    let layout = vertex_element.map(VertexLayout::new).unwrap_or_default();
+    if vertex_element.is_some() && layout.position.iter().any(Option::is_none) {
+        return Err(MeshError::new(
+            path,
+            None,
+            "vertex element needs x, y and z properties",
+        ));
+    }
+    let has_normals = layout.normal.iter().all(Option::is_some);
+    let has_uvs = layout.uv.iter().all(Option::is_some);
+    let has_colors = layout.color.iter().all(Option::is_some);
+
+    let ascii = match header.format {
+        Format::Ascii => std::str::from_utf8(&bytes[header.body..])
+            .map_err(|_| MeshError::new(path, None, "ASCII body is not valid text"))?,
+        _ => "",
+    };
+    let mut body = Body {
+        format: header.format,
+        bytes,
+        offset: header.body,
+        lines: ascii.lines().enumerate(),
+        line: 0,
+        tokens: Vec::new(),
+        token: 0,
+    };
+    let error = |body: &Body, message: String| {
+        let line = match header.format {
+            Format::Ascii => Some(header.lines + body.line + 1),
+            _ => None,
+        };
+        MeshError::new(path, line, message)
+    };
+
+    let mut data = MeshData {
+        materials: vec![material],
+        groups: vec![String::from("default")],
+        ..MeshData::default()
+    };
+    let mut colors: Vec<Color> = Vec::new();
+    let mut polygons: Vec<Vec<u32>> = Vec::new();
+
+    for element in &header.elements {
+        let is_vertex = element.name == "vertex";
+        let face_list = match element.name.as_str() {
+            "face" => element.properties.iter().position(|p| {
+                p.list.is_some() && (p.name == "vertex_indices" || p.name == "vertex_index")
+            }),
+            _ => None,
+        };
+        if element.name == "face" && face_list.is_none() {
+            return Err(MeshError::new(
+                path,
+                None,
+                "face element needs a vertex_indices list",
+            ));
+        }
+
+        // even an element without properties takes a byte, so a huge count
+        // cannot keep the loop below busy without data to back it
+        if header.format != Format::Ascii
+            && element.count > (bytes.len() - body.offset) / element.min_size().max(1)
+        {
+            return Err(MeshError::new(
+                path,
+                None,
+                format!(
+                    "{} {} elements do not fit in the remaining data",
+                    element.count, element.name
+                ),
+            ));
+        }
+
+        for index in 0..element.count {
+            let what = format!("{} {}", element.name, index);
+            body.begin(&element.name, index)
+                .map_err(|message| error(&body, message))?;
+            let (values, polygon) =
+                read_element(&mut body, element, &what, face_list, data.positions.len())
+                    .map_err(|message| error(&body, message))?;
+            body.end(&what).map_err(|message| error(&body, message))?;
+
+            if is_vertex {
+                let get = |slot: Option<usize>| slot.map_or(0.0, |i| values[i]);
+                let [x, y, z] = layout.position.map(get);
+                data.positions.push(Point3::new(x, y, z));
+                if has_normals {
+                    let [x, y, z] = layout.normal.map(get);
+                    data.normals.push(Vector3::new(x, y, z));
+                }
+                if has_uvs {
+                    data.uvs.push((get(layout.uv[0]), get(layout.uv[1])));
+                }
+                if has_colors {
+                    // integer channels are 8-bit sRGB, float ones linear
+                    let vertex = vertex_element.unwrap();
+                    let [r, g, b] = layout.color.map(|slot| {
+                        let property = &vertex.properties[slot.unwrap()];
+                        let value = values[slot.unwrap()];
+                        if property.scalar.is_integer() {
+                            TransferFunction::Srgb.decode(value / 255.0)
+                        } else {
+                            value
+                        }
+                    });
+                    colors.push(Color::new(r, g, b));
+                }
+            } else if face_list.is_some() {
+                if polygon.len() < 3 {
+                    return Err(error(
+                        &body,
+                        format!(
+                            "{} has {} vertices, expected at least 3",
+                            what,
+                            polygon.len()
+                        ),
+                    ));
+                }
+                polygons.push(polygon);
+            }
+        }
+    }
+
+    let mut palette: HashMap<[u8; 3], u32> = HashMap::new();
+    for polygon in &polygons {
+        let points: Vec<Point3> = polygon
+            .iter()
+            .map(|&i| data.positions[i as usize])
+            .collect();
+        let material = if has_colors {
+            face_material(&mut data, &mut palette, &colors, polygon)
+        } else {
+            0
+        };
+        for [a, b, c] in triangulate(&points) {
+            let indices = [polygon[a], polygon[b], polygon[c]];
+            let mut face = Face::new(indices);
+            face.normals = has_normals.then_some(indices);
+            face.uvs = has_uvs.then_some(indices);
+            face.material = material;
+            data.faces.push(face);
+        }
+    }
+
+    Ok(data)
+}
+
+// Materials are shared between faces whose average colour rounds to the same
+// 8-bit value.
+fn face_material(
+    data: &mut MeshData,
+    palette: &mut HashMap<[u8; 3], u32>,
+    colors: &[Color],
+    polygon: &[u32],
+) -> u32 {
+    let mut sum = Color::new(0.0, 0.0, 0.0);
+    for &i in polygon {
+        sum = sum + colors[i as usize];
+    }
+    let average = sum * (1.0 / polygon.len() as f64);
+    let quantize = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let key = [
+        quantize(average.red),
+        quantize(average.green),
+        quantize(average.blue),
+    ];
+    *palette.entry(key).or_insert_with(|| {
+        data.materials.push(Arc::new(Lambertian::new(average)));
+        data.materials.len() as u32 - 1
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grey() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    fn parse(bytes: &[u8]) -> Result<MeshData, MeshError> {
+        parse_ply(bytes, Path::new("model.ply"), grey())
+    }
+
+    fn parse_error(bytes: &[u8]) -> String {
+        match parse(bytes) {
+            Ok(_) => panic!("expected the PLY to be rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    const ASCII_QUAD: &str = "ply
+format ascii 1.0
+comment a unit quad with normals and a triangle hanging off it
+element vertex 5
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property float u
+property float v
+element face 2
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1 0 0
+1 0 0 0 0 1 1 0
+1 1 0 0 0 1 1 1
+0 1 0 0 0 1 0 1
+2 2 0 0 0 1 1 1
+4 0 1 2 3
+3 1 4 2
+";
+
+    // The same mesh as ASCII_QUAD in one of the binary encodings.
+    fn binary_quad(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let header = ASCII_QUAD
+            .replace("format ascii", &format!("format {}", format))
+            .replace("property list uchar int", "property list uchar uint");
+        let header = &header[..header.find("end_header\n").unwrap() + "end_header\n".len()];
+        let mut bytes = header.as_bytes().to_vec();
+
+        let f32_bytes = |v: f32| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        for line in ASCII_QUAD.lines().skip(15).take(5) {
+            for value in line.split_whitespace() {
+                bytes.extend(f32_bytes(value.parse().unwrap()));
+            }
+        }
+        for face in [[0u32, 1, 2, 3].as_slice(), &[1, 4, 2]] {
+            bytes.push(face.len() as u8);
+            for &i in face {
+                bytes.extend(if big_endian {
+                    i.to_be_bytes()
+                } else {
+                    i.to_le_bytes()
+                });
+            }
+        }
+        bytes
+    }
+
+    fn assert_quad(data: &MeshData) {
+        assert_eq!(data.positions.len(), 5);
+        assert_eq!(data.positions[4], Point3::new(2.0, 2.0, 0.0));
+        assert_eq!(data.normals.len(), 5);
+        assert_eq!(data.uvs[2], (1.0, 1.0));
+        assert_eq!(data.faces.len(), 3);
+        assert_eq!(data.faces[2].positions, [1, 4, 2]);
+        assert_eq!(data.faces[2].normals, Some([1, 4, 2]));
+        assert!(data.validate().is_ok());
+    }
+
+    #[test]
+    fn test_parse_ply_ascii() {
+        assert_quad(&parse(ASCII_QUAD.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_parse_ply_binary_little_endian() {
+        assert_quad(&parse(&binary_quad(false)).unwrap());
+    }
+
+    #[test]
+    fn test_parse_ply_binary_big_endian() {
+        assert_quad(&parse(&binary_quad(true)).unwrap());
+    }
+
+    #[test]
+    fn test_parse_ply_skips_unknown_elements_and_properties() {
+        let data = parse(
+            b"ply
+format ascii 1.0
+element vertex 3
+property double x
+property double y
+property double z
+property uchar flags
+element edge 1
+property int vertex1
+property int vertex2
+element face 1
+property uchar intensity
+property list uchar int vertex_index
+end_header
+0 0 0 7
+1 0 0 7
+0 1 0 7
+0 1
+9 3 0 1 2
+",
+        )
+        .unwrap();
+
+        assert_eq!(data.faces.len(), 1);
+        assert!(data.normals.is_empty() && data.uvs.is_empty());
+        assert_eq!(data.faces[0].material, 0);
+    }
+
+    #[test]
+    fn test_parse_ply_vertex_colors() {
+        let data = parse(
+            b"ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 2
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+0 1 0 255 0 0
+1 1 0 0 0 255
+3 0 1 2
+3 0 1 2
+",
+        )
+        .unwrap();
+
+        // both faces are red and share one material next to the default
+        assert_eq!(data.materials.len(), 2);
+        assert_eq!(data.faces[0].material, 1);
+        assert_eq!(data.faces[1].material, 1);
+    }
+
+    #[test]
+    fn test_parse_ply_errors() {
+        assert_eq!(
+            parse_error(b"obj\n"),
+            "model.ply:1: not a PLY file, expected 'ply'"
+        );
+        assert_eq!(
+            parse_error(
+                b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float128 x\nend_header\n"
+            ),
+            "model.ply:4: unknown property type 'float128'"
+        );
+        assert_eq!(
+            parse_error(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n"),
+            "model.ply: missing end_header"
+        );
+
+        let body = |lines: &str| {
+            format!(
+                "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n{}",
+                lines
+            )
+        };
+        assert_eq!(
+            parse_error(body("0 0 0\n1 0\n0 1 0\n3 0 1 2\n").as_bytes()),
+            "model.ply:11: too few values for vertex 1"
+        );
+        assert_eq!(
+            parse_error(body("0 0 0\n1 0 0 5\n0 1 0\n3 0 1 2\n").as_bytes()),
+            "model.ply:11: expected 3 values for vertex 1, found 4"
+        );
+        assert_eq!(
+            parse_error(body("0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n").as_bytes()),
+            "model.ply:13: vertex index 3 out of range in face 0, 3 vertices"
+        );
+        assert_eq!(
+            parse_error(body("0 0 0\n1 0 0\n").as_bytes()),
+            "model.ply:11: unexpected end of file in vertex 2"
+        );
+        assert_eq!(
+            parse_error(body("0 0 0\n1 0 0\n0 1 0\n3 0 1.5 2\n").as_bytes()),
+            "model.ply:13: expected an integer from -2147483648 to 2147483647, found '1.5' in face 0"
+        );
+        assert_eq!(
+            parse_error(body("0 0 0\n1 0 0\n0 1 0\n-1 0 1 2\n").as_bytes()),
+            "model.ply:13: expected an integer from 0 to 255, found '-1' in face 0"
+        );
+
+        let mut truncated = binary_quad(false);
+        truncated.truncate(truncated.len() - 3);
+        assert_eq!(
+            parse_error(&truncated),
+            "model.ply: unexpected end of data in face 1"
+        );
+
+        let mut endless =
+            b"ply\nformat binary_little_endian 1.0\nelement nothing 1000000000000\nend_header\n"
+                .to_vec();
+        endless.extend([0; 16]);
+        assert_eq!(
+            parse_error(&endless),
+            "model.ply: 1000000000000 nothing elements do not fit in the remaining data"
+        );
+    }
+}
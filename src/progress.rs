@@ -0,0 +1,247 @@
+use std::{
+    cell::Cell,
+    fmt,
+    io::{self, Write},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// Told about a render as it goes, from whichever worker thread finished the
+// latest tile.
+pub trait ProgressObserver: Send + Sync {
+    // `done` of `total` tiles are finished after `elapsed`.
+    fn tile_finished(&self, _done: usize, _total: usize, _elapsed: Duration) {}
+
+    fn render_finished(&self, _stats: &RenderStats) {}
+}
+
+// What a render cost. Rays are every ray traced into the world, camera,
+// bounce and shadow rays alike; intersection tests are those of these rays
+// against spheres, triangles and quads, not counting bounding boxes or the
+// lookups of light sampling densities.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderStats {
+    pub tiles: usize,
+    pub primary_rays: u64,
+    pub total_rays: u64,
+    pub intersection_tests: u64,
+    pub elapsed: Duration,
+}
+
+impl RenderStats {
+    pub fn mrays_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        self.total_rays as f64 / seconds / 1e6
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "primary rays:       {}", self.primary_rays)?;
+        writeln!(f, "total rays:         {}", self.total_rays)?;
+        writeln!(f, "intersection tests: {}", self.intersection_tests)?;
+        write!(
+            f,
+            "throughput:         {:.2} Mrays/s",
+            self.mrays_per_second()
+        )
+    }
+}
+
+// Keeps one line on stderr up to date with the share of tiles done and the
+// time left, then prints the statistics.
+pub struct StderrProgress {
+    // when the line was last redrawn, to keep from flooding the terminal
+    last_update: Mutex<Option<Instant>>,
+}
+
+impl StderrProgress {
+    const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+    pub fn new() -> Self {
+        StderrProgress {
+            last_update: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for StderrProgress {
+    fn default() -> Self {
+        StderrProgress::new()
+    }
+}
+
+impl ProgressObserver for StderrProgress {
+    fn tile_finished(&self, done: usize, total: usize, elapsed: Duration) {
+        let mut last_update = self.last_update.lock().unwrap();
+        let now = Instant::now();
+        if done < total && last_update.is_some_and(|last| now - last < Self::UPDATE_INTERVAL) {
+            return;
+        }
+        *last_update = Some(now);
+        // the padding wipes what is left of a longer previous line
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r{:<60}", progress_line(done, total, elapsed));
+        let _ = stderr.flush();
+    }
+
+    fn render_finished(&self, stats: &RenderStats) {
+        eprintln!();
+        eprintln!("{}", stats);
+    }
+}
+
+// Time left if the remaining tiles take as long as the finished ones did,
+// None until there is anything to go by.
+pub fn eta(done: usize, total: usize, elapsed: Duration) -> Option<Duration> {
+    if done == 0 {
+        return None;
+    }
+    let remaining = total.saturating_sub(done) as f64;
+    Some(elapsed.mul_f64(remaining / done as f64))
+}
+
+fn progress_line(done: usize, total: usize, elapsed: Duration) -> String {
+    let percent = (done * 100).checked_div(total).unwrap_or(100);
+    let mut line = format!(
+        "{}/{} tiles ({}%), {} elapsed",
+        done,
+        total,
+        percent,
+        format_duration(elapsed)
+    );
+    if done < total {
+        if let Some(eta) = eta(done, total, elapsed) {
+            line += &format!(", ETA {}", format_duration(eta));
+        }
+    }
+    line
+}
+
+// Like "1h 02m 03s", "2m 05s" or "4.2s".
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}h {:02}m {:02}s",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else if seconds >= 60 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
+
+// Per-thread tallies, cheap enough to bump in the innermost loops. Renders
+// read them before and after a worker's share of the tiles.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Counters {
+    pub rays: u64,
+    pub intersection_tests: u64,
+}
+
+impl std::ops::Sub for Counters {
+    type Output = Counters;
+
+    fn sub(self, other: Counters) -> Counters {
+        Counters {
+            rays: self.rays - other.rays,
+            intersection_tests: self.intersection_tests - other.intersection_tests,
+        }
+    }
+}
+
+thread_local! {
+    static COUNTERS: Cell<Counters> = const {
+        Cell::new(Counters {
+            rays: 0,
+            intersection_tests: 0,
+        })
+    };
+}
+
+pub(crate) fn counters() -> Counters {
+    COUNTERS.with(Cell::get)
+}
+
+pub(crate) fn count_ray() {
+    COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.rays += 1;
+        counters.set(c);
+    });
+}
+
+pub(crate) fn count_intersection_test() {
+    COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.intersection_tests += 1;
+        counters.set(c);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta() {
+        assert_eq!(eta(0, 10, Duration::from_secs(5)), None);
+        assert_eq!(
+            eta(4, 10, Duration::from_secs(8)),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(eta(10, 10, Duration::from_secs(8)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_progress_line() {
+        assert_eq!(
+            progress_line(25, 100, Duration::from_secs(75)),
+            "25/100 tiles (25%), 1m 15s elapsed, ETA 3m 45s"
+        );
+        assert_eq!(
+            progress_line(100, 100, Duration::from_millis(4200)),
+            "100/100 tiles (100%), 4.2s elapsed"
+        );
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1h 02m 03s");
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = RenderStats {
+            tiles: 4,
+            primary_rays: 1_000,
+            total_rays: 3_000_000,
+            intersection_tests: 9_000_000,
+            elapsed: Duration::from_secs(2),
+        };
+        assert_eq!(stats.mrays_per_second(), 1.5);
+        assert!(stats
+            .to_string()
+            .ends_with("throughput:         1.50 Mrays/s"));
+        assert_eq!(RenderStats::default().mrays_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_counters_are_per_thread() {
+        let before = counters();
+        count_ray();
+        count_intersection_test();
+        count_intersection_test();
+        std::thread::spawn(count_ray).join().unwrap();
+        assert_eq!(
+            counters() - before,
+            Counters {
+                rays: 1,
+                intersection_tests: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    interval::Interval,
+    material::Material,
+    point::Point3,
+    progress,
+    random::{self, Sampler},
+    ray::Ray,
+    vector::Vector3,
+};
+
+// The parallelogram with corner `q` and edges `u` and `v`, from "Ray Tracing:
+// The Next Week". Its front faces the side `u` x `v` points to, and its
+// texture coordinates run from 0 to 1 along each edge.
+pub struct Quad {
+    pub q: Point3,
+    pub u: Vector3,
+    pub v: Vector3,
+    pub mat: Arc<dyn Material>,
+    normal: Vector3,
+    // the plane is the set of points p with dot(normal, p) == d
+    d: f64,
+    // maps a point of the plane relative to `q` to its (alpha, beta) along
+    // `u` and `v`
+    w: Vector3,
+    area: f64,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vector3, v: Vector3, mat: Arc<dyn Material>) -> Self {
+        let n = Vector3::cross(&u, &v);
+        let normal = Vector3::unit_vector(&n);
+        let bbox = Aabb::surrounding(
+            &Aabb::from_points(q, q + u + v),
+            &Aabb::from_points(q + u, q + v),
+        )
+        .pad_to_minimums(1e-4);
+        Quad {
+            q,
+            u,
+            v,
+            mat,
+            normal,
+            d: Vector3::dot(&normal, &q),
+            w: n * (1.0 / Vector3::dot(&n, &n)),
+            area: n.length(),
+            bbox,
+        }
+    }
+
+    // Where `r` meets the quad as (t, alpha, beta), alpha and beta being the
+    // position along `u` and `v`.
+    fn intersect(&self, r: &Ray, int: Interval) -> Option<(f64, f64, f64)> {
+        let denom = Vector3::dot(&self.normal, r.direction());
+        // parallel to the plane
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+        let t = (self.d - Vector3::dot(&self.normal, r.origin())) / denom;
+        if !int.surrounds(t) {
+            return None;
+        }
+
+        let planar = r.at(t) - self.q;
+        let alpha = Vector3::dot(&self.w, &Vector3::cross(&planar, &self.v));
+        let beta = Vector3::dot(&self.w, &Vector3::cross(&self.u, &planar));
+        let unit = Interval::new(0.0, 1.0);
+        if !unit.contains(alpha) || !unit.contains(beta) {
+            return None;
+        }
+        Some((t, alpha, beta))
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+        progress::count_intersection_test();
+        let Some((t, alpha, beta)) = self.intersect(r, int) else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.mat = Some(Arc::clone(&self.mat));
+        rec.set_face_normal(r, &self.normal);
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_samplable(&self) -> bool {
+        true
+    }
+
+    // Sampled uniformly by area, converted to solid angle.
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3) -> f64 {
+        let r = Ray::new(*origin, *direction);
+        let Some((t, _, _)) = self.intersect(&r, Interval::new(0.001, f64::INFINITY)) else {
+            return 0.0;
+        };
+        let distance_squared = t * t * direction.length_squared();
+        let cosine = Vector3::dot(direction, &self.normal).abs() / direction.length();
+        if cosine < 1e-12 {
+            return 0.0;
+        }
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut Sampler) -> Vector3 {
+        let point =
+            self.q + self.u * random::random_double(rng) + self.v * random::random_double(rng);
+        point - *origin
+    }
+}
+
+// The axis-aligned box with opposite corners `a` and `b`, as six quads
+// facing outwards.
+pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
+    let min = Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+    let max = Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+    let dx = Vector3::new(max.x - min.x, 0.0, 0.0);
+    let dy = Vector3::new(0.0, max.y - min.y, 0.0);
+    let dz = Vector3::new(0.0, 0.0, max.z - min.z);
+
+    let mut sides = HittableList::new();
+    for (q, u, v) in [
+        (Point3::new(min.x, min.y, max.z), dx, dy),  // front
+        (Point3::new(max.x, min.y, max.z), -dz, dy), // right
+        (Point3::new(max.x, min.y, min.z), -dx, dy), // back
+        (Point3::new(min.x, min.y, min.z), dz, dy),  // left
+        (Point3::new(min.x, max.y, max.z), dx, -dz), // top
+        (Point3::new(min.x, min.y, min.z), dx, dz),  // bottom
+    ] {
+        sides.add(Box::new(Quad::new(q, u, v, mat.clone())));
+    }
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, material::Lambertian};
+
+    fn grey() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    // A 2 by 1 rectangle in the z = 0 plane, facing +z.
+    fn rectangle() -> Quad {
+        Quad::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            grey(),
+        )
+    }
+
+    fn hits(object: &dyn Hittable, r: &Ray) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        object
+            .hit(r, Interval::new(0.001, f64::INFINITY), &mut rec)
+            .then_some(rec)
+    }
+
+    fn down_at(x: f64, y: f64) -> Ray {
+        Ray::new(Point3::new(x, y, 3.0), Vector3::new(0.0, 0.0, -1.0))
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_quad_hit_inside() {
+        let rec = hits(&rectangle(), &down_at(1.5, 0.25)).unwrap();
+        assert_close(rec.t, 3.0);
+        assert_eq!(rec.p, Point3::new(1.5, 0.25, 0.0));
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert_close(rec.u, 0.75);
+        assert_close(rec.v, 0.25);
+    }
+
+    #[test]
+    fn test_quad_hit_outside_bounds() {
+        let quad = rectangle();
+        for (x, y) in [(-0.1, 0.5), (2.1, 0.5), (1.0, -0.1), (1.0, 1.1), (2.5, 1.5)] {
+            assert!(hits(&quad, &down_at(x, y)).is_none(), "hit at {}, {}", x, y);
+        }
+        let parallel = Ray::new(Point3::new(-1.0, 0.5, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(hits(&quad, &parallel).is_none());
+        let away = Ray::new(Point3::new(1.0, 0.5, 3.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(hits(&quad, &away).is_none());
+    }
+
+    #[test]
+    fn test_quad_hit_on_corners() {
+        let quad = rectangle();
+        for (x, y, u, v) in [
+            (0.0, 0.0, 0.0, 0.0),
+            (2.0, 0.0, 1.0, 0.0),
+            (0.0, 1.0, 0.0, 1.0),
+            (2.0, 1.0, 1.0, 1.0),
+        ] {
+            let rec = hits(&quad, &down_at(x, y)).unwrap();
+            assert_close(rec.u, u);
+            assert_close(rec.v, v);
+        }
+    }
+
+    #[test]
+    fn test_oriented_quad() {
+        // a rhombus tilted out of every axis plane
+        let quad = Quad::new(
+            Point3::new(1.0, 1.0, 1.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 1.0),
+            grey(),
+        );
+        let target = Point3::new(1.0, 1.0, 1.0)
+            + Vector3::new(1.0, 1.0, 0.0) * 0.3
+            + Vector3::new(0.0, 1.0, 1.0) * 0.6;
+        let origin = Point3::new(-2.0, 5.0, -1.0);
+        let rec = hits(&quad, &Ray::new(origin, target - origin)).unwrap();
+        assert!((rec.p - target).length() < 1e-9);
+        assert_close(rec.u, 0.3);
+        assert_close(rec.v, 0.6);
+
+        let outside = target + Vector3::new(1.0, 1.0, 0.0);
+        assert!(hits(&quad, &Ray::new(origin, outside - origin)).is_none());
+    }
+
+    #[test]
+    fn test_quad_from_behind() {
+        let r = Ray::new(Point3::new(1.0, 0.5, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let rec = hits(&rectangle(), &r).unwrap();
+        assert!(!rec.front_face);
+        assert_eq!(rec.normal, Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_quad_bounding_box_is_padded() {
+        let bbox = rectangle().bounding_box();
+        assert!(bbox.axis_interval(2).size() > 0.0);
+        assert_close(bbox.axis_interval(0).min, 0.0);
+        assert_close(bbox.axis_interval(0).max, 2.0);
+    }
+
+    #[test]
+    fn test_quad_light_sampling() {
+        let quad = rectangle();
+        let origin = Point3::new(0.3, 0.8, 2.0);
+        let mut rng = random::sampler(5, 0, 0);
+        for _ in 0..100 {
+            let direction = quad.random(&origin, &mut rng);
+            let rec = hits(&quad, &Ray::new(origin, direction)).unwrap();
+            assert_close(rec.t, 1.0);
+            // area 2 seen from height 2
+            let cosine = 2.0 / direction.length();
+            assert_close(
+                quad.pdf_value(&origin, &direction),
+                direction.length_squared() / (cosine * 2.0),
+            );
+        }
+        assert_eq!(quad.pdf_value(&origin, &Vector3::new(0.0, 0.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn test_make_box() {
+        let unit_box = make_box(
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(-1.0, -1.0, -1.0),
+            grey(),
+        );
+        let bbox = unit_box.bounding_box();
+        for axis in 0..3 {
+            // the faces are padded, so the box grows by a hair
+            assert!((bbox.axis_interval(axis).min + 1.0).abs() < 1e-4);
+            assert!((bbox.axis_interval(axis).max - 1.0).abs() < 1e-4);
+        }
+
+        // every face is hit from outside on its front
+        for direction in [
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        ] {
+            let origin = direction * 2.0 + Vector3::new(0.1, 0.2, 0.3);
+            let rec = hits(&unit_box, &Ray::new(origin, -direction)).unwrap();
+            assert!(rec.front_face, "face towards {:?}", direction);
+            assert_eq!(rec.normal, direction);
+            assert_close(Vector3::dot(&rec.p, &direction), 1.0);
+        }
+        assert_eq!(unit_box.into_objects().len(), 6);
+    }
+}
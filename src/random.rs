@@ -0,0 +1,69 @@
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+// Generator threaded through every sampling routine of the renderer.
+pub type Sampler = SmallRng;
+
+// Builds the generator for one camera sample. Each (pixel, sample) pair gets
+// its own stream derived from the render seed, so the result of a render is
+// fully determined by that seed no matter in which order pixels are traced.
+pub fn sampler(seed: u64, pixel: u64, sample: u64) -> Sampler {
+    SmallRng::seed_from_u64(mix(mix(seed, pixel), sample))
+}
+
+pub fn random_double(rng: &mut Sampler) -> f64 {
+    rng.gen()
+}
+
+pub fn random_range(rng: &mut Sampler, min: f64, max: f64) -> f64 {
+    rng.gen_range(min..max)
+}
+
+// SplitMix64 finalizer, used to derive well spread seeds from a single
+// render seed.
+pub fn mix(seed: u64, value: u64) -> u64 {
+    let mut z = seed ^ value.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampler_is_reproducible() {
+        let mut a = sampler(42, 3, 1);
+        let mut b = sampler(42, 3, 1);
+
+        let first: Vec<f64> = (0..8).map(|_| random_double(&mut a)).collect();
+        let second: Vec<f64> = (0..8).map(|_| random_double(&mut b)).collect();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_sampler_streams_differ() {
+        let from_pixel = random_double(&mut sampler(42, 3, 1));
+        let from_sample = random_double(&mut sampler(42, 3, 2));
+        let from_seed = random_double(&mut sampler(43, 3, 1));
+
+        assert_ne!(from_pixel, from_sample);
+        assert_ne!(from_pixel, from_seed);
+    }
+
+    #[test]
+    fn test_random_range_bounds() {
+        let mut rng = sampler(7, 0, 0);
+        for _ in 0..1000 {
+            let value = random_range(&mut rng, -2.0, 3.0);
+            assert!((-2.0..3.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_mix_spreads_neighbouring_values() {
+        assert_ne!(mix(1, 0), mix(1, 1));
+        assert_ne!(mix(1, 0), mix(2, 0));
+    }
+}
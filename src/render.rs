@@ -1,101 +1,1000 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression, Crc};
+
 use crate::color::Color;
+use crate::hdr::{invalid_data, read_hdr_image};
 use crate::interval::Interval;
 
-pub fn render_pixel(image_buffer: &mut Vec<String>, color: Color) {
+// The high dynamic range writers live with their readers.
+pub use crate::hdr::{ExrCompression, ExrWriter, PfmWriter, RgbeWriter};
+
+// Maps linear radiance to the non-linear values stored in 8-bit images.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum TransferFunction {
+    #[default]
+    Srgb,
+    Gamma(f64),
+    Linear,
+}
+
+impl TransferFunction {
+    pub fn encode(&self, linear: f64) -> f64 {
+        let linear = linear.max(0.0);
+        match self {
+            TransferFunction::Srgb => {
+                if linear <= 0.0031308 {
+                    12.92 * linear
+                } else {
+                    1.055 * linear.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma(gamma) => linear.powf(1.0 / gamma),
+            TransferFunction::Linear => linear,
+        }
+    }
+
+    // Inverse of `encode`, for colours read from 8-bit images and assets.
+    pub fn decode(&self, encoded: f64) -> f64 {
+        let encoded = encoded.max(0.0);
+        match self {
+            TransferFunction::Srgb => {
+                if encoded <= 0.04045 {
+                    encoded / 12.92
+                } else {
+                    ((encoded + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Gamma(gamma) => encoded.powf(*gamma),
+            TransferFunction::Linear => encoded,
+        }
+    }
+}
+
+// Parses "srgb", "linear", "gamma" (2.2) or "gamma=<exponent>".
+impl FromStr for TransferFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "srgb" => Ok(TransferFunction::Srgb),
+            "linear" => Ok(TransferFunction::Linear),
+            "gamma" => Ok(TransferFunction::Gamma(2.2)),
+            other => match other.strip_prefix("gamma=").map(str::parse::<f64>) {
+                Some(Ok(gamma)) if gamma > 0.0 => Ok(TransferFunction::Gamma(gamma)),
+                _ => Err(format!(
+                    "invalid transfer function '{}', expected srgb, linear, gamma or gamma=<exponent>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+pub fn render_pixel(color: Color, transfer: TransferFunction) -> [u8; 3] {
     static INTENSITY: Interval = Interval::new(0.000, 0.999);
 
-    let r = (256.0 * INTENSITY.clamp(color.red)) as i32;
-    let g = (256.0 * INTENSITY.clamp(color.green)) as i32;
-    let b = (256.0 * INTENSITY.clamp(color.blue)) as i32;
+    let r = (256.0 * INTENSITY.clamp(transfer.encode(color.red))) as u8;
+    let g = (256.0 * INTENSITY.clamp(transfer.encode(color.green))) as u8;
+    let b = (256.0 * INTENSITY.clamp(transfer.encode(color.blue))) as u8;
+
+    [r, g, b]
+}
+
+// Linear radiance of a rendered image, row by row from the top left pixel.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::new(0.0, 0.0, 0.0); width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    pub fn to_rgb8(&self, transfer: TransferFunction) -> Vec<[u8; 3]> {
+        self.pixels
+            .iter()
+            .map(|color| render_pixel(*color, transfer))
+            .collect()
+    }
+}
+
+pub trait ImageWriter {
+    fn write(&self, out: &mut dyn Write, image: &Framebuffer) -> io::Result<()>;
+}
+
+// ASCII "P3" portable pixmap, written by `write_plain_image`.
+pub struct PlainPpmWriter {
+    pub transfer: TransferFunction,
+}
+
+// Binary "P6" portable pixmap, written for `.ppm` and `.pnm` paths.
+pub struct PpmWriter {
+    pub transfer: TransferFunction,
+}
+
+// 8-bit RGB PNG, written for `.png` paths.
+pub struct PngWriter {
+    pub transfer: TransferFunction,
+}
+
+impl ImageWriter for PlainPpmWriter {
+    fn write(&self, out: &mut dyn Write, image: &Framebuffer) -> io::Result<()> {
+        write!(out, "P3\n{} {}\n255\n", image.width, image.height)?;
+        for [r, g, b] in image.to_rgb8(self.transfer) {
+            writeln!(out, "{} {} {}", r, g, b)?;
+        }
+        Ok(())
+    }
+}
+
+impl ImageWriter for PpmWriter {
+    fn write(&self, out: &mut dyn Write, image: &Framebuffer) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", image.width, image.height)?;
+        out.write_all(image.to_rgb8(self.transfer).as_flattened())
+    }
+}
+
+impl ImageWriter for PngWriter {
+    fn write(&self, out: &mut dyn Write, image: &Framebuffer) -> io::Result<()> {
+        out.write_all(&PNG_SIGNATURE)?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(image.width as u32).to_be_bytes());
+        header.extend_from_slice(&(image.height as u32).to_be_bytes());
+        // bit depth 8, truecolor, deflate, adaptive filtering, no interlace
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_png_chunk(out, b"IHDR", &header)?;
+
+        // every scanline is prefixed with its filter type, 0 meaning none
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        for row in image.to_rgb8(self.transfer).chunks(image.width.max(1)) {
+            encoder.write_all(&[0])?;
+            encoder.write_all(row.as_flattened())?;
+        }
+        write_png_chunk(out, b"IDAT", &encoder.finish()?)?;
+
+        write_png_chunk(out, b"IEND", &[])
+    }
+}
+
+fn write_png_chunk(out: &mut dyn Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    out.write_all(&crc.sum().to_be_bytes())
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+}
+
+// Picks the writer from the file extension, .ppm and .pnm get the binary (P6)
+// PPM. The transfer function only applies to 8-bit formats, high dynamic
+// range formats store linear values.
+pub fn writer_for_path(
+    path: &Path,
+    transfer: TransferFunction,
+) -> io::Result<Box<dyn ImageWriter>> {
+    match extension(path).as_deref() {
+        Some("png") => Ok(Box::new(PngWriter { transfer })),
+        Some("ppm") | Some("pnm") => Ok(Box::new(PpmWriter { transfer })),
+        Some("hdr") => Ok(Box::new(RgbeWriter)),
+        Some("pfm") => Ok(Box::new(PfmWriter)),
+        Some("exr") => Ok(Box::new(ExrWriter {
+            compression: ExrCompression::Zip,
+        })),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "unsupported image format for {}, expected .png, .pnm, .ppm, .hdr, .pfm or .exr",
+                path.display()
+            ),
+        )),
+    }
+}
+
+fn save(path: &Path, image: &Framebuffer, writer: &dyn ImageWriter) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writer.write(&mut out, image)?;
+    out.flush()
+}
 
-    image_buffer.push(format!("{} {} {}\n", r, g, b));
+pub fn write_image(path: &Path, image: &Framebuffer, transfer: TransferFunction) -> io::Result<()> {
+    save(path, image, writer_for_path(path, transfer)?.as_ref())
+}
+
+// Like `write_image`, but .ppm and .pnm paths get the plain (P3) PPM, which
+// is several times larger but readable as text.
+pub fn write_plain_image(
+    path: &Path,
+    image: &Framebuffer,
+    transfer: TransferFunction,
+) -> io::Result<()> {
+    match extension(path).as_deref() {
+        Some("ppm") | Some("pnm") => save(path, image, &PlainPpmWriter { transfer }),
+        _ => write_image(path, image, transfer),
+    }
+}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+// Decodes a non-interlaced PNG of any colour type and bit depth. Samples are
+// taken as sRGB and returned linear; alpha is dropped.
+pub fn read_png(data: &[u8]) -> io::Result<Framebuffer> {
+    if !data.starts_with(&PNG_SIGNATURE) {
+        return Err(invalid_data("missing PNG signature"));
+    }
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut compressed = Vec::new();
+    let mut rest = &data[8..];
+    loop {
+        if rest.len() < 12 {
+            return Err(invalid_data("truncated PNG chunk"));
+        }
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+        let chunk = rest
+            .get(8..8 + length)
+            .ok_or_else(|| invalid_data("truncated PNG chunk"))?;
+        match kind {
+            b"IHDR" if length == 13 => header = Some(chunk),
+            b"PLTE" => {
+                palette = chunk
+                    .chunks_exact(3)
+                    .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                    .collect()
+            }
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = rest
+            .get(12 + length..)
+            .ok_or_else(|| invalid_data("truncated PNG chunk"))?;
+    }
+
+    let header = header.ok_or_else(|| invalid_data("missing PNG header"))?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let (depth, color_type, interlace) = (header[8] as usize, header[9], header[12]);
+    if interlace != 0 {
+        return Err(invalid_data("interlaced PNG images are not supported"));
+    }
+    let channels = match (color_type, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) => 1,
+        (2, 8 | 16) => 3,
+        (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (6, 8 | 16) => 4,
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported PNG colour type {} with bit depth {}",
+                color_type, depth
+            )))
+        }
+    };
+    if color_type == 3 && palette.is_empty() {
+        return Err(invalid_data("missing PNG palette"));
+    }
+
+    // every row starts with its filter type byte
+    let too_large = || invalid_data("PNG image is too large");
+    let stride = width
+        .checked_mul(channels * depth)
+        .ok_or_else(too_large)?
+        .div_ceil(8);
+    let size = (stride + 1).checked_mul(height).ok_or_else(too_large)?;
+    // decompressing no more than the image needs keeps the header honest
+    let mut raw = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .take(size as u64)
+        .read_to_end(&mut raw)?;
+    if raw.len() < size {
+        return Err(invalid_data("truncated PNG image data"));
+    }
+    let rows = unfilter_png(&raw, stride, height, (channels * depth).div_ceil(8))?;
+
+    let max = ((1u32 << depth) - 1) as f64;
+    let mut image = Framebuffer::new(width, height);
+    for (y, row) in rows.chunks_exact(stride).enumerate() {
+        // the i-th sample of the row, as an integer
+        let sample = |i: usize| -> u32 {
+            match depth {
+                16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]) as u32,
+                8 => row[i] as u32,
+                _ => {
+                    let bit = i * depth;
+                    ((row[bit / 8] >> (8 - depth - bit % 8)) as u32) & ((1 << depth) - 1)
+                }
+            }
+        };
+        let decode = |value: u32| TransferFunction::Srgb.decode(value as f64 / max);
+        for x in 0..width {
+            let i = x * channels;
+            let color = match color_type {
+                0 | 4 => {
+                    let grey = decode(sample(i));
+                    Color::new(grey, grey, grey)
+                }
+                3 => {
+                    let [r, g, b] = *palette
+                        .get(sample(i) as usize)
+                        .ok_or_else(|| invalid_data("PNG palette index out of range"))?;
+                    let decode = |value: u8| TransferFunction::Srgb.decode(value as f64 / 255.0);
+                    Color::new(decode(r), decode(g), decode(b))
+                }
+                _ => Color::new(
+                    decode(sample(i)),
+                    decode(sample(i + 1)),
+                    decode(sample(i + 2)),
+                ),
+            };
+            image.set(x, y, color);
+        }
+    }
+    Ok(image)
+}
+
+// Undoes the per-scanline filters, returning the rows without their filter
+// type bytes. `bpp` is the number of bytes per pixel, at least one.
+fn unfilter_png(raw: &[u8], stride: usize, height: usize, bpp: usize) -> io::Result<Vec<u8>> {
+    let mut rows = vec![0u8; stride * height];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (done, current) = rows.split_at_mut(y * stride);
+        let previous = if y == 0 {
+            None
+        } else {
+            Some(&done[(y - 1) * stride..])
+        };
+        let current = &mut current[..stride];
+        for i in 0..stride {
+            let left = if i >= bpp { current[i - bpp] } else { 0 };
+            let up = previous.map_or(0, |row| row[i]);
+            let up_left = match previous {
+                Some(row) if i >= bpp => row[i - bpp],
+                _ => 0,
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(invalid_data(format!("invalid PNG filter type {}", filter))),
+            };
+            current[i] = line[i].wrapping_add(predicted);
+        }
+    }
+    Ok(rows)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// Decodes ASCII (P2, P3) and binary (P5, P6) grey and colour portable
+// any-maps with up to 16 bits per sample. Samples are taken as sRGB.
+pub fn read_ppm(data: &[u8]) -> io::Result<Framebuffer> {
+    let mut pos = 0;
+    // the next whitespace separated token, skipping comments
+    let token = |pos: &mut usize| -> io::Result<String> {
+        loop {
+            while data.get(*pos).is_some_and(|b| b.is_ascii_whitespace()) {
+                *pos += 1;
+            }
+            if data.get(*pos) != Some(&b'#') {
+                break;
+            }
+            while data.get(*pos).is_some_and(|&b| b != b'\n') {
+                *pos += 1;
+            }
+        }
+        let start = *pos;
+        while data.get(*pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+            *pos += 1;
+        }
+        if start == *pos {
+            return Err(invalid_data("truncated PPM image"));
+        }
+        Ok(String::from_utf8_lossy(&data[start..*pos]).into_owned())
+    };
+
+    let (channels, binary) = match token(&mut pos)?.as_str() {
+        "P2" => (1, false),
+        "P3" => (3, false),
+        "P5" => (1, true),
+        "P6" => (3, true),
+        _ => return Err(invalid_data("missing PPM signature")),
+    };
+    let number = |pos: &mut usize, what: &str| -> io::Result<usize> {
+        token(pos)?
+            .parse()
+            .map_err(|_| invalid_data(format!("invalid PPM {}", what)))
+    };
+    let width = number(&mut pos, "width")?;
+    let height = number(&mut pos, "height")?;
+    let max = number(&mut pos, "maximum value")?;
+    if max == 0 || max > 65535 {
+        return Err(invalid_data("invalid PPM maximum value"));
+    }
+
+    let size = if max > 255 { 2 } else { 1 };
+    // every sample takes at least one byte, even in ASCII
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .filter(|&count| count <= data.len())
+        .ok_or_else(|| invalid_data("truncated PPM pixel data"))?;
+    let samples: Vec<usize> = if binary {
+        // exactly one whitespace character separates the header from the data
+        pos += 1;
+        let bytes = data
+            .get(pos..pos + count * size)
+            .ok_or_else(|| invalid_data("truncated PPM pixel data"))?;
+        if size == 2 {
+            bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                .collect()
+        } else {
+            bytes.iter().map(|&b| b as usize).collect()
+        }
+    } else {
+        (0..count)
+            .map(|_| number(&mut pos, "sample"))
+            .collect::<io::Result<_>>()?
+    };
+
+    let decode = |value: usize| TransferFunction::Srgb.decode(value.min(max) as f64 / max as f64);
+    let mut image = Framebuffer::new(width, height);
+    for (i, pixel) in samples.chunks_exact(channels).enumerate() {
+        let color = match pixel {
+            [grey] => Color::new(decode(*grey), decode(*grey), decode(*grey)),
+            _ => Color::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2])),
+        };
+        image.set(i % width, i / width, color);
+    }
+    Ok(image)
+}
+
+// Reads an image as linear values, picking the format from the extension.
+// 8 and 16-bit formats are taken as sRGB encoded.
+pub fn read_image(path: &Path) -> io::Result<Framebuffer> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("png") => read_png(&fs::read(path)?),
+        Some("ppm" | "pnm" | "pgm") => read_ppm(&fs::read(path)?),
+        Some("hdr" | "pfm" | "exr") => read_hdr_image(path),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "unsupported image {}, expected .png, .ppm, .pnm, .pgm, .hdr, .pfm or .exr",
+                path.display()
+            ),
+        )),
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+
     use super::*;
 
+    const PIXELS: [[u8; 3]; 6] = [
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [0, 0, 0],
+        [128, 64, 192],
+        [255, 255, 255],
+    ];
+
+    fn image() -> Framebuffer {
+        let mut image = Framebuffer::new(3, 2);
+        image.pixels = vec![
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+            Color::new(0.0, 0.0, 0.0),
+            Color::new(0.5, 0.25, 0.75),
+            Color::new(1.0, 1.0, 1.0),
+        ];
+        image
+    }
+
+    fn encode(writer: &dyn ImageWriter) -> Vec<u8> {
+        let mut out = Vec::new();
+        writer.write(&mut out, &image()).unwrap();
+        out
+    }
+
     #[test]
     fn test_render_pixel_white() {
-        let mut buffer = Vec::new();
         let color = Color {
             red: 1.0,
             green: 1.0,
             blue: 1.0,
         };
-        render_pixel(&mut buffer, color);
 
-        let result = buffer.join("");
-        assert_eq!(result, "255 255 255\n");
+        assert_eq!(
+            render_pixel(color, TransferFunction::Linear),
+            [255, 255, 255]
+        );
     }
 
     #[test]
     fn test_render_pixel_black() {
-        let mut buffer = Vec::new();
         let color = Color {
             red: 0.0,
             green: 0.0,
             blue: 0.0,
         };
-        render_pixel(&mut buffer, color);
 
-        let result = buffer.join("");
-        assert_eq!(result, "0 0 0\n");
+        assert_eq!(render_pixel(color, TransferFunction::Linear), [0, 0, 0]);
     }
 
     #[test]
     fn test_render_pixel_red() {
-        let mut buffer = Vec::new();
         let color = Color {
             red: 1.0,
             green: 0.0,
             blue: 0.0,
         };
-        render_pixel(&mut buffer, color);
 
-        let result = buffer.join("");
-        assert_eq!(result, "255 0 0\n");
+        assert_eq!(render_pixel(color, TransferFunction::Linear), [255, 0, 0]);
     }
 
     #[test]
     fn test_render_pixel_fractional() {
-        let mut buffer = Vec::new();
         let color = Color {
             red: 0.5,
             green: 0.25,
             blue: 0.75,
         };
-        render_pixel(&mut buffer, color);
 
-        let result = buffer.join("");
-        assert_eq!(result, "128 64 192\n");
+        assert_eq!(
+            render_pixel(color, TransferFunction::Linear),
+            [128, 64, 192]
+        );
     }
 
     #[test]
     fn test_render_pixel_rounding() {
-        let mut buffer = Vec::new();
         let color = Color {
             red: 0.999,
             green: 0.001,
             blue: 0.5,
         };
-        render_pixel(&mut buffer, color);
 
-        let result = buffer.join("");
-        assert_eq!(result, "255 0 128\n");
+        assert_eq!(render_pixel(color, TransferFunction::Linear), [255, 0, 128]);
     }
 
     #[test]
     fn test_render_pixel_clamping() {
-        let mut buffer = Vec::new();
         let color = Color {
             red: 1.1,
             green: -0.1,
             blue: 2.0,
         };
-        render_pixel(&mut buffer, color);
 
-        let result = buffer.join("");
-        assert_eq!(result, "255 0 255\n");
+        assert_eq!(render_pixel(color, TransferFunction::Linear), [255, 0, 255]);
+    }
+
+    #[test]
+    fn test_render_pixel_srgb() {
+        let color = Color::new(0.5, 0.25, 0.75);
+
+        assert_eq!(render_pixel(color, TransferFunction::Srgb), [188, 137, 225]);
+    }
+
+    #[test]
+    fn test_render_pixel_srgb_linear_segment() {
+        let color = Color::new(0.002, 0.001, 0.0);
+
+        assert_eq!(render_pixel(color, TransferFunction::Srgb), [6, 3, 0]);
+    }
+
+    #[test]
+    fn test_render_pixel_gamma() {
+        let color = Color::new(0.5, 0.25, 0.75);
+
+        assert_eq!(
+            render_pixel(color, TransferFunction::Gamma(2.2)),
+            [186, 136, 224]
+        );
+    }
+
+    #[test]
+    fn test_transfer_functions_preserve_black_and_white() {
+        for transfer in [
+            TransferFunction::Srgb,
+            TransferFunction::Gamma(2.2),
+            TransferFunction::Linear,
+        ] {
+            assert_eq!(transfer.encode(0.0), 0.0);
+            assert!((transfer.encode(1.0) - 1.0).abs() < 1e-12);
+            assert_eq!(transfer.encode(-0.5), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_transfer_function_decode_inverts_encode() {
+        for transfer in [
+            TransferFunction::Srgb,
+            TransferFunction::Gamma(2.2),
+            TransferFunction::Linear,
+        ] {
+            for linear in [0.0, 0.001, 0.0031308, 0.2, 0.5, 1.0] {
+                let round_trip = transfer.decode(transfer.encode(linear));
+                assert!(
+                    (round_trip - linear).abs() < 1e-9,
+                    "{:?} {}",
+                    transfer,
+                    linear
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_transfer_function_from_str() {
+        assert_eq!("sRGB".parse(), Ok(TransferFunction::Srgb));
+        assert_eq!("linear".parse(), Ok(TransferFunction::Linear));
+        assert_eq!("gamma".parse(), Ok(TransferFunction::Gamma(2.2)));
+        assert_eq!("gamma=1.8".parse(), Ok(TransferFunction::Gamma(1.8)));
+        assert!("gamma=0".parse::<TransferFunction>().is_err());
+        assert!("log".parse::<TransferFunction>().is_err());
+    }
+
+    #[test]
+    fn test_plain_ppm_writer() {
+        let out = encode(&PlainPpmWriter {
+            transfer: TransferFunction::Linear,
+        });
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "P3\n3 2\n255\n255 0 0\n0 255 0\n0 0 255\n0 0 0\n128 64 192\n255 255 255\n"
+        );
+    }
+
+    #[test]
+    fn test_ppm_writer() {
+        let out = encode(&PpmWriter {
+            transfer: TransferFunction::Linear,
+        });
+        let header = b"P6\n3 2\n255\n";
+
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(&out[header.len()..], PIXELS.as_flattened());
+    }
+
+    #[test]
+    fn test_png_writer() {
+        let out = encode(&PngWriter {
+            transfer: TransferFunction::Linear,
+        });
+
+        assert_eq!(
+            &out[..8],
+            &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+        );
+
+        // walk the chunks, checking lengths and CRCs
+        let mut chunks = Vec::new();
+        let mut rest = &out[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let kind = &rest[4..8];
+            let data = &rest[8..8 + length];
+            let mut crc = Crc::new();
+            crc.update(kind);
+            crc.update(data);
+            let stored = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+            assert_eq!(crc.sum(), stored);
+            chunks.push((kind.to_vec(), data.to_vec()));
+            rest = &rest[12 + length..];
+        }
+
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| kind.as_slice()).collect();
+        assert_eq!(kinds, vec![&b"IHDR"[..], &b"IDAT"[..], &b"IEND"[..]]);
+        assert_eq!(chunks[0].1, vec![0, 0, 0, 3, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+
+        let mut scanlines = Vec::new();
+        ZlibDecoder::new(chunks[1].1.as_slice())
+            .read_to_end(&mut scanlines)
+            .unwrap();
+        let mut expected = vec![0];
+        expected.extend_from_slice(PIXELS[..3].as_flattened());
+        expected.push(0);
+        expected.extend_from_slice(PIXELS[3..].as_flattened());
+        assert_eq!(scanlines, expected);
+    }
+
+    #[test]
+    fn test_writer_for_path() {
+        let encode_path = |path: &str| {
+            encode(
+                writer_for_path(Path::new(path), TransferFunction::Srgb)
+                    .unwrap()
+                    .as_ref(),
+            )
+        };
+
+        assert_eq!(&encode_path("out/image.PNG")[1..4], b"PNG");
+        assert_eq!(&encode_path("image.pnm")[..2], b"P6");
+        assert_eq!(&encode_path("image.ppm")[..2], b"P6");
+        assert_eq!(&encode_path("image.hdr")[..10], b"#?RADIANCE");
+        assert_eq!(&encode_path("image.pfm")[..2], b"PF");
+        assert_eq!(&encode_path("image.exr")[..4], &[0x76, 0x2f, 0x31, 0x01]);
+        assert!(writer_for_path(Path::new("image.jpg"), TransferFunction::Srgb).is_err());
+        assert!(writer_for_path(Path::new("image"), TransferFunction::Srgb).is_err());
+    }
+
+    #[test]
+    fn test_ldr_writers_apply_transfer_function() {
+        let out = encode(&PpmWriter {
+            transfer: TransferFunction::Srgb,
+        });
+        let header = b"P6\n3 2\n255\n";
+
+        assert_eq!(&out[header.len() + 12..header.len() + 15], &[188, 137, 225]);
+    }
+
+    #[test]
+    fn test_framebuffer_get_set() {
+        let mut image = Framebuffer::new(4, 3);
+        image.set(3, 2, Color::new(0.1, 0.2, 0.3));
+
+        assert_eq!(image.pixels.len(), 12);
+        assert_eq!(image.get(3, 2), Color::new(0.1, 0.2, 0.3));
+        assert_eq!(image.pixels[11], Color::new(0.1, 0.2, 0.3));
+    }
+
+    // A PNG of the given header fields and already filtered scanlines.
+    fn png(
+        width: u32,
+        height: u32,
+        depth: u8,
+        color_type: u8,
+        palette: &[u8],
+        rows: &[u8],
+    ) -> Vec<u8> {
+        let mut out = PNG_SIGNATURE.to_vec();
+        let mut header = Vec::new();
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[depth, color_type, 0, 0, 0]);
+        write_png_chunk(&mut out, b"IHDR", &header).unwrap();
+        if !palette.is_empty() {
+            write_png_chunk(&mut out, b"PLTE", palette).unwrap();
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(rows).unwrap();
+        write_png_chunk(&mut out, b"IDAT", &encoder.finish().unwrap()).unwrap();
+        write_png_chunk(&mut out, b"IEND", &[]).unwrap();
+        out
+    }
+
+    fn assert_image_near(actual: &Framebuffer, expected: &Framebuffer, tolerance: f64) {
+        assert_eq!(
+            (actual.width, actual.height),
+            (expected.width, expected.height)
+        );
+        for (a, b) in actual.pixels.iter().zip(&expected.pixels) {
+            assert!(
+                (a.red - b.red).abs() <= tolerance
+                    && (a.green - b.green).abs() <= tolerance
+                    && (a.blue - b.blue).abs() <= tolerance,
+                "{:?} != {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let out = encode(&PngWriter {
+            transfer: TransferFunction::Srgb,
+        });
+        assert_image_near(&read_png(&out).unwrap(), &image(), 0.01);
+    }
+
+    #[test]
+    fn test_read_png_undoes_every_filter() {
+        // five rows of two RGB pixels, each row stored with a different
+        // filter type
+        let pixels: Vec<[u8; 6]> = (0..5u8)
+            .map(|y| [10 * y, 200 - y, 7 + 40 * y, 255 - 30 * y, 3 * y, 90])
+            .collect();
+        let mut rows = Vec::new();
+        for (y, row) in pixels.iter().enumerate() {
+            let filter = y as u8;
+            rows.push(filter);
+            for i in 0..6 {
+                let left = if i >= 3 { row[i - 3] } else { 0 };
+                let up = if y > 0 { pixels[y - 1][i] } else { 0 };
+                let up_left = if y > 0 && i >= 3 {
+                    pixels[y - 1][i - 3]
+                } else {
+                    0
+                };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    _ => paeth(left, up, up_left),
+                };
+                rows.push(row[i].wrapping_sub(predicted));
+            }
+        }
+
+        let image = read_png(&png(2, 5, 8, 2, &[], &rows)).unwrap();
+        let decode = |value: u8| TransferFunction::Srgb.decode(value as f64 / 255.0);
+        for (y, row) in pixels.iter().enumerate() {
+            for x in 0..2 {
+                let expected = Color::new(
+                    decode(row[3 * x]),
+                    decode(row[3 * x + 1]),
+                    decode(row[3 * x + 2]),
+                );
+                assert_eq!(image.get(x, y), expected, "pixel {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_png_palette_and_grey() {
+        // three 2-bit palette indices packed into one byte: 2, 0, 1
+        let palette = [0, 0, 0, 255, 255, 255, 255, 0, 0];
+        let image = read_png(&png(3, 1, 2, 3, &palette, &[0, 0b1000_0100])).unwrap();
+        assert_eq!(image.get(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(image.get(1, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(image.get(2, 0), Color::new(1.0, 1.0, 1.0));
+
+        // 16-bit grey with alpha, the alpha is dropped
+        let image = read_png(&png(1, 1, 16, 4, &[], &[0, 0xff, 0xff, 0, 0])).unwrap();
+        assert_eq!(image.get(0, 0), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_read_png_errors() {
+        let err = |data: &[u8]| read_png(data).err().unwrap().to_string();
+        assert_eq!(err(b"GIF89a"), "missing PNG signature");
+        assert_eq!(err(&png(1, 1, 8, 3, &[], &[0, 0])), "missing PNG palette");
+        assert_eq!(
+            err(&png(1, 1, 8, 2, &[], &[7, 0, 0, 0])),
+            "invalid PNG filter type 7"
+        );
+        assert_eq!(
+            err(&png(2, 2, 8, 2, &[], &[0, 1, 2, 3])),
+            "truncated PNG image data"
+        );
+        assert_eq!(
+            err(&png(1, 1, 4, 2, &[], &[0, 0])),
+            "unsupported PNG colour type 2 with bit depth 4"
+        );
+
+        // headers claiming more pixels than there is data for
+        assert_eq!(
+            err(&png(u32::MAX, u32::MAX, 16, 6, &[], &[0, 1, 2, 3])),
+            "PNG image is too large"
+        );
+        assert_eq!(
+            err(&png(u32::MAX, 1 << 20, 8, 2, &[], &[0, 1, 2, 3])),
+            "truncated PNG image data"
+        );
+    }
+
+    #[test]
+    fn test_ppm_round_trips() {
+        for writer in [
+            &PlainPpmWriter {
+                transfer: TransferFunction::Srgb,
+            } as &dyn ImageWriter,
+            &PpmWriter {
+                transfer: TransferFunction::Srgb,
+            },
+        ] {
+            assert_image_near(&read_ppm(&encode(writer)).unwrap(), &image(), 0.01);
+        }
+    }
+
+    #[test]
+    fn test_read_ppm_comments_grey_and_16_bit() {
+        let image = read_ppm(b"P2\n# a comment\n2 1 # trailing\n4\n0 4\n").unwrap();
+        assert_eq!(image.get(0, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(image.get(1, 0), Color::new(1.0, 1.0, 1.0));
+
+        let mut data = b"P6\n1 1\n65535\n".to_vec();
+        data.extend_from_slice(&[0xff, 0xff, 0, 0, 0xff, 0xff]);
+        assert_eq!(
+            read_ppm(&data).unwrap().get(0, 0),
+            Color::new(1.0, 0.0, 1.0)
+        );
+
+        let err = |data: &[u8]| read_ppm(data).err().unwrap().to_string();
+        assert_eq!(err(b"P6\n2 2\n255\n\x01\x02"), "truncated PPM pixel data");
+        assert_eq!(
+            err(b"P6\n4294967296 4294967296 255\n\x01\x02"),
+            "truncated PPM pixel data"
+        );
+        assert_eq!(
+            err(b"P3\n100000 100000 255\n0 0 0\n"),
+            "truncated PPM pixel data"
+        );
+        assert_eq!(err(b"P6\n-1 1 255\n\x01"), "invalid PPM width");
+    }
+
+    #[test]
+    fn test_read_image_by_extension() {
+        let dir = std::env::temp_dir().join(format!("raytracing-images-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.png", "a.ppm", "a.pnm", "a.pfm"] {
+            let path = dir.join(name);
+            write_image(&path, &image(), TransferFunction::Srgb).unwrap();
+            assert_image_near(&read_image(&path).unwrap(), &image(), 0.01);
+        }
+        let plain = dir.join("plain.ppm");
+        write_plain_image(&plain, &image(), TransferFunction::Srgb).unwrap();
+        assert!(fs::read(&plain).unwrap().starts_with(b"P3\n3 2\n255\n"));
+        assert_image_near(&read_image(&plain).unwrap(), &image(), 0.01);
+        write_plain_image(&dir.join("a.png"), &image(), TransferFunction::Srgb).unwrap();
+        assert_eq!(&fs::read(dir.join("a.png")).unwrap()[1..4], b"PNG");
+
+        let err = read_image(&dir.join("a.gif")).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
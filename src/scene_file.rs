@@ -0,0 +1,1018 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    camera::{Background, Camera},
+    color::Color,
+    environment::EnvironmentMap,
+    gltf::{load_gltf, Gltf},
+    hittable::Hittable,
+    hittable_list::HittableList,
+    json::{self, Json, Member},
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    mesh::MeshError,
+    obj::load_obj,
+    ply::load_ply,
+    render::{writer_for_path, TransferFunction},
+    scenes::{parse_aspect_ratio, RenderSettings, Scene},
+    sky::PhysicalSky,
+    sphere::Sphere,
+    texture::{CheckerTexture, ImageTexture, NoiseStyle, NoiseTexture, SolidColor, Texture},
+    triangle::Triangle,
+    vector::Vector3,
+};
+
+// Scene files are JSON documents of the form
+//
+// {
+//   "render": { "width": 800, "aspect": "16:9", "spp": 64, "max_depth": 10,
+//               "min_depth": 3, "seed": 1, "transfer": "srgb", "output": "image.png" },
+//   "camera": { "lookfrom": [13, 2, 3], "lookat": [0, 0, 0], "vup": [0, 1, 0],
+//               "vfov": 20, "defocus_angle": 0.6, "focus_dist": 10,
+//               "background": "sky" },
+//   "materials": {
+//     "ground": { "type": "lambertian", "albedo": [0.5, 0.5, 0.5] },
+//     "steel": { "type": "metal", "albedo": [0.7, 0.6, 0.5], "fuzz": 0.1 },
+//     "glass": { "type": "dielectric", "refraction_index": 1.5 },
+//     "lamp": { "type": "diffuse_light", "emit": [4, 4, 4] },
+//     "floor": { "type": "lambertian", "albedo": { "type": "checker", "scale": 0.5,
+//                "even": [0.2, 0.3, 0.1], "odd": { "type": "image", "file": "tiles.png" } } },
+//     "stone": { "type": "lambertian", "albedo": { "type": "noise", "style": "marble" } }
+//   },
+//   "objects": [
+//     { "type": "sphere", "center": [0, -1000, 0], "radius": 1000, "material": "ground" },
+//     { "type": "sphere", "center": [0, 1, 0], "radius": 1,
+//       "material": { "type": "dielectric", "refraction_index": 1.5 } },
+//     { "type": "triangle", "vertices": [[-1, 0, 0], [1, 0, 0], [0, 2, 0]],
+//       "normals": [[0, 0, 1], [0, 0, 1], [0, 0, 1]], "uvs": [[0, 0], [1, 0], [0.5, 1]],
+//       "material": "steel" },
+//     { "type": "mesh", "file": "teapot.obj", "material": "ground" }
+//   ]
+// }
+//
+// Every section except "objects" is optional. The camera's "background" is
+//
+// - "sky" (a blue gradient), "black" or a color,
+// - { "type": "environment", "file": "studio.hdr", "rotation": 90,
+//   "intensity": 2 }, an equirectangular image around the scene, turned by
+//   `rotation` degrees about the vertical axis, that also lights it, or
+// - { "type": "sky", "sun_direction": [1, 0.5, 0], "turbidity": 3,
+//   "ground_albedo": [0.3, 0.3, 0.3] }, the Preetham daylight sky with a sun
+//   that is sampled as a light.
+//
+// Wherever a material takes a color (`albedo` of lambertian and `emit` of
+// diffuse_light) it also takes a texture: a 3D "checker" of two textures, an
+// "image" (.png, .ppm, .hdr, ...) mapped by the surface's (u, v), or Perlin
+// "noise" that is "smooth", "turbulence" or "marble". Objects refer to
+// materials by name or define them inline. Mesh files (.obj, .ply, .gltf or
+// .glb) are relative to the scene file, and their own materials or vertex
+// colours take precedence over the object's.
+#[derive(Debug)]
+pub struct SceneError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// Loads a JSON scene file, or a .gltf/.glb asset together with its camera.
+pub fn load(path: &Path) -> std::result::Result<Scene, SceneError> {
+    if has_extension(path, &["gltf", "glb"]) {
+        return load_gltf(path, default_mesh_material())
+            .map(Gltf::into_scene)
+            .map_err(|err| SceneError {
+                path: err.path,
+                line: err.line,
+                message: err.message,
+            });
+    }
+    let text = fs::read_to_string(path).map_err(|err| SceneError {
+        path: path.to_path_buf(),
+        line: None,
+        message: err.to_string(),
+    })?;
+    parse(&text, path)
+}
+
+// Builds the scene described by `text`. `path` names the file in error
+// messages, and mesh, image and environment files are read relative to its
+// directory.
+pub fn parse(text: &str, path: &Path) -> std::result::Result<Scene, SceneError> {
+    let document = json::parse(text).map_err(|err| SceneError {
+        path: path.to_path_buf(),
+        line: Some(err.line),
+        message: format!("column {}: {}", err.column, err.message),
+    })?;
+    let mut loader = Loader {
+        base: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        materials: HashMap::new(),
+    };
+    loader.scene(&document).map_err(|err| SceneError {
+        path: path.to_path_buf(),
+        line: Some(err.line),
+        message: err.message,
+    })
+}
+
+struct Error {
+    line: usize,
+    message: String,
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T> {
+    Err(Error {
+        line,
+        message: message.into(),
+    })
+}
+
+// Checks that `node` is an object holding only `keys`, each at most once.
+fn object<'a>(node: &'a Json, what: &str, keys: &[&str]) -> Result<&'a [Member]> {
+    let members = match node.as_object() {
+        Some(members) => members,
+        None => return error(node.line, format!("expected {} to be an object", what)),
+    };
+    for (i, member) in members.iter().enumerate() {
+        if !keys.contains(&member.key.as_str()) {
+            return error(
+                member.line,
+                format!(
+                    "unknown key '{}' in {}, expected one of: {}",
+                    member.key,
+                    what,
+                    keys.join(", ")
+                ),
+            );
+        }
+        if members[..i].iter().any(|other| other.key == member.key) {
+            return error(
+                member.line,
+                format!("duplicate key '{}' in {}", member.key, what),
+            );
+        }
+    }
+    Ok(members)
+}
+
+fn required<'a>(node: &'a Json, key: &str, what: &str) -> Result<&'a Json> {
+    match node.get(key) {
+        Some(value) => Ok(value),
+        None => error(node.line, format!("missing field '{}' in {}", key, what)),
+    }
+}
+
+fn number(node: &Json, key: &str) -> Result<f64> {
+    match node.as_f64() {
+        Some(number) => Ok(number),
+        None => error(
+            node.line,
+            format!("expected a number for '{}', found {}", key, node.kind()),
+        ),
+    }
+}
+
+fn positive(node: &Json, key: &str) -> Result<f64> {
+    let value = number(node, key)?;
+    if value <= 0.0 {
+        return error(
+            node.line,
+            format!("invalid value for '{}': must be greater than zero", key),
+        );
+    }
+    Ok(value)
+}
+
+fn integer(node: &Json, key: &str, min: i64, max: i64) -> Result<i64> {
+    let value = number(node, key)?;
+    if value.fract() != 0.0 {
+        return error(
+            node.line,
+            format!("invalid value for '{}': expected a whole number", key),
+        );
+    }
+    if value < min as f64 || value > max as f64 {
+        return error(
+            node.line,
+            format!(
+                "invalid value for '{}': must be between {} and {}",
+                key, min, max
+            ),
+        );
+    }
+    Ok(value as i64)
+}
+
+fn string<'a>(node: &'a Json, key: &str) -> Result<&'a str> {
+    match node.as_str() {
+        Some(string) => Ok(string),
+        None => error(
+            node.line,
+            format!("expected a string for '{}', found {}", key, node.kind()),
+        ),
+    }
+}
+
+fn vector(node: &Json, key: &str) -> Result<Vector3> {
+    match node.as_array() {
+        Some([x, y, z]) => Ok(Vector3::new(
+            number(x, key)?,
+            number(y, key)?,
+            number(z, key)?,
+        )),
+        _ => error(
+            node.line,
+            format!("expected an array of three numbers for '{}'", key),
+        ),
+    }
+}
+
+fn uv(node: &Json, key: &str) -> Result<(f64, f64)> {
+    match node.as_array() {
+        Some([u, v]) => Ok((number(u, key)?, number(v, key)?)),
+        _ => error(
+            node.line,
+            format!("expected an array of two numbers for '{}'", key),
+        ),
+    }
+}
+
+// Reads an array of exactly three items, one per triangle vertex.
+fn triple<T>(node: &Json, key: &str, item: fn(&Json, &str) -> Result<T>) -> Result<[T; 3]> {
+    match node.as_array() {
+        Some([a, b, c]) => Ok([item(a, key)?, item(b, key)?, item(c, key)?]),
+        _ => error(
+            node.line,
+            format!("expected an array of three entries for '{}'", key),
+        ),
+    }
+}
+
+fn color(node: &Json, key: &str) -> Result<Color> {
+    let v = vector(node, key)?;
+    if v.x < 0.0 || v.y < 0.0 || v.z < 0.0 {
+        return error(
+            node.line,
+            format!(
+                "invalid value for '{}': components must not be negative",
+                key
+            ),
+        );
+    }
+    Ok(Color::new(v.x, v.y, v.z))
+}
+
+fn noise_style(node: &Json) -> Result<NoiseStyle> {
+    match string(node, "style")? {
+        "smooth" => Ok(NoiseStyle::Smooth),
+        "turbulence" => Ok(NoiseStyle::Turbulence),
+        "marble" => Ok(NoiseStyle::Marble),
+        _ => error(
+            node.line,
+            "invalid value for 'style': expected smooth, turbulence or marble",
+        ),
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+// Meshes fall back to this for faces their file gives no material.
+fn default_mesh_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)))
+}
+
+const OBJECT_TYPES: [&str; 3] = ["sphere", "triangle", "mesh"];
+
+struct Loader {
+    // directory of the scene file, which mesh paths are relative to
+    base: PathBuf,
+    materials: HashMap<String, Arc<dyn Material>>,
+}
+
+impl Loader {
+    fn scene(&mut self, document: &Json) -> Result<Scene> {
+        object(
+            document,
+            "scene",
+            &["render", "camera", "materials", "objects"],
+        )?;
+
+        let settings = match document.get("render") {
+            Some(render) => self.settings(render)?,
+            None => RenderSettings::default(),
+        };
+
+        let mut camera = Camera::builder();
+        if let Some(node) = document.get("camera") {
+            object(
+                node,
+                "camera",
+                &[
+                    "lookfrom",
+                    "lookat",
+                    "vup",
+                    "vfov",
+                    "defocus_angle",
+                    "focus_dist",
+                    "background",
+                ],
+            )?;
+            if let Some(value) = node.get("lookfrom") {
+                camera = camera.lookfrom(vector(value, "lookfrom")?);
+            }
+            if let Some(value) = node.get("lookat") {
+                camera = camera.lookat(vector(value, "lookat")?);
+            }
+            if let Some(value) = node.get("vup") {
+                camera = camera.vup(vector(value, "vup")?);
+            }
+            if let Some(value) = node.get("vfov") {
+                let vfov = positive(value, "vfov")?;
+                if vfov >= 180.0 {
+                    return error(
+                        value.line,
+                        "invalid value for 'vfov': must be less than 180 degrees",
+                    );
+                }
+                camera = camera.vfov(vfov);
+            }
+            if let Some(value) = node.get("defocus_angle") {
+                let angle = number(value, "defocus_angle")?;
+                if angle < 0.0 {
+                    return error(
+                        value.line,
+                        "invalid value for 'defocus_angle': must not be negative",
+                    );
+                }
+                camera = camera.defocus_angle(angle);
+            }
+            if let Some(value) = node.get("focus_dist") {
+                camera = camera.focus_dist(positive(value, "focus_dist")?);
+            }
+            if let Some(value) = node.get("background") {
+                camera = camera.background(self.background(value)?);
+            }
+            if let Err(err) = camera.validate() {
+                return error(node.line, format!("invalid camera: {}", err));
+            }
+        }
+
+        if let Some(node) = document.get("materials") {
+            let members = match node.as_object() {
+                Some(members) => members,
+                None => return error(node.line, "expected materials to be an object"),
+            };
+            for member in members {
+                if self.materials.contains_key(&member.key) {
+                    return error(member.line, format!("duplicate material '{}'", member.key));
+                }
+                let what = format!("material '{}'", member.key);
+                let material = self.material(&member.value, &what)?;
+                self.materials.insert(member.key.clone(), material);
+            }
+        }
+
+        let objects = required(document, "objects", "scene")?;
+        let items = match objects.as_array() {
+            Some(items) => items,
+            None => return error(objects.line, "expected objects to be an array"),
+        };
+        let mut world = HittableList::new();
+        let mut lights = HittableList::new();
+        for item in items {
+            self.add_object(&mut world, &mut lights, item)?;
+        }
+
+        Ok(Scene {
+            world,
+            lights,
+            camera,
+            settings,
+        })
+    }
+
+    fn settings(&self, node: &Json) -> Result<RenderSettings> {
+        object(
+            node,
+            "render",
+            &[
+                "width",
+                "aspect",
+                "spp",
+                "max_depth",
+                "min_depth",
+                "seed",
+                "transfer",
+                "output",
+            ],
+        )?;
+        let mut settings = RenderSettings::default();
+        if let Some(value) = node.get("width") {
+            settings.width = Some(integer(value, "width", 1, i32::MAX as i64)? as i32);
+        }
+        if let Some(value) = node.get("aspect") {
+            let ratio = match value.as_f64() {
+                Some(ratio) if ratio > 0.0 => Ok(ratio),
+                Some(_) => Err(String::from("must be a positive ratio")),
+                None => parse_aspect_ratio(string(value, "aspect")?),
+            };
+            match ratio {
+                Ok(ratio) => settings.aspect_ratio = Some(ratio),
+                Err(reason) => {
+                    return error(
+                        value.line,
+                        format!("invalid value for 'aspect': {}", reason),
+                    )
+                }
+            }
+        }
+        if let Some(value) = node.get("spp") {
+            settings.samples_per_pixel = Some(integer(value, "spp", 1, i32::MAX as i64)? as i32);
+        }
+        if let Some(value) = node.get("max_depth") {
+            settings.max_depth = Some(integer(value, "max_depth", 1, i32::MAX as i64)? as i32);
+        }
+        if let Some(value) = node.get("min_depth") {
+            settings.min_depth = Some(integer(value, "min_depth", 1, i32::MAX as i64)? as i32);
+        }
+        if let Some(value) = node.get("seed") {
+            // JSON numbers are doubles, so larger seeds would lose precision
+            settings.seed = Some(integer(value, "seed", 0, 1 << 53)? as u64);
+        }
+        if let Some(value) = node.get("transfer") {
+            match string(value, "transfer")?.parse::<TransferFunction>() {
+                Ok(transfer) => settings.transfer = Some(transfer),
+                Err(reason) => return error(value.line, reason),
+            }
+        }
+        if let Some(value) = node.get("output") {
+            let path = PathBuf::from(string(value, "output")?);
+            if let Err(err) = writer_for_path(&path, TransferFunction::default()) {
+                return error(value.line, err.to_string());
+            }
+            settings.output = Some(path);
+        }
+        Ok(settings)
+    }
+
+    fn material(&self, node: &Json, what: &str) -> Result<Arc<dyn Material>> {
+        let kind = string(required(node, "type", what)?, "type")?;
+        match kind {
+            "lambertian" => {
+                object(node, what, &["type", "albedo"])?;
+                let albedo = self.texture(required(node, "albedo", what)?, "albedo")?;
+                Ok(Arc::new(Lambertian::from_texture(albedo)))
+            }
+            "metal" => {
+                object(node, what, &["type", "albedo", "fuzz"])?;
+                let albedo = color(required(node, "albedo", what)?, "albedo")?;
+                let fuzz = match node.get("fuzz") {
+                    Some(value) => {
+                        let fuzz = number(value, "fuzz")?;
+                        if !(0.0..=1.0).contains(&fuzz) {
+                            return error(
+                                value.line,
+                                "invalid value for 'fuzz': must be between 0 and 1",
+                            );
+                        }
+                        fuzz
+                    }
+                    None => 0.0,
+                };
+                Ok(Arc::new(Metal::new(albedo, fuzz)))
+            }
+            "diffuse_light" => {
+                object(node, what, &["type", "emit"])?;
+                let emit = self.texture(required(node, "emit", what)?, "emit")?;
+                Ok(Arc::new(DiffuseLight::from_texture(emit)))
+            }
+            "dielectric" => {
+                object(node, what, &["type", "refraction_index"])?;
+                let index = positive(
+                    required(node, "refraction_index", what)?,
+                    "refraction_index",
+                )?;
+                Ok(Arc::new(Dielectric::new(index)))
+            }
+            other => error(
+                node.get("type").map_or(node.line, |value| value.line),
+                format!(
+                    "unknown material type '{}', expected lambertian, metal, dielectric or diffuse_light",
+                    other
+                ),
+            ),
+        }
+    }
+
+    fn background(&self, node: &Json) -> Result<Background> {
+        match node.as_str() {
+            Some("sky") => return Ok(Background::Sky),
+            Some("black") => return Ok(Background::BLACK),
+            Some(_) => return error(
+                node.line,
+                "invalid value for 'background': expected sky, black, a color or an object of type environment or sky",
+            ),
+            None => {}
+        }
+        if node.as_object().is_none() {
+            return Ok(Background::Solid(color(node, "background")?));
+        }
+        let what = "background";
+        match string(required(node, "type", what)?, "type")? {
+            "environment" => {
+                object(node, what, &["type", "file", "rotation", "intensity"])?;
+                let file = required(node, "file", what)?;
+                let path = self.base.join(string(file, "file")?);
+                let rotation = match node.get("rotation") {
+                    Some(value) => number(value, "rotation")?,
+                    None => 0.0,
+                };
+                let intensity = match node.get("intensity") {
+                    Some(value) => positive(value, "intensity")?,
+                    None => 1.0,
+                };
+                match EnvironmentMap::load(&path, rotation, intensity) {
+                    Ok(map) => Ok(Background::Environment(Arc::new(map))),
+                    Err(err) => error(file.line, format!("{}: {}", path.display(), err)),
+                }
+            }
+            "sky" => {
+                object(
+                    node,
+                    what,
+                    &["type", "sun_direction", "turbidity", "ground_albedo"],
+                )?;
+                let sun = vector(required(node, "sun_direction", what)?, "sun_direction")?;
+                if sun.length_squared() == 0.0 {
+                    return error(
+                        node.get("sun_direction")
+                            .map_or(node.line, |value| value.line),
+                        "invalid value for 'sun_direction': must not be zero",
+                    );
+                }
+                let turbidity = match node.get("turbidity") {
+                    Some(value) => {
+                        let turbidity = number(value, "turbidity")?;
+                        if !(1.7..=10.0).contains(&turbidity) {
+                            return error(
+                                value.line,
+                                "invalid value for 'turbidity': must be between 1.7 and 10",
+                            );
+                        }
+                        turbidity
+                    }
+                    None => 3.0,
+                };
+                let ground_albedo = match node.get("ground_albedo") {
+                    Some(value) => color(value, "ground_albedo")?,
+                    None => Color::new(0.3, 0.3, 0.3),
+                };
+                Ok(Background::PhysicalSky(Arc::new(PhysicalSky::new(
+                    sun,
+                    turbidity,
+                    ground_albedo,
+                ))))
+            }
+            other => error(
+                node.get("type").map_or(node.line, |value| value.line),
+                format!(
+                    "unknown background type '{}', expected environment or sky",
+                    other
+                ),
+            ),
+        }
+    }
+
+    // A plain color, or a texture object.
+    fn texture(&self, node: &Json, key: &str) -> Result<Arc<dyn Texture>> {
+        if node.as_array().is_some() {
+            return Ok(Arc::new(SolidColor::new(color(node, key)?)));
+        }
+        if node.as_object().is_none() {
+            return error(
+                node.line,
+                format!("expected a color or a texture for '{}'", key),
+            );
+        }
+        let what = format!("texture of '{}'", key);
+        let scale = |default: f64| match node.get("scale") {
+            Some(value) => positive(value, "scale"),
+            None => Ok(default),
+        };
+        match string(required(node, "type", &what)?, "type")? {
+            "checker" => {
+                object(node, &what, &["type", "scale", "even", "odd"])?;
+                Ok(Arc::new(CheckerTexture::new(
+                    scale(1.0)?,
+                    self.texture(required(node, "even", &what)?, "even")?,
+                    self.texture(required(node, "odd", &what)?, "odd")?,
+                )))
+            }
+            "image" => {
+                object(node, &what, &["type", "file"])?;
+                let file = required(node, "file", &what)?;
+                let path = self.base.join(string(file, "file")?);
+                match ImageTexture::load(&path) {
+                    Ok(texture) => Ok(Arc::new(texture)),
+                    Err(err) => error(file.line, format!("{}: {}", path.display(), err)),
+                }
+            }
+            "noise" => {
+                object(node, &what, &["type", "style", "scale"])?;
+                let style = match node.get("style") {
+                    None => NoiseStyle::Marble,
+                    Some(value) => noise_style(value)?,
+                };
+                Ok(Arc::new(NoiseTexture::new(style, scale(4.0)?)))
+            }
+            other => error(
+                node.get("type").map_or(node.line, |value| value.line),
+                format!(
+                    "unknown texture type '{}', expected checker, image or noise",
+                    other
+                ),
+            ),
+        }
+    }
+
+    // An object's material is either the name of one from "materials" or an
+    // inline definition.
+    fn object_material(&self, node: &Json, what: &str) -> Result<Arc<dyn Material>> {
+        let value = required(node, "material", what)?;
+        match value.as_str() {
+            Some(name) => match self.materials.get(name) {
+                Some(material) => Ok(material.clone()),
+                None => error(value.line, format!("unknown material '{}'", name)),
+            },
+            None => self.material(value, &format!("material of {}", what)),
+        }
+    }
+
+    // Spheres and triangles that give off light also go into `lights`, so
+    // that they are sampled directly. Meshes never do.
+    fn add_object(
+        &self,
+        world: &mut HittableList,
+        lights: &mut HittableList,
+        node: &Json,
+    ) -> Result<()> {
+        let kind = string(required(node, "type", "object")?, "type")?;
+        match kind {
+            "sphere" => {
+                object(node, "sphere", &["type", "center", "radius", "material"])?;
+                let center = vector(required(node, "center", "sphere")?, "center")?;
+                let radius = positive(required(node, "radius", "sphere")?, "radius")?;
+                let material = self.object_material(node, "sphere")?;
+                if material.is_emissive() {
+                    lights.add(Box::new(Sphere::new(center, radius, material.clone())));
+                }
+                world.add(Box::new(Sphere::new(center, radius, material)));
+            }
+            "triangle" => {
+                object(
+                    node,
+                    "triangle",
+                    &["type", "vertices", "normals", "uvs", "material"],
+                )?;
+                let vertices = triple(required(node, "vertices", "triangle")?, "vertices", vector)?;
+                let material = self.object_material(node, "triangle")?;
+                if material.is_emissive() {
+                    lights.add(Box::new(Triangle::new(
+                        vertices[0],
+                        vertices[1],
+                        vertices[2],
+                        material.clone(),
+                    )));
+                }
+                let mut triangle = Triangle::new(vertices[0], vertices[1], vertices[2], material);
+                if let Some(value) = node.get("normals") {
+                    triangle = triangle.with_normals(triple(value, "normals", vector)?);
+                }
+                if let Some(value) = node.get("uvs") {
+                    triangle = triangle.with_uvs(triple(value, "uvs", uv)?);
+                }
+                world.add(Box::new(triangle));
+            }
+            "mesh" => {
+                object(node, "mesh", &["type", "file", "material"])?;
+                let file = required(node, "file", "mesh")?;
+                let path = self.base.join(string(file, "file")?);
+                let material = match node.get("material") {
+                    Some(_) => self.object_material(node, "mesh")?,
+                    None => default_mesh_material(),
+                };
+                let mesh: std::result::Result<Box<dyn Hittable>, MeshError> =
+                    if has_extension(&path, &["obj"]) {
+                        load_obj(&path, material).map(|mesh| Box::new(mesh) as _)
+                    } else if has_extension(&path, &["ply"]) {
+                        load_ply(&path, material).map(|mesh| Box::new(mesh) as _)
+                    } else if has_extension(&path, &["gltf", "glb"]) {
+                        load_gltf(&path, material).map(|gltf| Box::new(gltf.world) as _)
+                    } else {
+                        return error(
+                            file.line,
+                            "unsupported mesh format, expected .obj, .ply, .gltf or .glb",
+                        );
+                    };
+                match mesh {
+                    Ok(mesh) => world.add(mesh),
+                    Err(err) => return error(file.line, err.to_string()),
+                }
+            }
+            other => {
+                return error(
+                    node.get("type").map_or(node.line, |value| value.line),
+                    format!(
+                        "unknown object type '{}', expected one of: {}",
+                        other,
+                        OBJECT_TYPES.join(", ")
+                    ),
+                )
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_error(text: &str) -> String {
+        match parse(text, Path::new("scene.json")) {
+            Ok(_) => panic!("expected the scene to be rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_scene() {
+        let text = r#"{
+            "render": { "width": 320, "aspect": "4:3", "spp": 8, "max_depth": 4,
+                        "min_depth": 2, "seed": 7, "transfer": "linear", "output": "out.hdr" },
+            "camera": { "lookfrom": [0, 0, 5], "lookat": [0, 0, 0], "vfov": 40,
+                        "background": [0.1, 0.1, 0.1] },
+            "materials": {
+                "matte": { "type": "lambertian", "albedo": [0.5, 0.5, 0.5] },
+                "lamp": { "type": "diffuse_light", "emit": [4, 4, 4] }
+            },
+            "objects": [
+                { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "matte" },
+                { "type": "sphere", "center": [2, 0, 0], "radius": 0.5,
+                  "material": { "type": "metal", "albedo": [0.8, 0.8, 0.8], "fuzz": 0.2 } },
+                { "type": "triangle", "vertices": [[0, 0, 0], [1, 0, 0], [0, 1, 0]],
+                  "uvs": [[0, 0], [1, 0], [0, 1]], "material": "lamp" }
+            ]
+        }"#;
+        let scene = parse(text, Path::new("scene.json")).unwrap_or_else(|err| panic!("{}", err));
+
+        assert_eq!(scene.world.into_objects().len(), 3);
+        assert_eq!(
+            scene.settings,
+            RenderSettings {
+                width: Some(320),
+                aspect_ratio: Some(4.0 / 3.0),
+                samples_per_pixel: Some(8),
+                max_depth: Some(4),
+                min_depth: Some(2),
+                seed: Some(7),
+                transfer: Some(TransferFunction::Linear),
+                output: Some(PathBuf::from("out.hdr")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_example_scene() {
+        let scene =
+            load(Path::new("scenes/three_spheres.json")).unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(scene.world.into_objects().len(), 5);
+    }
+
+    #[test]
+    fn test_unknown_key_reports_line() {
+        let text = "{\n  \"objects\": [\n    { \"type\": \"sphere\", \"center\": [0, 0, 0],\n      \"radus\": 1, \"material\": \"x\" }\n  ]\n}";
+        assert_eq!(
+            load_error(text),
+            "scene.json:4: unknown key 'radus' in sphere, expected one of: type, center, radius, material"
+        );
+        assert_eq!(
+            load_error("{\n\"objects\": [],\n\"lights\": []}"),
+            "scene.json:3: unknown key 'lights' in scene, expected one of: render, camera, materials, objects"
+        );
+    }
+
+    #[test]
+    fn test_missing_field_reports_line() {
+        let text = "{\n  \"objects\": [\n    {\n      \"type\": \"sphere\",\n      \"center\": [0, 0, 0]\n    }\n  ]\n}";
+        assert_eq!(
+            load_error(text),
+            "scene.json:3: missing field 'radius' in sphere"
+        );
+        assert_eq!(
+            load_error("{}"),
+            "scene.json:1: missing field 'objects' in scene"
+        );
+    }
+
+    #[test]
+    fn test_invalid_values_report_line() {
+        let sphere = |fields: &str| {
+            format!(
+                "{{\n\"materials\": {{\"m\": {{\"type\": \"lambertian\", \"albedo\": [1, 1, 1]}}}},\n\"objects\": [\n{{\"type\": \"sphere\", {}}}]}}",
+                fields
+            )
+        };
+        assert_eq!(
+            load_error(&sphere(
+                r#""center": [0, 0, 0], "radius": -1, "material": "m""#
+            )),
+            "scene.json:4: invalid value for 'radius': must be greater than zero"
+        );
+        assert_eq!(
+            load_error(&sphere(r#""center": [0, 0], "radius": 1, "material": "m""#)),
+            "scene.json:4: expected an array of three numbers for 'center'"
+        );
+        assert_eq!(
+            load_error(&sphere(
+                r#""center": [0, 0, 0], "radius": "big", "material": "m""#
+            )),
+            "scene.json:4: expected a number for 'radius', found a string"
+        );
+        assert_eq!(
+            load_error(&sphere(
+                r#""center": [0, 0, 0], "radius": 1, "material": "glass""#
+            )),
+            "scene.json:4: unknown material 'glass'"
+        );
+        assert_eq!(
+            load_error("{\"render\": {\"spp\": 1.5}, \"objects\": []}"),
+            "scene.json:1: invalid value for 'spp': expected a whole number"
+        );
+        assert_eq!(
+            load_error("{\"render\": {\"output\": \"a.gif\"}, \"objects\": []}"),
+            "scene.json:1: unsupported image format for a.gif, expected .png, .pnm, .ppm, .hdr, .pfm or .exr"
+        );
+        assert_eq!(
+            load_error("{\"objects\": [{\"type\": \"triangle\", \"vertices\": [[0, 0, 0], [1, 0, 0]], \"material\": {\"type\": \"dielectric\", \"refraction_index\": 1.5}}]}"),
+            "scene.json:1: expected an array of three entries for 'vertices'"
+        );
+        assert_eq!(
+            load_error("{\"objects\": [{\"type\": \"cube\"}]}"),
+            "scene.json:1: unknown object type 'cube', expected one of: sphere, triangle, mesh"
+        );
+        assert_eq!(
+            load_error("{\n\"materials\": {\"m\": {\"type\": \"metal\", \"albedo\": [1, 1, 1], \"fuzz\": 2}}, \"objects\": []}"),
+            "scene.json:2: invalid value for 'fuzz': must be between 0 and 1"
+        );
+        assert_eq!(
+            load_error("{\"camera\": {\"background\": \"white\"}, \"objects\": []}"),
+            "scene.json:1: invalid value for 'background': expected sky, black, a color or an object of type environment or sky"
+        );
+        assert_eq!(
+            load_error("{\n\"camera\": {\"lookfrom\": [0, 5, 0], \"lookat\": [0, 0, 0]},\n\"objects\": []}"),
+            "scene.json:2: invalid camera: vup is zero or parallel to the view direction"
+        );
+    }
+
+    #[test]
+    fn test_textured_materials() {
+        let text = r#"{
+            "materials": {
+                "floor": { "type": "lambertian", "albedo": { "type": "checker", "scale": 0.5,
+                           "even": [0, 0, 0], "odd": { "type": "noise", "style": "smooth" } } },
+                "clouds": { "type": "diffuse_light",
+                            "emit": { "type": "noise", "style": "turbulence", "scale": 2 } }
+            },
+            "objects": [
+                { "type": "sphere", "center": [0, -100, 0], "radius": 99, "material": "floor" },
+                { "type": "sphere", "center": [0, 3, 0], "radius": 1, "material": "clouds" }
+            ]
+        }"#;
+        let scene = parse(text, Path::new("scene.json")).unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(scene.world.into_objects().len(), 2);
+        // textured emission still marks a light
+        assert_eq!(scene.lights.into_objects().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_textures_report_line() {
+        let lambertian = |albedo: &str| {
+            format!(
+                "{{\"materials\": {{\"m\": {{\"type\": \"lambertian\",\n\"albedo\": {}}}}}, \"objects\": []}}",
+                albedo
+            )
+        };
+        assert_eq!(
+            load_error(&lambertian("\"red\"")),
+            "scene.json:2: expected a color or a texture for 'albedo'"
+        );
+        assert_eq!(
+            load_error(&lambertian("{\"type\": \"wood\"}")),
+            "scene.json:2: unknown texture type 'wood', expected checker, image or noise"
+        );
+        assert_eq!(
+            load_error(&lambertian("{\"type\": \"checker\", \"even\": [1, 1, 1]}")),
+            "scene.json:2: missing field 'odd' in texture of 'albedo'"
+        );
+        assert_eq!(
+            load_error(&lambertian(
+                "{\"type\": \"checker\", \"scale\": 0, \"even\": [1, 1, 1], \"odd\": [0, 0, 0]}"
+            )),
+            "scene.json:2: invalid value for 'scale': must be greater than zero"
+        );
+        assert_eq!(
+            load_error(&lambertian("{\"type\": \"noise\", \"style\": \"wavy\"}")),
+            "scene.json:2: invalid value for 'style': expected smooth, turbulence or marble"
+        );
+        assert_eq!(
+            load_error(&lambertian("{\"type\": \"noise\", \"octaves\": 3}")),
+            "scene.json:2: unknown key 'octaves' in texture of 'albedo', expected one of: type, style, scale"
+        );
+    }
+
+    #[test]
+    fn test_sky_background() {
+        let text = r#"{
+            "camera": { "background": { "type": "sky", "sun_direction": [1, 1, 0],
+                                        "turbidity": 5 } },
+            "objects": []
+        }"#;
+        let scene = parse(text, Path::new("scene.json")).unwrap_or_else(|err| panic!("{}", err));
+        let camera = scene.camera.build().unwrap();
+        let Background::PhysicalSky(sky) = camera.background() else {
+            panic!("expected a physical sky, got {:?}", camera.background());
+        };
+        assert_eq!(sky.turbidity(), 5.0);
+        assert_eq!(sky.ground_albedo(), Color::new(0.3, 0.3, 0.3));
+        assert!((sky.sun_direction().y - 0.5f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_invalid_backgrounds_report_line() {
+        let background = |value: &str| {
+            format!(
+                "{{\"camera\": {{\n\"background\": {}}}, \"objects\": []}}",
+                value
+            )
+        };
+        assert_eq!(
+            load_error(&background("{\"type\": \"gradient\"}")),
+            "scene.json:2: unknown background type 'gradient', expected environment or sky"
+        );
+        assert_eq!(
+            load_error(&background("{\"type\": \"environment\"}")),
+            "scene.json:2: missing field 'file' in background"
+        );
+        assert_eq!(
+            load_error(&background(
+                "{\"type\": \"environment\", \"file\": \"sky.hdr\", \"intensity\": -1}"
+            )),
+            "scene.json:2: invalid value for 'intensity': must be greater than zero"
+        );
+        assert_eq!(
+            load_error(&background(
+                "{\"type\": \"sky\", \"sun_direction\": [0, 0, 0]}"
+            )),
+            "scene.json:2: invalid value for 'sun_direction': must not be zero"
+        );
+        assert_eq!(
+            load_error(&background(
+                "{\"type\": \"sky\", \"sun_direction\": [0, 1, 0], \"turbidity\": 20}"
+            )),
+            "scene.json:2: invalid value for 'turbidity': must be between 1.7 and 10"
+        );
+        assert!(load_error(&background(
+            "{\"type\": \"environment\", \"file\": \"missing.hdr\"}"
+        ))
+        .starts_with("scene.json:2: missing.hdr: "));
+    }
+
+    #[test]
+    fn test_syntax_error_reports_line() {
+        assert_eq!(
+            load_error("{\n  \"objects\": [\n    1,\n  ]\n}"),
+            "scene.json:4: column 3: unexpected character ']'"
+        );
+    }
+}
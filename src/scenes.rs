@@ -0,0 +1,341 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{
+    camera::{Background, Camera, CameraBuilder},
+    color::Color,
+    hittable_list::HittableList,
+    integrator::Integrator,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    point::Point3,
+    quad::{make_box, Quad},
+    random,
+    render::TransferFunction,
+    sphere::Sphere,
+    vector::Vector3,
+};
+
+// A world together with the camera framing it. Image settings such as the
+// resolution or sample count live in `settings`, where anything left unset
+// falls back to the command line or the defaults.
+pub struct Scene {
+    pub world: HittableList,
+    // copies of the emitters in `world` to aim shadow rays at
+    pub lights: HittableList,
+    pub camera: CameraBuilder,
+    pub settings: RenderSettings,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderSettings {
+    pub width: Option<i32>,
+    pub aspect_ratio: Option<f64>,
+    pub samples_per_pixel: Option<i32>,
+    pub max_depth: Option<i32>,
+    pub min_depth: Option<i32>,
+    pub seed: Option<u64>,
+    pub transfer: Option<TransferFunction>,
+    pub output: Option<PathBuf>,
+}
+
+impl RenderSettings {
+    pub const DEFAULT_WIDTH: i32 = 1920;
+    pub const DEFAULT_ASPECT_RATIO: f64 = 16.0 / 9.0;
+    pub const DEFAULT_SAMPLES_PER_PIXEL: i32 = 100;
+    pub const DEFAULT_MAX_DEPTH: i32 = 10;
+    pub const DEFAULT_OUTPUT: &'static str = "image.png";
+
+    // Settings from `self` win, the gaps are filled from `other`.
+    pub fn or(self, other: RenderSettings) -> RenderSettings {
+        RenderSettings {
+            width: self.width.or(other.width),
+            aspect_ratio: self.aspect_ratio.or(other.aspect_ratio),
+            samples_per_pixel: self.samples_per_pixel.or(other.samples_per_pixel),
+            max_depth: self.max_depth.or(other.max_depth),
+            min_depth: self.min_depth.or(other.min_depth),
+            seed: self.seed.or(other.seed),
+            transfer: self.transfer.or(other.transfer),
+            output: self.output.or(other.output),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width.unwrap_or(Self::DEFAULT_WIDTH)
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio.unwrap_or(Self::DEFAULT_ASPECT_RATIO)
+    }
+
+    pub fn output(&self) -> PathBuf {
+        self.output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(Self::DEFAULT_OUTPUT))
+    }
+
+    pub fn apply(&self, camera: CameraBuilder) -> CameraBuilder {
+        let camera = camera
+            .image_width(self.width())
+            .aspect_ratio(self.aspect_ratio())
+            .samples_per_pixel(
+                self.samples_per_pixel
+                    .unwrap_or(Self::DEFAULT_SAMPLES_PER_PIXEL),
+            )
+            .max_depth(self.max_depth.unwrap_or(Self::DEFAULT_MAX_DEPTH))
+            .min_depth(self.min_depth.unwrap_or(Integrator::DEFAULT_MIN_DEPTH))
+            .transfer_function(self.transfer.unwrap_or_default());
+        match self.seed {
+            Some(seed) => camera.seed(seed),
+            None => camera,
+        }
+    }
+}
+
+// Parses "16:9", "16/9" or a plain number such as "1.5".
+pub fn parse_aspect_ratio(value: &str) -> Result<f64, String> {
+    let ratio = match value.split_once([':', '/']) {
+        Some((width, height)) => {
+            match (width.trim().parse::<f64>(), height.trim().parse::<f64>()) {
+                (Ok(width), Ok(height)) => width / height,
+                _ => return Err(String::from("expected W:H or a number")),
+            }
+        }
+        None => value
+            .parse::<f64>()
+            .map_err(|_| String::from("expected W:H or a number"))?,
+    };
+    if !ratio.is_finite() || ratio <= 0.0 {
+        return Err(String::from("must be a positive ratio"));
+    }
+    Ok(ratio)
+}
+
+pub const BUILTIN_SCENES: [&str; 3] = ["default", "spheres", "cornell"];
+
+pub fn builtin(name: &str) -> Option<Scene> {
+    match name {
+        "default" => Some(default_scene()),
+        "spheres" => Some(spheres_scene()),
+        "cornell" => Some(cornell_box()),
+        _ => None,
+    }
+}
+
+fn default_scene() -> Scene {
+    let mut world = HittableList::new();
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_center = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+    let material_left = Arc::new(Dielectric::new(1.50));
+    let material_bubble = Arc::new(Dielectric::new(1.00 / 1.50));
+    let material_right = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 1.0));
+
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, 0.0, -1.2),
+        0.5,
+        material_center,
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(-1.0, 0.0, -1.0),
+        0.5,
+        material_left,
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(-1.0, 0.0, -1.0),
+        0.4,
+        material_bubble,
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(1.0, 0.0, -1.0),
+        0.5,
+        material_right,
+    )));
+
+    let camera = Camera::builder()
+        .vfov(20.0)
+        .lookfrom(Point3::new(-2.0, 2.0, 1.0))
+        .lookat(Point3::new(0.0, 0.0, -1.0))
+        .vup(Vector3::new(0.0, 1.0, 0.0))
+        .defocus_angle(10.0)
+        .focus_dist(3.4);
+
+    Scene {
+        world,
+        lights: HittableList::new(),
+        camera,
+        settings: RenderSettings::default(),
+    }
+}
+
+// The cover of "Ray Tracing in One Weekend": a field of small random spheres
+// around three large ones. The layout is generated from a fixed seed.
+fn spheres_scene() -> Scene {
+    let mut rng = random::sampler(0, 0, 0);
+    let mut world = HittableList::new();
+
+    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random::random_double(&mut rng);
+            let center = Point3::new(
+                a as f64 + 0.9 * random::random_double(&mut rng),
+                0.2,
+                b as f64 + 0.9 * random::random_double(&mut rng),
+            );
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() <= 0.9 {
+                continue;
+            }
+
+            let sphere_material: Arc<dyn Material> = if choose_mat < 0.8 {
+                let albedo = Vector3::random(&mut rng) * Vector3::random(&mut rng);
+                Arc::new(Lambertian::new(Color::new(albedo.x, albedo.y, albedo.z)))
+            } else if choose_mat < 0.95 {
+                let albedo = Vector3::random_range(&mut rng, 0.5, 1.0);
+                let fuzz = random::random_range(&mut rng, 0.0, 0.5);
+                Arc::new(Metal::new(Color::new(albedo.x, albedo.y, albedo.z), fuzz))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+            world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
+        }
+    }
+
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1))),
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)),
+    )));
+
+    let camera = Camera::builder()
+        .vfov(20.0)
+        .lookfrom(Point3::new(13.0, 2.0, 3.0))
+        .lookat(Point3::new(0.0, 0.0, 0.0))
+        .vup(Vector3::new(0.0, 1.0, 0.0))
+        .defocus_angle(0.6)
+        .focus_dist(10.0);
+
+    Scene {
+        world,
+        lights: HittableList::new(),
+        camera,
+        settings: RenderSettings::default(),
+    }
+}
+
+// The Cornell box from "Ray Tracing: The Next Week", lit only by the panel
+// in its ceiling.
+fn cornell_box() -> Scene {
+    let mut world = HittableList::new();
+
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0)));
+
+    let (x, y, z) = (
+        Vector3::new(555.0, 0.0, 0.0),
+        Vector3::new(0.0, 555.0, 0.0),
+        Vector3::new(0.0, 0.0, 555.0),
+    );
+    world.add(Box::new(Quad::new(
+        Point3::new(555.0, 0.0, 0.0),
+        y,
+        z,
+        green,
+    )));
+    world.add(Box::new(Quad::new(Point3::new(0.0, 0.0, 0.0), y, z, red)));
+    let panel = || {
+        Quad::new(
+            Point3::new(343.0, 554.0, 332.0),
+            Vector3::new(-130.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, -105.0),
+            light.clone(),
+        )
+    };
+    world.add(Box::new(panel()));
+    world.add(Box::new(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        x,
+        z,
+        white.clone(),
+    )));
+    world.add(Box::new(Quad::new(
+        Point3::new(555.0, 555.0, 555.0),
+        -x,
+        -z,
+        white.clone(),
+    )));
+    world.add(Box::new(Quad::new(
+        Point3::new(0.0, 0.0, 555.0),
+        x,
+        y,
+        white.clone(),
+    )));
+
+    world.add(Box::new(make_box(
+        Point3::new(130.0, 0.0, 65.0),
+        Point3::new(295.0, 165.0, 230.0),
+        white.clone(),
+    )));
+    world.add(Box::new(make_box(
+        Point3::new(265.0, 0.0, 295.0),
+        Point3::new(430.0, 330.0, 460.0),
+        white,
+    )));
+
+    let mut lights = HittableList::new();
+    lights.add(Box::new(panel()));
+
+    let camera = Camera::builder()
+        .vfov(40.0)
+        .lookfrom(Point3::new(278.0, 278.0, -800.0))
+        .lookat(Point3::new(278.0, 278.0, 0.0))
+        .vup(Vector3::new(0.0, 1.0, 0.0))
+        .background(Background::BLACK);
+
+    Scene {
+        world,
+        lights,
+        camera,
+        settings: RenderSettings {
+            width: Some(600),
+            aspect_ratio: Some(1.0),
+            samples_per_pixel: Some(200),
+            max_depth: Some(50),
+            ..RenderSettings::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_scenes_exist() {
+        for name in BUILTIN_SCENES {
+            assert!(builtin(name).is_some(), "missing scene {}", name);
+        }
+        assert!(builtin("nope").is_none());
+    }
+}
@@ -0,0 +1,312 @@
+use std::f64::consts::PI;
+
+use crate::{
+    color::Color,
+    random::{self, Sampler},
+    vector::Vector3,
+};
+
+// Angular radius of the sun seen from the earth, in degrees.
+const SUN_ANGULAR_RADIUS: f64 = 0.2666;
+
+// The model gives luminance in kcd/m^2, about 8 for the zenith of a clear
+// midday sky. This brings that down to around 1, where the other lights of
+// the renderer live.
+const SKY_SCALE: f64 = 1.0 / 8.0;
+
+// Luminance of the sun above the atmosphere, in kcd/m^2.
+const SUN_LUMINANCE: f64 = 1.6e6;
+
+// The clear daylight sky of Preetham, Shirley and Smits, "A Practical
+// Analytic Model for Daylight" (1999), with the sun as a small bright disk
+// in it. Skylight comes from the Perez luminance distribution fitted to the
+// turbidity, the haziness of the air, from about 2 for a very clear day to
+// 10 for a hazy one. Below the horizon is flat ground of `ground_albedo`
+// lit by the sky and sun. +y is up.
+#[derive(Debug, Clone)]
+pub struct PhysicalSky {
+    sun_direction: Vector3,
+    turbidity: f64,
+    ground_albedo: Color,
+    // Perez coefficients A to E and zenith values of Y, x and y
+    perez: [[f64; 5]; 3],
+    zenith: [f64; 3],
+    sun_radiance: Color,
+    ground: Color,
+    cos_sun_radius: f64,
+}
+
+impl PhysicalSky {
+    // `sun_direction` points towards the sun; a sun below the horizon lights
+    // the sky as if it were on it, but is itself not drawn.
+    pub fn new(sun_direction: Vector3, turbidity: f64, ground_albedo: Color) -> Self {
+        let sun_direction = Vector3::unit_vector(&sun_direction);
+        let t = turbidity;
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        let theta_sun = sun_direction.y.clamp(0.0, 1.0).acos();
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[f64; 4]; 3]| {
+            let thetas = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let ts = [t * t, t, 1.0];
+            (0..3)
+                .map(|i| ts[i] * (0..4).map(|j| m[i][j] * thetas[j]).sum::<f64>())
+                .sum::<f64>()
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let mut sky = PhysicalSky {
+            sun_direction,
+            turbidity,
+            ground_albedo,
+            perez,
+            zenith: [zenith_luminance.max(0.0), zenith_x, zenith_y],
+            sun_radiance: Color::new(0.0, 0.0, 0.0),
+            ground: Color::new(0.0, 0.0, 0.0),
+            cos_sun_radius: SUN_ANGULAR_RADIUS.to_radians().cos(),
+        };
+        sky.sun_radiance = sun_transmittance(theta_sun, t) * (SUN_LUMINANCE * SKY_SCALE);
+        sky.ground = sky.ground_radiance();
+        sky
+    }
+
+    pub fn sun_direction(&self) -> Vector3 {
+        self.sun_direction
+    }
+
+    pub fn turbidity(&self) -> f64 {
+        self.turbidity
+    }
+
+    pub fn ground_albedo(&self) -> Color {
+        self.ground_albedo
+    }
+
+    // Radiance seen looking towards `direction`, the sun included.
+    pub fn color(&self, direction: &Vector3) -> Color {
+        let direction = Vector3::unit_vector(direction);
+        if direction.y < 0.0 {
+            return self.ground;
+        }
+        let sky = self.sky_radiance(&direction);
+        if self.sun_visible()
+            && Vector3::dot(&direction, &self.sun_direction) >= self.cos_sun_radius
+        {
+            sky + self.sun_radiance
+        } else {
+            sky
+        }
+    }
+
+    // Density per unit solid angle with which `random` picks `direction`:
+    // uniform over the sun disk.
+    pub fn pdf_value(&self, direction: &Vector3) -> f64 {
+        let cosine = Vector3::dot(&Vector3::unit_vector(direction), &self.sun_direction);
+        if !self.sun_visible() || cosine < self.cos_sun_radius {
+            return 0.0;
+        }
+        1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius))
+    }
+
+    // A direction towards the sun disk, or None when the sun has set.
+    pub fn random(&self, rng: &mut Sampler) -> Option<Vector3> {
+        if !self.sun_visible() {
+            return None;
+        }
+        let z = 1.0 + random::random_double(rng) * (self.cos_sun_radius - 1.0);
+        let phi = 2.0 * PI * random::random_double(rng);
+        let sin_theta = (1.0 - z * z).sqrt();
+        let (u, v) = Vector3::orthonormal_basis(&self.sun_direction);
+        Some(u * (phi.cos() * sin_theta) + v * (phi.sin() * sin_theta) + self.sun_direction * z)
+    }
+
+    fn sun_visible(&self) -> bool {
+        self.sun_direction.y > 0.0
+    }
+
+    // The Perez distribution scaled to the zenith values, for a direction
+    // at or above the horizon.
+    fn sky_radiance(&self, direction: &Vector3) -> Color {
+        let cos_theta = direction.y.max(1e-4);
+        let theta_sun = self.sun_direction.y.clamp(0.0, 1.0).acos();
+        let gamma = Vector3::dot(direction, &self.sun_direction)
+            .clamp(-1.0, 1.0)
+            .acos();
+        let perez = |[a, b, c, d, e]: [f64; 5], cos_theta: f64, gamma: f64| {
+            (1.0 + a * (b / cos_theta).exp())
+                * (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+        };
+        let [luminance, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * perez(self.perez[i], cos_theta, gamma)
+                / perez(self.perez[i], 1.0, theta_sun)
+        });
+        xyy_to_rgb(x, y, luminance * SKY_SCALE)
+    }
+
+    // Lambertian ground under the sky and sun: albedo / pi times the
+    // irradiance, with the sky integrated numerically over the hemisphere.
+    fn ground_radiance(&self) -> Color {
+        let (steps_theta, steps_phi) = (32, 64);
+        let (d_theta, d_phi) = (PI / 2.0 / steps_theta as f64, 2.0 * PI / steps_phi as f64);
+        let mut irradiance = Color::new(0.0, 0.0, 0.0);
+        for i in 0..steps_theta {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..steps_phi {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let direction = Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                let weight = theta.cos() * theta.sin() * d_theta * d_phi;
+                irradiance = irradiance + self.sky_radiance(&direction) * weight;
+            }
+        }
+        if self.sun_visible() {
+            let solid_angle = 2.0 * PI * (1.0 - self.cos_sun_radius);
+            irradiance = irradiance + self.sun_radiance * (solid_angle * self.sun_direction.y);
+        }
+        self.ground_albedo * irradiance * (1.0 / PI)
+    }
+}
+
+// CIE xyY to linear sRGB, dropping colours outside its gamut.
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+// The share of sunlight at red, green and blue wavelengths that makes it
+// through the air to the ground, from Rayleigh scattering by the air and Mie
+// scattering by haze, following the appendix of Preetham et al.
+fn sun_transmittance(theta_sun: f64, turbidity: f64) -> Color {
+    // relative optical path length through the atmosphere (Kasten)
+    let degrees = theta_sun.to_degrees().min(93.0);
+    let air_mass = 1.0 / (theta_sun.cos() + 0.15 * (93.885 - degrees).powf(-1.253));
+    // Angstrom's turbidity coefficient, with wavelength exponent 1.3
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = |micrometres: f64| {
+        let rayleigh = 0.008735 * micrometres.powf(-4.08);
+        let haze = beta * micrometres.powf(-1.3);
+        (-air_mass * (rayleigh + haze)).exp()
+    };
+    Color::new(
+        transmittance(0.68),
+        transmittance(0.55),
+        transmittance(0.44),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn midday() -> PhysicalSky {
+        PhysicalSky::new(Vector3::new(0.3, 1.0, 0.2), 3.0, Color::new(0.3, 0.3, 0.3))
+    }
+
+    #[test]
+    fn test_clear_sky_is_blue_and_brightest_near_the_sun() {
+        let sky = midday();
+        let zenith = sky.color(&Vector3::new(0.0, 1.0, 0.0));
+        assert!(zenith.blue > zenith.red, "{:?}", zenith);
+        assert!((0.2..5.0).contains(&zenith.blue), "{:?}", zenith);
+
+        // just outside the sun disk the sky outshines the far side
+        let near_sun = Vector3::unit_vector(&(sky.sun_direction() + Vector3::new(0.05, 0.0, 0.0)));
+        let away = Vector3::new(-0.7, 0.4, -0.5);
+        assert!(sky.color(&near_sun).green > sky.color(&away).green);
+    }
+
+    #[test]
+    fn test_sun_disk() {
+        let sky = midday();
+        let sun = sky.color(&sky.sun_direction());
+        let beside = Vector3::unit_vector(&(sky.sun_direction() + Vector3::new(0.02, 0.0, 0.0)));
+        assert!(sun.green > 1000.0 * sky.color(&beside).green);
+        // the air takes more blue than red out of sunlight
+        let setting =
+            PhysicalSky::new(Vector3::new(1.0, 0.05, 0.0), 3.0, Color::new(0.3, 0.3, 0.3));
+        let low_sun = setting.color(&setting.sun_direction());
+        assert!(low_sun.red > 2.0 * low_sun.blue, "{:?}", low_sun);
+    }
+
+    #[test]
+    fn test_sun_sampling() {
+        let sky = midday();
+        let mut rng = random::sampler(4, 0, 0);
+        let pdf = 1.0 / (2.0 * PI * (1.0 - sky.cos_sun_radius));
+        for _ in 0..100 {
+            let direction = sky.random(&mut rng).unwrap();
+            assert!((direction.length() - 1.0).abs() < 1e-9);
+            assert!(Vector3::dot(&direction, &sky.sun_direction()) >= sky.cos_sun_radius - 1e-12);
+            assert!((sky.pdf_value(&direction) - pdf).abs() < 1e-6 * pdf);
+        }
+        assert_eq!(sky.pdf_value(&Vector3::new(0.0, -1.0, 0.0)), 0.0);
+
+        let night = PhysicalSky::new(Vector3::new(0.0, -1.0, 0.3), 3.0, Color::new(0.3, 0.3, 0.3));
+        assert!(night.random(&mut rng).is_none());
+        assert_eq!(night.pdf_value(&night.sun_direction()), 0.0);
+    }
+
+    #[test]
+    fn test_ground_reflects_the_sky() {
+        let sky = midday();
+        let down = Vector3::new(0.2, -1.0, 0.0);
+        let ground = sky.color(&down);
+        assert!(ground.green > 0.0);
+        // the ground looks the same from every angle
+        assert_eq!(sky.color(&Vector3::new(-0.5, -0.1, 0.7)), ground);
+        let brighter = PhysicalSky::new(sky.sun_direction(), 3.0, Color::new(0.6, 0.6, 0.6));
+        let ratio = brighter.color(&down).green / ground.green;
+        assert!((ratio - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_haze_whitens_the_sky() {
+        let direction = Vector3::new(0.0, 0.6, 0.8);
+        let sun = Vector3::new(0.3, 1.0, 0.2);
+        let clear = PhysicalSky::new(sun, 2.0, Color::new(0.3, 0.3, 0.3)).color(&direction);
+        let hazy = PhysicalSky::new(sun, 8.0, Color::new(0.3, 0.3, 0.3)).color(&direction);
+        assert!(clear.blue / clear.red > hazy.blue / hazy.red);
+    }
+}
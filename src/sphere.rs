@@ -1,7 +1,13 @@
+use std::sync::Arc;
+
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     interval::Interval,
+    material::Material,
     point::Point3,
+    progress,
+    random::{self, Sampler},
     ray::Ray,
     vector::Vector3,
 };
@@ -9,19 +15,23 @@ use crate::{
 pub struct Sphere {
     pub center: Point3,
     pub radius: f64,
+    pub mat: Arc<dyn Material>,
+    bbox: Aabb,
 }
 
 impl Sphere {
-    pub fn new(point3: Point3, radius: f64) -> Self {
+    pub fn new(point3: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let rvec = Vector3::new(radius, radius, radius);
         Sphere {
             center: point3,
             radius,
+            mat,
+            bbox: Aabb::from_points(point3 - rvec, point3 + rvec),
         }
     }
-}
 
-impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+    // The nearest `t` within `int` at which `r` meets the sphere.
+    fn intersect(&self, r: &Ray, int: Interval) -> Option<f64> {
         let oc = self.center - *r.origin();
         let a = r.direction().length_squared();
         let h = Vector3::dot(&r.direction, &oc);
@@ -29,40 +39,117 @@ impl Hittable for Sphere {
 
         let discriminant = h * h - a * c;
         if discriminant < 0.0 {
-            return false;
+            return None;
         }
 
         let sqrtd = discriminant.sqrt();
 
         let mut root = (h - sqrtd) / a;
-        if root <= int.min || int.max <= root {
+        if !int.surrounds(root) {
             root = (h + sqrtd) / a;
-            if root <= int.min || int.max <= root {
-                return false;
+            if !int.surrounds(root) {
+                return None;
             }
         }
+        Some(root)
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+        progress::count_intersection_test();
+        let Some(root) = self.intersect(r, int) else {
+            return false;
+        };
 
         rec.t = root;
         rec.p = r.at(rec.t);
         let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, &outward_normal);
+        (rec.u, rec.v) = sphere_uv(&outward_normal);
+        rec.mat = Some(Arc::clone(&self.mat));
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
 
+    fn is_samplable(&self) -> bool {
         true
     }
+
+    // Sampled uniformly over the cone of directions the sphere subtends.
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3) -> f64 {
+        let r = Ray::new(*origin, *direction);
+        if self
+            .intersect(&r, Interval::new(0.001, f64::INFINITY))
+            .is_none()
+        {
+            return 0.0;
+        }
+        let distance_squared = (self.center - *origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            // from inside the sphere covers every direction
+            return 1.0 / (4.0 * std::f64::consts::PI);
+        }
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut Sampler) -> Vector3 {
+        let direction = self.center - *origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return Vector3::random_unit_vector(rng);
+        }
+
+        let r1 = random::random_double(rng);
+        let r2 = random::random_double(rng);
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        let z = 1.0 + r2 * (cos_theta_max - 1.0);
+        let phi = 2.0 * std::f64::consts::PI * r1;
+        let sin_theta = (1.0 - z * z).sqrt();
+
+        let w = Vector3::unit_vector(&direction);
+        let (u, v) = Vector3::orthonormal_basis(&w);
+        u * (phi.cos() * sin_theta) + v * (phi.sin() * sin_theta) + w * z
+    }
+}
+
+// Texture coordinates of a point on the unit sphere: u turns once around
+// the y axis starting from -x, v runs from the bottom pole to the top one.
+pub fn sphere_uv(p: &Point3) -> (f64, f64) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + std::f64::consts::PI;
+    (
+        phi / (2.0 * std::f64::consts::PI),
+        theta / std::f64::consts::PI,
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
     use crate::ray::Ray;
     use crate::vector::Vector3;
-    use std::f64::EPSILON;
+
+    fn grey_sphere(center: Point3, radius: f64) -> Sphere {
+        Sphere::new(
+            center,
+            radius,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        )
+    }
 
     #[test]
     fn test_sphere_new() {
         let center = Point3::new(1.0, 2.0, 3.0);
         let radius = 2.0;
-        let sphere = Sphere::new(center, radius);
+        let sphere = grey_sphere(center, radius);
 
         assert_eq!(sphere.center, center);
         assert_eq!(sphere.radius, radius);
@@ -70,7 +157,7 @@ mod tests {
 
     #[test]
     fn test_sphere_hit_miss() {
-        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0);
+        let sphere = grey_sphere(Point3::new(0.0, 0.0, 0.0), 1.0);
         let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(1.0, 1.0, 0.0));
         let mut rec = HitRecord::new();
 
@@ -79,25 +166,26 @@ mod tests {
 
     #[test]
     fn test_sphere_hit_from_outside() {
-        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0);
+        let sphere = grey_sphere(Point3::new(0.0, 0.0, 0.0), 1.0);
         let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         let mut rec = HitRecord::new();
 
         assert!(sphere.hit(&ray, Interval::new(0.0, f64::INFINITY), &mut rec));
-        assert!((rec.t - 4.0).abs() < EPSILON);
+        assert!((rec.t - 4.0).abs() < f64::EPSILON);
         assert_eq!(rec.p, Point3::new(0.0, 0.0, -1.0));
         assert_eq!(rec.normal, Vector3::new(0.0, 0.0, -1.0));
         assert!(rec.front_face);
+        assert!(rec.mat.is_some());
     }
 
     #[test]
     fn test_sphere_hit_from_inside() {
-        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0);
+        let sphere = grey_sphere(Point3::new(0.0, 0.0, 0.0), 1.0);
         let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
         let mut rec = HitRecord::new();
 
         assert!(sphere.hit(&ray, Interval::new(0.0, f64::INFINITY), &mut rec));
-        assert!((rec.t - 1.0).abs() < EPSILON);
+        assert!((rec.t - 1.0).abs() < f64::EPSILON);
         assert_eq!(rec.p, Point3::new(0.0, 0.0, 1.0));
         assert_eq!(rec.normal, Vector3::new(0.0, 0.0, -1.0));
         assert!(!rec.front_face);
@@ -105,7 +193,7 @@ mod tests {
 
     #[test]
     fn test_sphere_hit_beyond_tmax() {
-        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0);
+        let sphere = grey_sphere(Point3::new(0.0, 0.0, 0.0), 1.0);
         let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         let mut rec = HitRecord::new();
 
@@ -114,10 +202,79 @@ mod tests {
 
     #[test]
     fn test_sphere_hit_before_tmin() {
-        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0);
+        let sphere = grey_sphere(Point3::new(0.0, 0.0, 0.0), 1.0);
         let ray = Ray::new(Point3::new(0.0, 0.0, -0.5), Vector3::new(0.0, 0.0, 1.0));
         let mut rec = HitRecord::new();
 
         assert!(!sphere.hit(&ray, Interval::new(2.0, f64::INFINITY), &mut rec));
     }
+
+    #[test]
+    fn test_sphere_samples_hit_it_with_the_cone_density() {
+        let sphere = grey_sphere(Point3::new(0.0, 0.0, -4.0), 1.0);
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let mut rng = crate::random::sampler(5, 0, 0);
+        let expected = 1.0 / (2.0 * std::f64::consts::PI * (1.0 - (15.0f64 / 16.0).sqrt()));
+
+        for _ in 0..100 {
+            let direction = sphere.random(&origin, &mut rng);
+            assert!((sphere.pdf_value(&origin, &direction) - expected).abs() < 1e-9);
+        }
+        assert_eq!(sphere.pdf_value(&origin, &Vector3::new(0.0, 1.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn test_sphere_counts_only_traced_intersection_tests() {
+        let sphere = grey_sphere(Point3::new(0.0, 0.0, -4.0), 1.0);
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let direction = Vector3::new(0.0, 0.0, -1.0);
+        let before = progress::counters();
+
+        sphere.pdf_value(&origin, &direction);
+        assert_eq!(progress::counters(), before);
+        let mut rec = HitRecord::new();
+        let r = Ray::new(origin, direction);
+        assert!(sphere.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert_eq!(
+            progress::counters().intersection_tests,
+            before.intersection_tests + 1
+        );
+    }
+
+    #[test]
+    fn test_sphere_bounding_box() {
+        let sphere = grey_sphere(Point3::new(1.0, 2.0, 3.0), 0.5);
+        let bbox = sphere.bounding_box();
+
+        assert_eq!((bbox.x.min, bbox.x.max), (0.5, 1.5));
+        assert_eq!((bbox.y.min, bbox.y.max), (1.5, 2.5));
+        assert_eq!((bbox.z.min, bbox.z.max), (2.5, 3.5));
+    }
+
+    #[test]
+    fn test_sphere_uv() {
+        for (p, expected) in [
+            (Point3::new(1.0, 0.0, 0.0), (0.5, 0.5)),
+            (Point3::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Point3::new(0.0, 0.0, 1.0), (0.25, 0.5)),
+            (Point3::new(-1.0, 0.0, 0.0), (0.0, 0.5)),
+            (Point3::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (Point3::new(0.0, 0.0, -1.0), (0.75, 0.5)),
+        ] {
+            let (u, v) = sphere_uv(&p);
+            assert!(
+                (u - expected.0).abs() < 1e-12 && (v - expected.1).abs() < 1e-12,
+                "{:?} maps to {:?}",
+                p,
+                (u, v)
+            );
+        }
+
+        // hits carry the coordinates of the point relative to the centre
+        let sphere = grey_sphere(Point3::new(3.0, 0.0, 0.0), 2.0);
+        let ray = Ray::new(Point3::new(3.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::new();
+        assert!(sphere.hit(&ray, Interval::new(0.0, f64::INFINITY), &mut rec));
+        assert!((rec.u - 0.25).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+    }
 }
@@ -0,0 +1,250 @@
+use std::{io, path::Path, sync::Arc};
+
+use crate::{
+    color::Color,
+    perlin::Perlin,
+    point::Point3,
+    render::{read_image, Framebuffer},
+};
+
+// A colour that varies over a surface, looked up by the texture coordinates
+// and position of a hit.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+pub struct SolidColor {
+    pub albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        SolidColor { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+// Cubes of side `scale` alternating between two textures through space, so
+// any surface cutting through them shows a checkerboard.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        CheckerTexture {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Color, odd: Color) -> Self {
+        CheckerTexture::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let cell = (self.inv_scale * p.x).floor() as i64
+            + (self.inv_scale * p.y).floor() as i64
+            + (self.inv_scale * p.z).floor() as i64;
+        if cell % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+// An image stretched once over the (u, v) unit square with (0, 0) at its
+// bottom left, repeating outside of it. Lookups blend the four nearest
+// pixels.
+pub struct ImageTexture {
+    pub image: Framebuffer,
+}
+
+impl ImageTexture {
+    pub fn new(image: Framebuffer) -> Self {
+        ImageTexture { image }
+    }
+
+    // Reads any image `render::read_image` understands.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let image = read_image(path)?;
+        if image.width == 0 || image.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is empty", path.display()),
+            ));
+        }
+        Ok(ImageTexture::new(image))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        let (width, height) = (self.image.width, self.image.height);
+        if width == 0 || height == 0 {
+            // a cyan debugging aid, as in the book
+            return Color::new(0.0, 1.0, 1.0);
+        }
+        // continuous pixel coordinates, with pixel centres at half integers
+        let x = (u - u.floor()) * width as f64 - 0.5;
+        let y = (1.0 - (v - v.floor())) * height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let column = |i: f64| (i as i64).rem_euclid(width as i64) as usize;
+        let row = |j: f64| (j as i64).rem_euclid(height as i64) as usize;
+        let (left, right) = (column(x0), column(x0 + 1.0));
+        let (top, bottom) = (row(y0), row(y0 + 1.0));
+
+        let image = &self.image;
+        image.get(left, top) * ((1.0 - fx) * (1.0 - fy))
+            + image.get(right, top) * (fx * (1.0 - fy))
+            + image.get(left, bottom) * ((1.0 - fx) * fy)
+            + image.get(right, bottom) * (fx * fy)
+    }
+}
+
+// How a `NoiseTexture` turns Perlin noise into a shade of grey.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseStyle {
+    // the noise itself, mapped to [0, 1]
+    Smooth,
+    // several octaves of noise, giving a cloudy look
+    Turbulence,
+    // stripes along z whose phase is disturbed by turbulence
+    Marble,
+}
+
+pub struct NoiseTexture {
+    noise: Perlin,
+    pub style: NoiseStyle,
+    // frequency of the noise, larger values give finer detail
+    pub scale: f64,
+}
+
+impl NoiseTexture {
+    // The noise is always built from the same seed, so renders stay
+    // reproducible.
+    pub fn new(style: NoiseStyle, scale: f64) -> Self {
+        NoiseTexture {
+            noise: Perlin::new(0),
+            style,
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let grey = match self.style {
+            NoiseStyle::Smooth => 0.5 * (1.0 + self.noise.noise(&(*p * self.scale))),
+            NoiseStyle::Turbulence => self.noise.turbulence(&(*p * self.scale), 7),
+            NoiseStyle::Marble => {
+                0.5 * (1.0 + (self.scale * p.z + 10.0 * self.noise.turbulence(p, 7)).sin())
+            }
+        };
+        Color::new(grey, grey, grey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_near(a: Color, b: Color) {
+        assert!(
+            (a.red - b.red).abs() < 1e-9
+                && (a.green - b.green).abs() < 1e-9
+                && (a.blue - b.blue).abs() < 1e-9,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_solid_color() {
+        let texture = SolidColor::new(Color::new(0.1, 0.2, 0.3));
+        assert_eq!(
+            texture.value(0.7, 0.2, &Point3::new(5.0, 1.0, 0.0)),
+            Color::new(0.1, 0.2, 0.3)
+        );
+    }
+
+    #[test]
+    fn test_checker_alternates_through_space() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let checker = CheckerTexture::from_colors(0.5, white, black);
+        let at = |x, y, z| checker.value(0.0, 0.0, &Point3::new(x, y, z));
+
+        assert_eq!(at(0.1, 0.1, 0.1), white);
+        assert_eq!(at(0.6, 0.1, 0.1), black);
+        assert_eq!(at(0.6, 0.6, 0.1), white);
+        assert_eq!(at(0.6, 0.6, 0.6), black);
+        // negative coordinates continue the pattern across zero
+        assert_eq!(at(-0.1, 0.1, 0.1), black);
+        assert_eq!(at(-0.1, -0.1, 0.1), white);
+    }
+
+    fn two_by_two() -> ImageTexture {
+        let mut image = Framebuffer::new(2, 2);
+        image.set(0, 0, Color::new(1.0, 0.0, 0.0));
+        image.set(1, 0, Color::new(0.0, 1.0, 0.0));
+        image.set(0, 1, Color::new(0.0, 0.0, 1.0));
+        image.set(1, 1, Color::new(1.0, 1.0, 1.0));
+        ImageTexture::new(image)
+    }
+
+    #[test]
+    fn test_image_texture_pixel_centres() {
+        let texture = two_by_two();
+        let p = Point3::new(0.0, 0.0, 0.0);
+        // v runs upwards while image rows run downwards
+        assert_color_near(texture.value(0.25, 0.75, &p), Color::new(1.0, 0.0, 0.0));
+        assert_color_near(texture.value(0.75, 0.75, &p), Color::new(0.0, 1.0, 0.0));
+        assert_color_near(texture.value(0.25, 0.25, &p), Color::new(0.0, 0.0, 1.0));
+        assert_color_near(texture.value(1.75, -0.75, &p), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_image_texture_is_bilinear() {
+        let texture = two_by_two();
+        let p = Point3::new(0.0, 0.0, 0.0);
+        // halfway between the top two pixel centres
+        assert_color_near(texture.value(0.5, 0.75, &p), Color::new(0.5, 0.5, 0.0));
+        // the middle of the image blends all four
+        assert_color_near(texture.value(0.5, 0.5, &p), Color::new(0.5, 0.5, 0.5));
+        // at the left edge the image wraps around to its right column
+        assert_color_near(texture.value(0.0, 0.75, &p), Color::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn test_noise_textures_stay_in_range() {
+        let p = Point3::new(1.3, -0.4, 2.9);
+        for style in [
+            NoiseStyle::Smooth,
+            NoiseStyle::Turbulence,
+            NoiseStyle::Marble,
+        ] {
+            let texture = NoiseTexture::new(style, 4.0);
+            let color = texture.value(0.0, 0.0, &p);
+            assert_eq!(color.red, color.blue);
+            assert!((0.0..=1.5).contains(&color.red), "{:?}: {:?}", style, color);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    matrix::Matrix4,
+    point::Point3,
+    random::Sampler,
+    ray::Ray,
+    vector::Vector3,
+};
+
+// An object placed in the world by an affine transform. Rays are moved into
+// the object's own space rather than the object into the world, so wrapping
+// an `Arc` of a mesh places another instance of it without copying any
+// geometry.
+pub struct Transformed<H> {
+    object: H,
+    // object to world space, and back
+    transform: Matrix4,
+    inverse: Matrix4,
+    bbox: Aabb,
+}
+
+impl<H: Hittable> Transformed<H> {
+    // Panics if `transform` cannot be inverted, as then the object would be
+    // flattened away.
+    pub fn new(object: H, transform: Matrix4) -> Self {
+        let inverse = transform
+            .inverse()
+            .expect("an object's transform must be invertible");
+        let bbox = transformed_bbox(&object.bounding_box(), &transform);
+        Transformed {
+            object,
+            transform,
+            inverse,
+            bbox,
+        }
+    }
+
+    pub fn object(&self) -> &H {
+        &self.object
+    }
+
+    pub fn transform(&self) -> &Matrix4 {
+        &self.transform
+    }
+}
+
+impl<H: Hittable> Hittable for Transformed<H> {
+    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+        // the direction keeps its scale, so `t` means the same in both spaces
+        let local = Ray::new(
+            self.inverse.transform_point(*r.origin()),
+            self.inverse.transform_vector(*r.direction()),
+        );
+        if !self.object.hit(&local, int, rec) {
+            return false;
+        }
+        rec.p = self.transform.transform_point(rec.p);
+        // the normal keeps the side of the surface `set_face_normal` chose
+        // for the local ray, which is the same side for the world ray
+        rec.normal = Vector3::unit_vector(&self.transform.transform_normal(rec.normal));
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_samplable(&self) -> bool {
+        self.object.is_samplable()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3) -> f64 {
+        let local = Vector3::unit_vector(&self.inverse.transform_vector(*direction));
+        let pdf = self
+            .object
+            .pdf_value(&self.inverse.transform_point(*origin), &local);
+        // a linear map A takes solid angle around the unit direction d to
+        // |det A| / |A d|^3 times as much
+        let stretch = self.transform.transform_vector(local).length();
+        pdf * stretch * stretch * stretch / self.transform.determinant().abs()
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut Sampler) -> Vector3 {
+        let local = self
+            .object
+            .random(&self.inverse.transform_point(*origin), rng);
+        self.transform.transform_vector(local)
+    }
+}
+
+// The box around the eight transformed corners of `bbox`.
+fn transformed_bbox(bbox: &Aabb, transform: &Matrix4) -> Aabb {
+    let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for x in [bbox.x.min, bbox.x.max] {
+        for y in [bbox.y.min, bbox.y.max] {
+            for z in [bbox.z.min, bbox.z.max] {
+                let p = transform.transform_point(Point3::new(x, y, z));
+                min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+                max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+            }
+        }
+    }
+    Aabb::from_points(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        color::Color, hittable_list::HittableList, material::Lambertian, quad::make_box, random,
+        sphere::Sphere,
+    };
+
+    fn unit_sphere() -> Sphere {
+        Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        )
+    }
+
+    fn hits(object: &dyn Hittable, r: &Ray) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        object
+            .hit(r, Interval::new(0.001, f64::INFINITY), &mut rec)
+            .then_some(rec)
+    }
+
+    fn assert_near(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_translated_sphere() {
+        let sphere = Transformed::new(
+            unit_sphere(),
+            Matrix4::translation(Vector3::new(5.0, 0.0, 0.0)),
+        );
+        let r = Ray::new(Point3::new(5.0, 0.0, 4.0), Vector3::new(0.0, 0.0, -1.0));
+        let rec = hits(&sphere, &r).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9);
+        assert_near(rec.p, Point3::new(5.0, 0.0, 1.0));
+        assert_near(rec.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert!(rec.front_face);
+
+        let miss = Ray::new(Point3::new(0.0, 0.0, 4.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(hits(&sphere, &miss).is_none());
+    }
+
+    #[test]
+    fn test_scaled_sphere_normal_uses_inverse_transpose() {
+        // an ellipsoid twice as wide as it is tall
+        let ellipsoid =
+            Transformed::new(unit_sphere(), Matrix4::scaling(Vector3::new(2.0, 1.0, 1.0)));
+        // the point (sqrt 2, sqrt 2 / 2, 0) lies on it, where the surface
+        // normal is along (x / 4, y, 0)
+        let p = Point3::new(2f64.sqrt(), 2f64.sqrt() / 2.0, 0.0);
+        let origin = p * 3.0;
+        let rec = hits(&ellipsoid, &Ray::new(origin, p - origin)).unwrap();
+        assert_near(rec.p, p);
+        assert_near(
+            rec.normal,
+            Vector3::unit_vector(&Vector3::new(p.x / 4.0, p.y, 0.0)),
+        );
+    }
+
+    #[test]
+    fn test_rotated_box_bounding_box() {
+        let cube = Transformed::new(
+            make_box(
+                Point3::new(-1.0, -1.0, -1.0),
+                Point3::new(1.0, 1.0, 1.0),
+                Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+            ),
+            Matrix4::rotation(Vector3::new(0.0, 1.0, 0.0), 45.0),
+        );
+        let bbox = cube.bounding_box();
+        let half_diagonal = 2f64.sqrt();
+        assert!((bbox.x.max - half_diagonal).abs() < 1e-3);
+        assert!((bbox.z.min + half_diagonal).abs() < 1e-3);
+        assert!((bbox.y.max - 1.0).abs() < 1e-3);
+
+        // an edge now points at the viewer, with the faces at 45 degrees
+        // either side of it
+        let r = Ray::new(Point3::new(0.1, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let rec = hits(&cube, &r).unwrap();
+        assert!((rec.t - (5.0 - half_diagonal + 0.1)).abs() < 1e-9);
+        assert_near(
+            rec.normal,
+            Vector3::unit_vector(&Vector3::new(1.0, 0.0, 1.0)),
+        );
+    }
+
+    #[test]
+    fn test_instances_share_one_object() {
+        let sphere = Arc::new(unit_sphere());
+        let mut world = HittableList::new();
+        for x in [-3.0, 0.0, 3.0] {
+            world.add(Box::new(Transformed::new(
+                Arc::clone(&sphere),
+                Matrix4::translation(Vector3::new(x, 0.0, 0.0)),
+            )));
+        }
+        assert_eq!(Arc::strong_count(&sphere), 4);
+        for x in [-3.0, 0.0, 3.0] {
+            let r = Ray::new(Point3::new(x, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+            assert!((hits(&world, &r).unwrap().t - 4.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_mirrored_object_keeps_outward_normals() {
+        let sphere = Transformed::new(
+            unit_sphere(),
+            Matrix4::scaling(Vector3::new(-1.0, 1.0, 1.0)),
+        );
+        let r = Ray::new(Point3::new(3.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        let rec = hits(&sphere, &r).unwrap();
+        assert!(rec.front_face);
+        assert_near(rec.normal, Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_light_sampling_accounts_for_scale() {
+        // a unit sphere scaled by two samples like a sphere of radius two
+        let scaled = Transformed::new(
+            Sphere::new(
+                Point3::new(0.0, 0.0, 0.0),
+                1.0,
+                Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+            ),
+            Matrix4::translation(Vector3::new(0.0, 0.0, -10.0))
+                * Matrix4::scaling(Vector3::new(2.0, 2.0, 2.0)),
+        );
+        let plain = Sphere::new(
+            Point3::new(0.0, 0.0, -10.0),
+            2.0,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let mut rng = random::sampler(2, 0, 0);
+        for _ in 0..50 {
+            let direction = scaled.random(&origin, &mut rng);
+            assert!(hits(&plain, &Ray::new(origin, direction)).is_some());
+            let expected = plain.pdf_value(&origin, &direction);
+            assert!((scaled.pdf_value(&origin, &direction) - expected).abs() < 1e-9 * expected);
+        }
+    }
+}
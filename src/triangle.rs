@@ -0,0 +1,426 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    point::Point3,
+    progress,
+    random::{self, Sampler},
+    ray::Ray,
+    vector::Vector3,
+};
+
+pub struct Triangle {
+    pub vertices: [Point3; 3],
+    // per-vertex shading normals, the geometric normal is used without them
+    pub normals: Option<[Vector3; 3]>,
+    // per-vertex texture coordinates, the barycentrics are used without them
+    pub uvs: Option<[(f64, f64); 3]>,
+    pub mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: Arc<dyn Material>) -> Self {
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            uvs: None,
+            mat,
+            bbox: triangle_bbox(&[a, b, c]),
+        }
+    }
+
+    pub fn with_normals(mut self, normals: [Vector3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, int: Interval, rec: &mut HitRecord) -> bool {
+        progress::count_intersection_test();
+        let Some(hit) = intersect(&self.vertices, r, int) else {
+            return false;
+        };
+        set_hit_record(rec, r, &hit, &self.vertices, self.normals, self.uvs);
+        rec.mat = Some(Arc::clone(&self.mat));
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_samplable(&self) -> bool {
+        true
+    }
+
+    // Sampled uniformly by area, converted to solid angle.
+    fn pdf_value(&self, origin: &Point3, direction: &Vector3) -> f64 {
+        let r = Ray::new(*origin, *direction);
+        let Some(hit) = intersect(&self.vertices, &r, Interval::new(0.001, f64::INFINITY)) else {
+            return 0.0;
+        };
+        let [a, b, c] = self.vertices;
+        let normal = Vector3::cross(&(b - a), &(c - a));
+        let area = 0.5 * normal.length();
+        let distance_squared = hit.t * hit.t * direction.length_squared();
+        let cosine =
+            Vector3::dot(direction, &normal).abs() / (direction.length() * normal.length());
+        if cosine < 1e-12 {
+            return 0.0;
+        }
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: &Point3, rng: &mut Sampler) -> Vector3 {
+        let r1 = random::random_double(rng).sqrt();
+        let r2 = random::random_double(rng);
+        let [a, b, c] = self.vertices;
+        let point = a * (1.0 - r1) + b * (r1 * (1.0 - r2)) + c * (r1 * r2);
+        point - *origin
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TriangleHit {
+    pub t: f64,
+    // weights of the three vertices at the hit point, summing to one
+    pub barycentrics: [f64; 3],
+}
+
+pub fn triangle_bbox(vertices: &[Point3; 3]) -> Aabb {
+    let [a, b, c] = vertices;
+    Aabb::surrounding(&Aabb::from_points(*a, *b), &Aabb::from_points(*b, *c)).pad_to_minimums(1e-4)
+}
+
+// Watertight ray/triangle intersection (Woop, Benthin and Wald, 2013).
+//
+// The vertices are moved into a space where the ray starts at the origin and
+// points down +z, so the test reduces to 2D edge functions. Each edge function
+// only depends on the two vertices of its edge, and swapping them negates it
+// exactly, so neighbouring triangles agree on which side of a shared edge or
+// vertex a ray passes and no ray can slip between them.
+pub fn intersect(vertices: &[Point3; 3], r: &Ray, int: Interval) -> Option<TriangleHit> {
+    let dir = r.direction();
+    let org = r.origin();
+
+    // permute the axes so that z is the dominant direction component, and
+    // keep the winding by swapping x and y when it is negative
+    let (ax, ay, az) = (dir.x.abs(), dir.y.abs(), dir.z.abs());
+    let kz = if ax > ay && ax > az {
+        0
+    } else if ay > az {
+        1
+    } else {
+        2
+    };
+    let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // shear coefficients
+    let sx = dir[kx] / dir[kz];
+    let sy = dir[ky] / dir[kz];
+    let sz = 1.0 / dir[kz];
+
+    let a = vertices[0] - *org;
+    let b = vertices[1] - *org;
+    let c = vertices[2] - *org;
+
+    let (a_x, a_y) = (a[kx] - sx * a[kz], a[ky] - sy * a[kz]);
+    let (b_x, b_y) = (b[kx] - sx * b[kz], b[ky] - sy * b[kz]);
+    let (c_x, c_y) = (c[kx] - sx * c[kz], c[ky] - sy * c[kz]);
+
+    // scaled barycentrics, a hit on an edge or vertex gives exact zeros
+    let u = c_x * b_y - c_y * b_x;
+    let v = a_x * c_y - a_y * c_x;
+    let w = b_x * a_y - b_y * a_x;
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let t = (u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz]) / det;
+    if !int.surrounds(t) {
+        return None;
+    }
+
+    Some(TriangleHit {
+        t,
+        barycentrics: [u / det, v / det, w / det],
+    })
+}
+
+// Fills everything but the material from a hit on the given triangle.
+pub fn set_hit_record(
+    rec: &mut HitRecord,
+    r: &Ray,
+    hit: &TriangleHit,
+    vertices: &[Point3; 3],
+    normals: Option<[Vector3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+) {
+    let [b0, b1, b2] = hit.barycentrics;
+    let [p0, p1, p2] = vertices;
+
+    rec.t = hit.t;
+    rec.p = r.at(hit.t);
+
+    let outward_normal = Vector3::unit_vector(&Vector3::cross(&(*p1 - *p0), &(*p2 - *p0)));
+    rec.set_face_normal(r, &outward_normal);
+
+    if let Some([n0, n1, n2]) = normals {
+        let shading = n0 * b0 + n1 * b1 + n2 * b2;
+        if !shading.near_zero() {
+            let shading = Vector3::unit_vector(&shading);
+            rec.normal = if rec.front_face { shading } else { -shading };
+        }
+    }
+
+    (rec.u, rec.v) = match uvs {
+        Some([(u0, v0), (u1, v1), (u2, v2)]) => {
+            (b0 * u0 + b1 * u1 + b2 * u2, b0 * v0 + b1 * v1 + b2 * v2)
+        }
+        None => (b1, b2),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, material::Lambertian, random};
+
+    fn grey_triangle(a: Point3, b: Point3, c: Point3) -> Triangle {
+        Triangle::new(
+            a,
+            b,
+            c,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        )
+    }
+
+    fn unit_triangle() -> Triangle {
+        grey_triangle(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    fn towards(target: Point3) -> Ray {
+        let origin = Point3::new(0.2, 0.3, 5.0);
+        Ray::new(origin, target - origin)
+    }
+
+    fn hits(triangle: &Triangle, r: &Ray) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        triangle
+            .hit(r, Interval::new(0.001, f64::INFINITY), &mut rec)
+            .then_some(rec)
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_triangle_hit_inside() {
+        let triangle = unit_triangle();
+        let r = Ray::new(Point3::new(0.25, 0.25, 2.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let hit = intersect(&triangle.vertices, &r, Interval::new(0.0, f64::INFINITY)).unwrap();
+        assert_close(hit.t, 2.0);
+        assert_close(hit.barycentrics[0], 0.5);
+        assert_close(hit.barycentrics[1], 0.25);
+        assert_close(hit.barycentrics[2], 0.25);
+
+        let rec = hits(&triangle, &r).unwrap();
+        assert_close(rec.p.x, 0.25);
+        assert_close(rec.p.z, 0.0);
+        assert!(rec.front_face);
+        assert_close(rec.normal.z, 1.0);
+        assert_close(rec.u, 0.25);
+        assert_close(rec.v, 0.25);
+    }
+
+    #[test]
+    fn test_triangle_hit_miss() {
+        let triangle = unit_triangle();
+        let outside = Ray::new(Point3::new(0.6, 0.6, 2.0), Vector3::new(0.0, 0.0, -1.0));
+        let parallel = Ray::new(Point3::new(-1.0, 0.2, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let behind = Ray::new(Point3::new(0.2, 0.2, -2.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!(hits(&triangle, &outside).is_none());
+        assert!(hits(&triangle, &parallel).is_none());
+        assert!(hits(&triangle, &behind).is_none());
+    }
+
+    #[test]
+    fn test_triangle_hit_from_behind() {
+        let triangle = unit_triangle();
+        let r = Ray::new(Point3::new(0.25, 0.25, -2.0), Vector3::new(0.0, 0.0, 1.0));
+        let rec = hits(&triangle, &r).unwrap();
+
+        assert!(!rec.front_face);
+        assert_close(rec.normal.z, -1.0);
+    }
+
+    #[test]
+    fn test_triangle_hit_on_edges_and_vertices() {
+        let triangle = unit_triangle();
+        let [a, b, c] = triangle.vertices;
+
+        for (target, expected) in [
+            (a, [1.0, 0.0, 0.0]),
+            (b, [0.0, 1.0, 0.0]),
+            (c, [0.0, 0.0, 1.0]),
+            ((a + b) * 0.5, [0.5, 0.5, 0.0]),
+            ((b + c) * 0.5, [0.0, 0.5, 0.5]),
+            ((c + a) * 0.5, [0.5, 0.0, 0.5]),
+        ] {
+            let r = Ray::new(
+                target + Vector3::new(0.0, 0.0, 1.0),
+                Vector3::new(0.0, 0.0, -1.0),
+            );
+            let hit = intersect(&triangle.vertices, &r, Interval::new(0.0, f64::INFINITY))
+                .unwrap_or_else(|| panic!("missed {:?}", target));
+            for (weight, expected) in hit.barycentrics.iter().zip(expected) {
+                assert_close(*weight, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_triangle_shared_edges_are_watertight() {
+        // a skewed, tilted quad split along its diagonal
+        let origin = Point3::new(-0.31, 0.17, -1.3);
+        let axis_u = Vector3::new(1.44, -0.46, 0.6);
+        let axis_v = Vector3::new(-0.22, 0.9, 0.2);
+        let p = [
+            origin,
+            origin + axis_u,
+            origin + axis_u * 1.1 + axis_v * 1.3,
+            origin + axis_v,
+        ];
+        let triangles = [
+            grey_triangle(p[0], p[1], p[2]),
+            grey_triangle(p[0], p[2], p[3]),
+        ];
+        let mut rng = random::sampler(7, 0, 0);
+
+        for _ in 0..20_000 {
+            // aim at the shared diagonal from random directions on both sides
+            let s = random::random_range(&mut rng, 0.01, 0.99);
+            let target = p[0] + (p[2] - p[0]) * s;
+            let origin = target + Vector3::random_unit_vector(&mut rng) * 3.0;
+            let r = Ray::new(origin, target - origin);
+            assert!(
+                triangles.iter().any(|tri| hits(tri, &r).is_some()),
+                "ray through the shared edge at {:?} leaked",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_triangle_shared_vertex_is_watertight() {
+        // a tilted hexagon made of six triangles around a common centre
+        let center = Point3::new(0.3, -0.2, -2.0);
+        let axis_u = Vector3::new(0.8, 0.1, 0.3);
+        let axis_v = Vector3::new(-0.2, 0.9, 0.4);
+        let corner = |k: usize| {
+            let angle = k as f64 * std::f64::consts::PI / 3.0;
+            center + axis_u * angle.cos() + axis_v * angle.sin()
+        };
+        let fan: Vec<Triangle> = (0..6)
+            .map(|k| grey_triangle(center, corner(k), corner(k + 1)))
+            .collect();
+        let mut rng = random::sampler(11, 0, 0);
+
+        for _ in 0..20_000 {
+            let origin = center + Vector3::random_unit_vector(&mut rng) * 3.0;
+            let r = Ray::new(origin, center - origin);
+            assert!(
+                fan.iter().any(|tri| hits(tri, &r).is_some()),
+                "ray through the shared vertex from {:?} leaked",
+                origin
+            );
+        }
+    }
+
+    #[test]
+    fn test_triangle_interpolates_normals_and_uvs() {
+        let n = Vector3::new(0.0, 0.0, 1.0);
+        let tilted = Vector3::unit_vector(&Vector3::new(1.0, 0.0, 1.0));
+        let triangle = unit_triangle().with_normals([n, tilted, n]).with_uvs([
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (0.0, 1.0),
+        ]);
+
+        let r = Ray::new(Point3::new(0.5, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let rec = hits(&triangle, &r).unwrap();
+        let expected = Vector3::unit_vector(&(n * 0.5 + tilted * 0.5));
+        assert_close(rec.normal.x, expected.x);
+        assert_close(rec.normal.z, expected.z);
+        assert_close(rec.u, 0.5);
+        assert_close(rec.v, 0.0);
+
+        // the shading normal follows the side that was hit
+        let r = Ray::new(Point3::new(0.5, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        let rec = hits(&triangle, &r).unwrap();
+        assert_close(rec.normal.z, -expected.z);
+    }
+
+    #[test]
+    fn test_triangle_bounding_box_is_padded() {
+        let triangle = unit_triangle();
+        let bbox = triangle.bounding_box();
+
+        assert_eq!((bbox.x.min, bbox.x.max), (0.0, 1.0));
+        assert!(bbox.z.size() > 0.0);
+        assert!(bbox.hit(
+            &towards(Point3::new(0.1, 0.1, 0.0)),
+            Interval::new(0.0, f64::INFINITY)
+        ));
+    }
+
+    #[test]
+    fn test_triangle_light_sampling_density() {
+        // a right triangle of area 2 facing the origin from two units away
+        let triangle = grey_triangle(
+            Point3::new(0.0, 0.0, -2.0),
+            Point3::new(2.0, 0.0, -2.0),
+            Point3::new(0.0, 2.0, -2.0),
+        );
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let straight = Vector3::new(0.1, 0.1, -1.0);
+        let expected = (4.0 * straight.length_squared()) / ((1.0 / straight.length()) * 2.0);
+        assert!((triangle.pdf_value(&origin, &straight) - expected).abs() < 1e-9);
+        assert_eq!(
+            triangle.pdf_value(&origin, &Vector3::new(0.0, 0.0, 1.0)),
+            0.0
+        );
+
+        let mut rng = random::sampler(1, 0, 0);
+        for _ in 0..100 {
+            let direction = triangle.random(&origin, &mut rng);
+            assert!(triangle.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::ops::{Add, Div, Index, Mul, Neg, Sub};
 
-use rand::Rng;
+use crate::random::{self, Sampler};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Vector3 {
@@ -38,21 +38,27 @@ impl Vector3 {
         (vec1.x * vec2.x) + (vec1.y * vec2.y) + (vec1.z * vec2.z)
     }
 
-    pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn cross(u: &Vector3, v: &Vector3) -> Vector3 {
         Vector3 {
-            x: rng.gen(),
-            y: rng.gen(),
-            z: rng.gen(),
+            x: u.y * v.z - u.z * v.y,
+            y: u.z * v.x - u.x * v.z,
+            z: u.x * v.y - u.y * v.x,
         }
     }
 
-    pub fn random_range(min: f64, max: f64) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn random(rng: &mut Sampler) -> Self {
         Vector3 {
-            x: rng.gen_range(min..max),
-            y: rng.gen_range(min..max),
-            z: rng.gen_range(min..max),
+            x: random::random_double(rng),
+            y: random::random_double(rng),
+            z: random::random_double(rng),
+        }
+    }
+
+    pub fn random_range(rng: &mut Sampler, min: f64, max: f64) -> Self {
+        Vector3 {
+            x: random::random_range(rng, min, max),
+            y: random::random_range(rng, min, max),
+            z: random::random_range(rng, min, max),
         }
     }
 
@@ -60,26 +66,58 @@ impl Vector3 {
         *v / v.length()
     }
 
-    pub fn random_in_unit_sphere() -> Vector3 {
+    pub fn random_in_unit_sphere(rng: &mut Sampler) -> Vector3 {
+        loop {
+            let p = Vector3::random_range(rng, -1.0, 1.0);
+            if p.length_squared() < 1.0 {
+                return p;
+            }
+        }
+    }
+
+    pub fn random_in_unit_disk(rng: &mut Sampler) -> Vector3 {
         loop {
-            let p = Vector3::random_range(-1.0, 1.0);
+            let p = Vector3::new(
+                random::random_range(rng, -1.0, 1.0),
+                random::random_range(rng, -1.0, 1.0),
+                0.0,
+            );
             if p.length_squared() < 1.0 {
                 return p;
             }
         }
     }
 
-    pub fn random_unit_vector() -> Vector3 {
-        Vector3::unit_vector(&Vector3::random_in_unit_sphere())
+    pub fn random_unit_vector(rng: &mut Sampler) -> Vector3 {
+        Vector3::unit_vector(&Vector3::random_in_unit_sphere(rng))
+    }
+
+    pub fn near_zero(&self) -> bool {
+        let s = 1e-8;
+        self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
+    }
+
+    pub fn reflect(v: &Vector3, n: &Vector3) -> Vector3 {
+        *v - 2.0 * Vector3::dot(v, n) * *n
+    }
+
+    pub fn refract(uv: &Vector3, n: &Vector3, etai_over_etat: f64) -> Vector3 {
+        let cos_theta = Vector3::dot(&-*uv, n).min(1.0);
+        let r_out_perp = etai_over_etat * (*uv + cos_theta * *n);
+        let r_out_parallel = -(1.0 - r_out_perp.length_squared()).abs().sqrt() * *n;
+        r_out_perp + r_out_parallel
     }
 
-    pub fn random_on_hemisphere(normal: &Vector3) -> Vector3 {
-        let on_unit_sphere = Vector3::random_unit_vector();
-        if Vector3::dot(&on_unit_sphere, normal) > 0.0 {
-            on_unit_sphere
+    // Two unit vectors completing the unit vector `n` to an orthonormal basis.
+    pub fn orthonormal_basis(n: &Vector3) -> (Vector3, Vector3) {
+        let a = if n.x.abs() > 0.9 {
+            Vector3::new(0.0, 1.0, 0.0)
         } else {
-            -on_unit_sphere
-        }
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vector3::unit_vector(&Vector3::cross(n, &a));
+        let u = Vector3::cross(n, &v);
+        (u, v)
     }
 }
 
@@ -192,6 +230,19 @@ impl Mul<f64> for Vector3 {
     }
 }
 
+impl Index<usize> for Vector3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &Self::Output {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector3 index out of range: {}", axis),
+        }
+    }
+}
+
 impl Neg for Vector3 {
     type Output = Self;
 
@@ -368,6 +419,73 @@ mod tests {
         assert_eq!(neg.z, -2.0);
     }
 
+    #[test]
+    fn test_vector3_cross() {
+        let x = Vector3::new(1.0, 0.0, 0.0);
+        let y = Vector3::new(0.0, 1.0, 0.0);
+
+        assert_eq!(Vector3::cross(&x, &y), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(Vector3::cross(&y, &x), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            Vector3::cross(&Vector3::new(1.0, 2.0, 3.0), &Vector3::new(4.0, 5.0, 6.0)),
+            Vector3::new(-3.0, 6.0, -3.0)
+        );
+    }
+
+    #[test]
+    fn test_vector3_near_zero() {
+        assert!(Vector3::new(1e-9, -1e-9, 0.0).near_zero());
+        assert!(!Vector3::new(1e-9, 1e-3, 0.0).near_zero());
+    }
+
+    #[test]
+    fn test_vector3_reflect() {
+        let v = Vector3::new(1.0, -1.0, 0.0);
+        let n = Vector3::new(0.0, 1.0, 0.0);
+
+        let reflected = Vector3::reflect(&v, &n);
+
+        assert_eq!(reflected, Vector3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_vector3_refract_same_medium() {
+        let uv = Vector3::unit(&Vector3::new(1.0, -1.0, 0.0));
+        let n = Vector3::new(0.0, 1.0, 0.0);
+
+        let refracted = Vector3::refract(&uv, &n, 1.0);
+
+        assert!((refracted - uv).near_zero());
+    }
+
+    #[test]
+    fn test_vector3_random_unit_vector_is_seeded() {
+        let a = Vector3::random_unit_vector(&mut random::sampler(9, 0, 0));
+        let b = Vector3::random_unit_vector(&mut random::sampler(9, 0, 0));
+
+        assert_eq!(a, b);
+        assert!((a.length() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_vector3_random_in_unit_disk() {
+        let mut rng = random::sampler(5, 0, 0);
+        for _ in 0..100 {
+            let p = Vector3::random_in_unit_disk(&mut rng);
+            assert!(p.length_squared() < 1.0);
+            assert_eq!(p.z, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_vector3_index() {
+        let v = Vector3::new(1.0, 2.0, 3.0);
+
+        assert_eq!(v[0], 1.0);
+        assert_eq!(v[1], 2.0);
+        assert_eq!(v[2], 3.0);
+    }
+
     #[test]
     fn test_vector3_scalar_multiplication() {
         let v = Vector3::new(1.0, 2.0, 3.0);
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use raytracing::{
+    BvhNode, Camera, CameraBuilder, Color, Dielectric, Framebuffer, HittableList, Lambertian,
+    Metal, Point3, Sphere, TransferFunction, Vector3,
+};
+
+fn world() -> BvhNode {
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0))),
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, 0.0, -1.0),
+        0.5,
+        Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5))),
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(-1.0, 0.0, -1.0),
+        0.5,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.add(Box::new(Sphere::new(
+        Point3::new(1.0, 0.0, -1.0),
+        0.5,
+        Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1)),
+    )));
+    BvhNode::new(world)
+}
+
+fn camera() -> CameraBuilder {
+    Camera::builder()
+        .aspect_ratio(2.0)
+        .image_width(32)
+        .samples_per_pixel(4)
+        .max_depth(8)
+        .lookfrom(Point3::new(0.0, 0.0, 1.0))
+        .lookat(Point3::new(0.0, 0.0, -1.0))
+        .vup(Vector3::new(0.0, 1.0, 0.0))
+        .vfov(90.0)
+        .seed(1)
+}
+
+fn bits(image: &Framebuffer) -> Vec<[u64; 3]> {
+    image
+        .pixels
+        .iter()
+        .map(|c| [c.red.to_bits(), c.green.to_bits(), c.blue.to_bits()])
+        .collect()
+}
+
+#[test]
+fn test_render_to_buffer_dimensions() {
+    let camera = camera().build().unwrap();
+    let image = camera.render_to_buffer(&world(), &HittableList::new());
+
+    assert_eq!((camera.image_width(), camera.image_height()), (32, 16));
+    assert_eq!((image.width, image.height), (32, 16));
+    assert_eq!(image.pixels.len(), 32 * 16);
+    assert!(image
+        .pixels
+        .iter()
+        .all(|c| c.red >= 0.0 && c.green >= 0.0 && c.blue >= 0.0));
+}
+
+#[test]
+fn test_render_to_buffer_is_reproducible() {
+    let world = world();
+    let first = camera()
+        .threads(1)
+        .build()
+        .unwrap()
+        .render_to_buffer(&world, &HittableList::new());
+    let second = camera()
+        .threads(3)
+        .build()
+        .unwrap()
+        .render_to_buffer(&world, &HittableList::new());
+    assert_eq!(bits(&first), bits(&second));
+}
+
+#[test]
+fn test_render_to_buffer_sees_the_scene() {
+    let image = camera()
+        .build()
+        .unwrap()
+        .render_to_buffer(&world(), &HittableList::new());
+
+    // the top row looks at the sky, the centre at the blue diffuse sphere
+    let sky = image.get(16, 0);
+    let sphere = image.get(16, 8);
+    assert!(sky.blue > sky.red);
+    assert!(sphere.blue > sphere.red && sphere.red < sky.red);
+}
+
+#[test]
+fn test_rgb8_uses_camera_transfer_function() {
+    let camera = camera()
+        .transfer_function(TransferFunction::Linear)
+        .build()
+        .unwrap();
+    let image = camera.render_to_buffer(&world(), &HittableList::new());
+    let rgb = image.to_rgb8(camera.transfer_function());
+
+    assert_eq!(rgb.len(), image.pixels.len());
+    for (encoded, linear) in rgb.iter().zip(&image.pixels) {
+        let expected = (256.0 * linear.red.clamp(0.0, 0.999)) as u8;
+        assert_eq!(encoded[0], expected);
+    }
+}
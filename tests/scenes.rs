@@ -0,0 +1,300 @@
+use std::{fs, path::Path};
+
+use raytracing::{
+    render::write_image, scene_file, scenes, Background, BvhNode, Color, Framebuffer, HitRecord,
+    Hittable, Interval, Point3, Ray, RenderSettings, TransferFunction, Vector3,
+};
+
+#[test]
+fn test_builtin_scenes_render() {
+    for name in scenes::BUILTIN_SCENES {
+        let scene = scenes::builtin(name).unwrap();
+        let settings = RenderSettings {
+            width: Some(16),
+            samples_per_pixel: Some(1),
+            max_depth: Some(4),
+            seed: Some(3),
+            ..RenderSettings::default()
+        };
+        let camera = settings.apply(scene.camera).build().unwrap();
+        let image = camera.render_to_buffer(&BvhNode::new(scene.world), &scene.lights);
+        assert_eq!((image.width, image.height), (16, 9), "scene {}", name);
+    }
+}
+
+#[test]
+fn test_scene_file_settings_yield_to_overrides() {
+    let scene = scene_file::load(Path::new("scenes/three_spheres.json")).unwrap();
+    let overrides = RenderSettings {
+        width: Some(40),
+        ..RenderSettings::default()
+    };
+    let settings = overrides.or(scene.settings);
+
+    assert_eq!(settings.width(), 40);
+    assert_eq!(settings.samples_per_pixel, Some(64));
+    assert_eq!(settings.output(), Path::new("three_spheres.png"));
+}
+
+#[test]
+fn test_scene_file_render_writes_image() {
+    let dir = std::env::temp_dir().join(format!("raytracing-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let scene_path = dir.join("scene.json");
+    fs::write(
+        &scene_path,
+        r#"{
+            "render": { "width": 8, "aspect": 1, "spp": 1, "seed": 5 },
+            "objects": [
+                { "type": "sphere", "center": [0, 0, -1], "radius": 0.5,
+                  "material": { "type": "lambertian", "albedo": [0.5, 0.5, 0.5] } }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let scene = scene_file::load(&scene_path).unwrap();
+    let camera = scene.settings.apply(scene.camera).build().unwrap();
+    let output = dir.join("scene.ppm");
+    camera
+        .render(&BvhNode::new(scene.world), &scene.lights, &output)
+        .unwrap();
+
+    let written = fs::read(&output).unwrap();
+    assert!(written.starts_with(b"P6\n8 8\n255\n"));
+    assert_eq!(written.len(), 11 + 8 * 8 * 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scene_file_errors_name_file_and_line() {
+    let err = scene_file::parse(
+        "{\n  \"objects\": [\n    { \"type\": \"torus\" }\n  ]\n}",
+        Path::new("bad.json"),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err.line, Some(3));
+    assert_eq!(
+        err.to_string(),
+        "bad.json:3: unknown object type 'torus', expected one of: sphere, triangle, mesh"
+    );
+}
+
+#[test]
+fn test_scene_file_loads_obj_mesh() {
+    let dir = std::env::temp_dir().join(format!("raytracing-mesh-{}", std::process::id()));
+    fs::create_dir_all(dir.join("models")).unwrap();
+    fs::write(
+        dir.join("models/quad.obj"),
+        "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\nvn 0 0 1\nf 1//1 2//1 3//1 4//1\n",
+    )
+    .unwrap();
+    fs::write(dir.join("models/broken.obj"), "v 0 0 0\nf 1 2 3\n").unwrap();
+
+    let scene = scene_file::parse(
+        r#"{"objects": [{"type": "mesh", "file": "models/quad.obj"}]}"#,
+        &dir.join("scene.json"),
+    )
+    .unwrap();
+    let world = BvhNode::new(scene.world);
+    let r = Ray::new(Point3::new(0.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    assert!(world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+    assert!((rec.t - 2.0).abs() < 1e-9);
+    assert_eq!(rec.normal, Vector3::new(0.0, 0.0, 1.0));
+
+    let err = scene_file::parse(
+        "{\"objects\": [\n{\"type\": \"mesh\",\n \"file\": \"models/broken.obj\"}]}",
+        &dir.join("scene.json"),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err.line, Some(3));
+    assert!(err
+        .message
+        .ends_with("broken.obj:2: vertex index 2 out of range, 1 defined so far"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scene_file_loads_image_texture() {
+    let dir = std::env::temp_dir().join(format!("raytracing-texture-{}", std::process::id()));
+    fs::create_dir_all(dir.join("textures")).unwrap();
+    // one red and one green pixel
+    fs::write(
+        dir.join("textures/flag.ppm"),
+        "P3\n2 1\n255\n255 0 0 0 255 0\n",
+    )
+    .unwrap();
+
+    let scene = scene_file::parse(
+        r#"{"objects": [{"type": "triangle", "vertices": [[0, 0, 0], [1, 0, 0], [0, 1, 0]],
+            "uvs": [[0.75, 0.5], [0.75, 0.5], [0.75, 0.5]],
+            "material": {"type": "diffuse_light",
+                         "emit": {"type": "image", "file": "textures/flag.ppm"}}}]}"#,
+        &dir.join("scene.json"),
+    )
+    .unwrap();
+    assert_eq!(scene.lights.into_objects().len(), 1);
+    let r = Ray::new(Point3::new(0.2, 0.2, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    assert!(scene
+        .world
+        .hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+    let emitted = rec.mat.unwrap().emitted(rec.u, rec.v, &rec.p);
+    assert!((emitted.green - 1.0).abs() < 1e-9 && emitted.red.abs() < 1e-9);
+
+    let err = scene_file::parse(
+        "{\"objects\": [{\"type\": \"sphere\", \"center\": [0, 0, 0], \"radius\": 1,\n\"material\": {\"type\": \"lambertian\", \"albedo\":\n{\"type\": \"image\", \"file\": \"textures/missing.png\"}}}]}",
+        &dir.join("scene.json"),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err.line, Some(3));
+    assert!(err.message.contains("missing.png"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scene_file_loads_environment_map() {
+    let dir = std::env::temp_dir().join(format!("raytracing-environment-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    // a bright upper half over a dark lower one
+    let mut sky = Framebuffer::new(8, 4);
+    for y in 0..4 {
+        for x in 0..8 {
+            let value = if y < 2 { 2.0 } else { 0.25 };
+            sky.set(x, y, Color::new(value, value, value));
+        }
+    }
+    write_image(&dir.join("sky.pfm"), &sky, TransferFunction::Linear).unwrap();
+
+    let scene = scene_file::parse(
+        r#"{"camera": {"background": {"type": "environment", "file": "sky.pfm",
+                                      "rotation": 45, "intensity": 3}},
+            "objects": []}"#,
+        &dir.join("scene.json"),
+    )
+    .unwrap();
+    let camera = scene.camera.build().unwrap();
+    let up = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+    let down = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+    let background = camera.background();
+    assert_eq!(background.color(&up), Color::new(6.0, 6.0, 6.0));
+    assert_eq!(background.color(&down), Color::new(0.75, 0.75, 0.75));
+    let Background::Environment(map) = background else {
+        panic!("expected an environment background");
+    };
+    assert_eq!(map.rotation(), 45.0);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scene_file_loads_ply_mesh() {
+    let dir = std::env::temp_dir().join(format!("raytracing-ply-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut ply = b"ply\nformat binary_little_endian 1.0\nelement vertex 4\n\
+property float x\nproperty float y\nproperty float z\n\
+element face 1\nproperty list uchar int vertex_indices\nend_header\n"
+        .to_vec();
+    for [x, y] in [[-1.0f32, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]] {
+        for value in [x, y, -1.0] {
+            ply.extend(value.to_le_bytes());
+        }
+    }
+    ply.push(4);
+    for index in 0..4i32 {
+        ply.extend(index.to_le_bytes());
+    }
+    fs::write(dir.join("quad.ply"), ply).unwrap();
+
+    let scene = scene_file::parse(
+        r#"{"objects": [{"type": "mesh", "file": "quad.ply"}]}"#,
+        &dir.join("scene.json"),
+    )
+    .unwrap();
+    let world = BvhNode::new(scene.world);
+    let r = Ray::new(Point3::new(-0.5, 0.5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    assert!(world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+    assert!((rec.t - 2.0).abs() < 1e-9);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scene_file_loads_gltf_asset_with_camera() {
+    let dir = std::env::temp_dir().join(format!("raytracing-gltf-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut bin = Vec::new();
+    for value in [-1.0f32, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0] {
+        bin.extend(value.to_le_bytes());
+    }
+    fs::write(dir.join("triangle.bin"), bin).unwrap();
+    fs::write(
+        dir.join("triangle.gltf"),
+        r#"{
+  "asset": {"version": "2.0"},
+  "buffers": [{"byteLength": 36, "uri": "triangle.bin"}],
+  "bufferViews": [{"buffer": 0, "byteLength": 36}],
+  "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}],
+  "meshes": [{"primitives": [{"attributes": {"POSITION": 0}}]}],
+  "cameras": [{"type": "perspective", "perspective": {"yfov": 0.8, "aspectRatio": 2.0}}],
+  "scene": 0,
+  "scenes": [{"nodes": [0, 1]}],
+  "nodes": [{"mesh": 0}, {"camera": 0, "translation": [0, 0, 4]}]
+}"#,
+    )
+    .unwrap();
+
+    let scene = scene_file::load(&dir.join("triangle.gltf")).unwrap();
+    assert_eq!(scene.settings.aspect_ratio, Some(2.0));
+    let settings = RenderSettings {
+        width: Some(32),
+        samples_per_pixel: Some(1),
+        ..RenderSettings::default()
+    }
+    .or(scene.settings);
+    let camera = settings.apply(scene.camera).build().unwrap();
+    assert_eq!((camera.image_width(), camera.image_height()), (32, 16));
+
+    let world = BvhNode::new(scene.world);
+    let r = Ray::new(Point3::new(0.0, 0.0, 4.0), Vector3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    assert!(world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+    assert!((rec.t - 4.0).abs() < 1e-9);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cornell_box_is_lit_by_its_light() {
+    let scene = scenes::builtin("cornell").unwrap();
+    let settings = RenderSettings {
+        width: Some(24),
+        samples_per_pixel: Some(8),
+        max_depth: Some(8),
+        seed: Some(1),
+        ..RenderSettings::default()
+    }
+    .or(scene.settings);
+    let camera = settings.apply(scene.camera).build().unwrap();
+    let image = camera.render_to_buffer(&BvhNode::new(scene.world), &scene.lights);
+    assert_eq!((image.width, image.height), (24, 24));
+
+    let luminance = |x, y| {
+        let c = image.get(x, y);
+        (c.red + c.green + c.blue) / 3.0
+    };
+    let mut brightest: f64 = 0.0;
+    let mut total = 0.0;
+    for y in 0..24 {
+        for x in 0..24 {
+            brightest = brightest.max(luminance(x, y));
+            total += luminance(x, y);
+        }
+    }
+    // the light panel is seen directly, and the walls catch some of it while
+    // the black background adds nothing
+    assert!(brightest >= 15.0 - 1e-9, "brightest pixel {}", brightest);
+    assert!(total / 576.0 > 0.05);
+}